@@ -45,15 +45,10 @@ fn main () {
             Ok((_size, src)) => {
                 let (_header, payload) = deserialize_lifx_packet(&buf).unwrap();
 
-                match payload {
-                    Message::Service { service, port: _ } => {
-                        if service == 1 {
-                            println!("Got UDP Service advertisement from {}", src);
-
-                            light_addresses.insert(src);
-                        }
-                    },
-                    _ => {}
+                if let Message::Service { service: 1, port: _ } = payload {
+                    println!("Got UDP Service advertisement from {}", src);
+
+                    light_addresses.insert(src);
                 }
             }
             Err(e) => {
@@ -77,11 +72,8 @@ fn main () {
             Ok((_size, src)) => {
                 let (_header, payload) = deserialize_lifx_packet(&buf).unwrap();
 
-                match payload {
-                    Message::Label { label } => {
-                        println!("Got label from {}: {}", src, label);
-                    },
-                    _ => {}
+                if let Message::Label { label } = payload {
+                    println!("Got label from {}: {}", src, label);
                 }
             }
             Err(e) => {
@@ -0,0 +1,122 @@
+// Regenerates src/products/catalogue.rs from a copy of https://github.com/LIFX/products/blob/master/products.json
+//
+//     cargo run --example generate_products -- products.json [src/products/catalogue.rs]
+
+use std::{env, fmt::Write, fs, process};
+
+use serde_json::Value;
+
+const FLAGS: [&str; 9] = ["hev", "color", "chain", "matrix", "relays", "buttons", "infrared", "multizone", "extended_multizone"];
+
+fn temperature_range(value: &Value) -> Option<String> {
+    let range = value.as_array()?;
+    Some(format!("TemperatureRange {{ min: {}, max: {} }}", range.first()?.as_u64()?, range.get(1)?.as_u64()?))
+}
+
+/// A `Features` literal listing only what the product has.
+fn features_literal(features: &Value) -> String {
+    let mut fields: Vec<String> =
+        FLAGS.iter().filter(|flag| features[**flag].as_bool() == Some(true)).map(|flag| format!("{}: true", flag)).collect();
+
+    if let Some(range) = temperature_range(&features["temperature_range"]) {
+        fields.push(format!("temperature_range: Some({})", range));
+    }
+
+    literal("Features", &fields)
+}
+
+/// A `FeatureUpgrade` literal listing only what the upgrade changes.
+fn upgrade_literal(features: &Value) -> String {
+    let mut fields: Vec<String> = FLAGS
+        .iter()
+        .filter_map(|flag| Some(format!("{}: Some({})", flag, features.get(*flag)?.as_bool()?)))
+        .collect();
+
+    if let Some(range) = temperature_range(&features["temperature_range"]) {
+        fields.push(format!("temperature_range: Some({})", range));
+    }
+
+    literal("FeatureUpgrade", &fields)
+}
+
+fn literal(name: &str, fields: &[String]) -> String {
+    if fields.is_empty() {
+        format!("{}::NONE", name)
+    } else {
+        format!("{} {{ {}, ..{}::NONE }}", name, fields.join(", "), name)
+    }
+}
+
+fn generate(catalogue: &Value) -> Result<String, String> {
+    let mut output = String::new();
+    output.push_str("// Generated by `cargo run --example generate_products`. Do not edit by hand.\n");
+    output.push_str("use super::{FeatureUpgrade, Features, Product, TemperatureRange, Upgrade};\n\n");
+    output.push_str("pub(super) static PRODUCTS: &[Product] = &[\n");
+
+    for vendor in catalogue.as_array().ok_or("expected a list of vendors")? {
+        let vid = vendor["vid"].as_u64().ok_or("vendor without a vid")?;
+        let defaults = &vendor["defaults"];
+
+        for product in vendor["products"].as_array().ok_or("vendor without products")? {
+            let pid = product["pid"].as_u64().ok_or("product without a pid")?;
+            let name = product["name"].as_str().ok_or("product without a name")?;
+
+            // Products only list the features that differ from their vendor's defaults
+            let mut features = defaults.clone();
+            if let (Some(features), Some(overrides)) = (features.as_object_mut(), product["features"].as_object()) {
+                features.extend(overrides.clone());
+            }
+
+            let mut upgrades = Vec::new();
+            for upgrade in product["upgrades"].as_array().into_iter().flatten() {
+                upgrades.push(format!(
+                    "Upgrade {{ major: {}, minor: {}, features: {} }}",
+                    upgrade["major"].as_u64().ok_or("upgrade without a major version")?,
+                    upgrade["minor"].as_u64().ok_or("upgrade without a minor version")?,
+                    upgrade_literal(&upgrade["features"]),
+                ));
+            }
+
+            writeln!(output, "    Product {{").unwrap();
+            writeln!(output, "        vendor: {},", vid).unwrap();
+            writeln!(output, "        product: {},", pid).unwrap();
+            writeln!(output, "        name: {:?},", name).unwrap();
+            writeln!(output, "        features: {},", features_literal(&features)).unwrap();
+            if upgrades.is_empty() {
+                writeln!(output, "        upgrades: &[],").unwrap();
+            } else {
+                writeln!(output, "        upgrades: &[").unwrap();
+                for upgrade in upgrades {
+                    writeln!(output, "            {},", upgrade).unwrap();
+                }
+                writeln!(output, "        ],").unwrap();
+            }
+            writeln!(output, "    }},").unwrap();
+        }
+    }
+
+    output.push_str("];\n");
+    Ok(output)
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let (Some(input), output) = (args.next(), args.next()) else {
+        eprintln!("usage: generate_products <products.json> [output.rs]");
+        process::exit(2);
+    };
+    let output = output.unwrap_or_else(|| String::from("src/products/catalogue.rs"));
+
+    let catalogue = fs::read_to_string(&input)
+        .map_err(|err| err.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|err| err.to_string()))
+        .and_then(|catalogue| generate(&catalogue));
+
+    match catalogue.and_then(|code| fs::write(&output, code).map_err(|err| err.to_string())) {
+        Ok(()) => println!("Wrote {}", output),
+        Err(err) => {
+            eprintln!("Failed to generate {} from {}: {}", output, input, err);
+            process::exit(1);
+        }
+    }
+}
@@ -1,24 +1,127 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use syn::{parse_macro_input, Data, DeriveInput, Fields};
 
-#[proc_macro_derive(LifxPayload, attributes(packet_number, size))]
+// Everything generated for a single variant, so that the arms can be emitted for any enum containing it
+struct VariantCode {
+    ident: syn::Ident,
+    definition: proc_macro2::TokenStream,
+    // `{ field, .. }` destructuring every field, or nothing for unit variants
+    fields: proc_macro2::TokenStream,
+    serialization: proc_macro2::TokenStream,
+    // None for the #[unknown] variant, which is the fallback of every match instead
+    deserialization: Option<proc_macro2::TokenStream>,
+    size: proc_macro2::TokenStream,
+    packet_number: proc_macro2::TokenStream,
+    number: Option<u16>,
+    name: proc_macro2::TokenStream,
+    responses: proc_macro2::TokenStream,
+    is_unknown: bool,
+    is_request: bool,
+    is_response: bool,
+}
+
+#[proc_macro_derive(LifxPayload, attributes(packet_number, size, unknown, response, direction, directions))]
 pub fn from_bytes_derive(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
 
     let name = input.ident;
+    let visibility = input.vis;
+    let ref_name = format_ident!("{}Ref", name);
+    let ref_doc = format!("Borrowed view of a [`{}`], with string and byte array fields pointing into the receive buffer.", name);
+    let directions = parse_directions(&input.attrs);
     let data = match input.data {
         Data::Enum(data) => data,
         _ => panic!("LifxPayload can only be derived for enums"),
     };
 
-    let mut serialization = Vec::<proc_macro2::TokenStream>::new();
-    let mut deserialization = Vec::<proc_macro2::TokenStream>::new();
-    let mut map_variant_to_packet_number = Vec::<proc_macro2::TokenStream>::new();
-    let mut map_variant_to_size = Vec::<proc_macro2::TokenStream>::new();
+    let mut variants = Vec::<VariantCode>::new();
+
+    let mut ref_variants = Vec::<proc_macro2::TokenStream>::new();
+    let mut ref_serialization = Vec::<proc_macro2::TokenStream>::new();
+    let mut ref_deserialization = Vec::<proc_macro2::TokenStream>::new();
+    let mut ref_to_owned = Vec::<proc_macro2::TokenStream>::new();
+
+    let mut schemas = Vec::<proc_macro2::TokenStream>::new();
+    let mut map_packet_number_to_responses = Vec::<proc_macro2::TokenStream>::new();
+
+    // Variants other variants are answered by get a standalone struct, see `response_code`
+    let mut response_packet_numbers = Vec::<u16>::new();
+    let mut variant_layouts = Vec::<(&syn::Variant, u16)>::new();
+
+    let mut unknown_ref_deserialization = quote! {
+        _ => Err(lifx_serialization::LifxDeserializationError::InvalidPacketNumber(packet_number)),
+    };
 
     for message in data.variants.iter() {
         let variant_name = &message.ident;
+        let variant_name_string = variant_name.to_string();
+        let direction = parse_direction(message);
+
+        // The #[unknown] variant catches every packet number without a variant of its own,
+        // keeping the raw payload so it can be re-serialized unchanged.
+        if message.attrs.iter().any(|attr| attr.path().is_ident("unknown")) {
+            let field_names = match &message.fields {
+                Fields::Named(fields) => fields.named.iter().map(|f| f.ident.as_ref().unwrap().to_string()).collect::<Vec<_>>(),
+                _ => Vec::new(),
+            };
+
+            if field_names != ["packet_number", "payload"] {
+                panic!("The unknown variant must have exactly the fields `packet_number` and `payload`");
+            }
+
+            unknown_ref_deserialization = quote! {
+                _ => Ok(#ref_name::#variant_name {
+                    packet_number,
+                    payload: bytes,
+                }),
+            };
+
+            ref_variants.push(quote! {
+                #variant_name { packet_number: u16, payload: &'a [u8] }
+            });
+
+            ref_serialization.push(quote! {
+                #ref_name::#variant_name { payload, .. } => {
+                    buffer[..payload.len()].copy_from_slice(payload);
+                    payload.len()
+                }
+            });
+
+            ref_to_owned.push(quote! {
+                #ref_name::#variant_name { packet_number, payload } => {
+                    Ok(#name::#variant_name {
+                        packet_number,
+                        payload: lifx_serialization::deserialize_bytes(payload)?,
+                    })
+                }
+            });
+
+            variants.push(VariantCode {
+                ident: variant_name.clone(),
+                definition: variant_definition(message),
+                fields: quote! { { packet_number, payload } },
+                serialization: quote! {
+                    #variant_name { payload, .. } => {
+                        buffer[..payload.len()].copy_from_slice(payload);
+                        payload.len()
+                    }
+                },
+                deserialization: None,
+                size: quote! { #variant_name { payload, .. } => payload.len() },
+                packet_number: quote! { #variant_name { packet_number, .. } => *packet_number },
+                number: None,
+                name: quote! { #variant_name { .. } => #variant_name_string },
+                responses: quote! { #variant_name { .. } => &[] },
+                is_unknown: true,
+                // Packets from newer firmware may travel either way
+                is_request: true,
+                is_response: true,
+            });
+
+            continue;
+        }
+
         let packet_number: u16 = message.attrs.iter().find_map(|attr| {
             if attr.meta.path().is_ident("packet_number") {
                 let lit: syn::LitInt = attr.parse_args().expect("Packet number must be a u16");
@@ -27,16 +130,41 @@ pub fn from_bytes_derive(input: TokenStream) -> TokenStream {
             None
         }).expect("Packet number is required for each variant.");
 
+        let responses: Vec<u16> = message.attrs.iter().filter(|attr| attr.path().is_ident("response")).flat_map(|attr| {
+            let lits = attr.parse_args_with(syn::punctuated::Punctuated::<syn::LitInt, syn::Token![,]>::parse_terminated).expect("Responses must be a list of u16 packet numbers");
+            lits.iter().map(|lit| lit.base10_parse::<u16>().expect("Responses must be a list of u16 packet numbers")).collect::<Vec<_>>()
+        }).collect();
+
+        response_packet_numbers.extend(responses.iter().copied());
+        variant_layouts.push((message, packet_number));
+
         let mut variant_current_size: usize = 0;
+        let mut field_schemas = Vec::<proc_macro2::TokenStream>::new();
 
-        match &message.fields {
+        let (serialization, deserialization) = match &message.fields {
             Fields::Named(fields) => {
                 let mut variant_field_serialization = Vec::<proc_macro2::TokenStream>::new();
                 let mut variant_field_deserialization = Vec::<proc_macro2::TokenStream>::new();
+                let mut variant_ref_fields = Vec::<proc_macro2::TokenStream>::new();
+                let mut variant_ref_serialization = Vec::<proc_macro2::TokenStream>::new();
+                let mut variant_ref_deserialization = Vec::<proc_macro2::TokenStream>::new();
+                let mut variant_ref_to_owned = Vec::<proc_macro2::TokenStream>::new();
 
                 for field in fields.named.iter() {
                     let field_name = field.ident.as_ref().unwrap();
-                    let (field_serialization, field_deserialization, field_size) = generate_field_code(field, variant_current_size);
+                    let field_code = generate_field_code(field, variant_current_size);
+
+                    let FieldCode { serialization: field_serialization, deserialization: field_deserialization, size: field_size, ty: field_type, .. } = &field_code;
+
+                    let field_name_string = field_name.to_string();
+                    field_schemas.push(quote! {
+                        lifx_serialization::FieldSchema {
+                            name: #field_name_string,
+                            offset: #variant_current_size,
+                            size: #field_size,
+                            ty: #field_type,
+                        }
+                    });
 
                     variant_current_size += field_size;
 
@@ -48,88 +176,543 @@ pub fn from_bytes_derive(input: TokenStream) -> TokenStream {
                     variant_field_deserialization.push(quote! {
                         #field_name: #field_deserialization
                     });
+
+                    let FieldCode { ref_type, ref_serialization: field_ref_serialization, ref_deserialization: field_ref_deserialization, ref_to_owned: field_ref_to_owned, .. } = &field_code;
+
+                    variant_ref_fields.push(quote! {
+                        #field_name: #ref_type
+                    });
+
+                    variant_ref_serialization.push(quote! {
+                        #field_ref_serialization
+                        buffer_index += #field_size;
+                    });
+
+                    variant_ref_deserialization.push(quote! {
+                        #field_name: #field_ref_deserialization
+                    });
+
+                    variant_ref_to_owned.push(quote! {
+                        #field_name: #field_ref_to_owned
+                    });
                 }
 
-                let field_names = fields.named.iter().map(|f| &f.ident);
+                let field_names = fields.named.iter().map(|f| &f.ident).collect::<Vec<_>>();
+
+                ref_variants.push(quote! {
+                    #variant_name { #( #variant_ref_fields ),* }
+                });
 
-                serialization.push(quote! {
-                    #name::#variant_name { #( #field_names ),* } => {
+                ref_serialization.push(quote! {
+                    #ref_name::#variant_name { #( #field_names ),* } => {
                         let mut buffer_index: usize = 0;
-                        #( #variant_field_serialization )*
+                        #( #variant_ref_serialization )*
                         buffer_index
                     }
                 });
 
-                deserialization.push(quote! {
+                ref_deserialization.push(quote! {
                     #packet_number => {
+                        if bytes.len() < #variant_current_size {
+                            return Err(lifx_serialization::LifxDeserializationError::InvalidPacketSize);
+                        }
+
+                        Ok(#ref_name::#variant_name {
+                            #( #variant_ref_deserialization ),*
+                        })
+                    }
+                });
+
+                ref_to_owned.push(quote! {
+                    #ref_name::#variant_name { #( #field_names ),* } => {
                         Ok(#name::#variant_name {
-                            #( #variant_field_deserialization ),*
+                            #( #variant_ref_to_owned ),*
                         })
                     }
                 });
+
+                (
+                    quote! {
+                        #variant_name { #( #field_names ),* } => {
+                            let mut buffer_index: usize = 0;
+                            #( #variant_field_serialization )*
+                            buffer_index
+                        }
+                    },
+                    quote! {
+                        #packet_number => {
+                            if bytes.len() < #variant_current_size {
+                                return Err(lifx_serialization::LifxDeserializationError::InvalidPacketSize);
+                            }
+
+                            Ok(Self::#variant_name {
+                                #( #variant_field_deserialization ),*
+                            })
+                        }
+                    },
+                )
             },
             Fields::Unit => {
-                serialization.push(quote! {
-                    #name::#variant_name => {
+                ref_variants.push(quote! {
+                    #variant_name
+                });
+
+                ref_serialization.push(quote! {
+                    #ref_name::#variant_name => {
                         0
                     }
                 });
 
-                deserialization.push(quote! {
+                ref_deserialization.push(quote! {
                     #packet_number => {
+                        Ok(#ref_name::#variant_name)
+                    }
+                });
+
+                ref_to_owned.push(quote! {
+                    #ref_name::#variant_name => {
                         Ok(#name::#variant_name)
                     }
                 });
+
+                (
+                    quote! {
+                        #variant_name => {
+                            0
+                        }
+                    },
+                    quote! {
+                        #packet_number => {
+                            Ok(Self::#variant_name)
+                        }
+                    },
+                )
             }
             _ => panic!("LifxPayload can only be derived for enums with named fields (tuples)"),
         };
 
-        map_variant_to_size.push(quote! {
-            #name::#variant_name { .. } => #variant_current_size
+        map_packet_number_to_responses.push(quote! {
+            #packet_number => &[ #( #responses ),* ]
+        });
+
+        schemas.push(quote! {
+            lifx_serialization::PacketSchema {
+                name: #variant_name_string,
+                packet_number: #packet_number,
+                size: #variant_current_size,
+                fields: &[ #( #field_schemas ),* ],
+                responses: &[ #( #responses ),* ],
+            }
         });
 
-        map_variant_to_packet_number.push(quote! {
-            #name::#variant_name { .. } => #packet_number
+        variants.push(VariantCode {
+            ident: variant_name.clone(),
+            definition: variant_definition(message),
+            fields: variant_fields(message),
+            serialization,
+            deserialization: Some(deserialization),
+            size: quote! { #variant_name { .. } => #variant_current_size },
+            packet_number: quote! { #variant_name { .. } => #packet_number },
+            number: Some(packet_number),
+            name: quote! { #variant_name { .. } => #variant_name_string },
+            responses: quote! { #variant_name { .. } => &[ #( #responses ),* ] },
+            is_unknown: false,
+            is_request: direction == Some(Direction::Request) || (direction.is_none() && !responses.is_empty()),
+            is_response: direction == Some(Direction::Response),
         });
     }
 
+    // Anything another message is answered with travels from the device to the client
+    for (variant, (_, packet_number)) in variants.iter_mut().filter(|variant| !variant.is_unknown).zip(variant_layouts.iter()) {
+        if response_packet_numbers.contains(packet_number) {
+            variant.is_response = true;
+        }
+    }
+
+    let payload_impl = payload_code(&name, variants.iter(), &[]);
+    let map_variant_to_name = variants.iter().map(|variant| &variant.name);
+    let map_variant_to_responses = variants.iter().map(|variant| &variant.responses);
+
+    let direction_enums = directions.map(|(request_name, response_name)| {
+        let mut request_variants = Vec::<&VariantCode>::new();
+        let mut response_variants = Vec::<&VariantCode>::new();
+
+        for variant in variants.iter() {
+            if !variant.is_request && !variant.is_response {
+                panic!("Cannot tell which way {} is sent, add #[direction(request)] or #[direction(response)]", variant.ident);
+            }
+
+            if variant.is_request {
+                request_variants.push(variant);
+            }
+
+            if variant.is_response {
+                response_variants.push(variant);
+            }
+        }
+
+        let request_doc = format!("The [`{}`]s a client sends to a device.", name);
+        let request_enum = direction_code(&name, &visibility, &request_name, &request_doc, &request_variants, &variants);
+        let request_responses = request_variants.iter().map(|variant| &variant.responses);
+
+        let response_doc = format!("The [`{}`]s a device sends back to a client.", name);
+        let response_enum = direction_code(&name, &visibility, &response_name, &response_doc, &response_variants, &variants);
+
+        quote! {
+            #request_enum
+
+            impl #request_name {
+                /// Packet numbers a device may answer this message with, besides an Acknowledgement.
+                pub fn response_packet_numbers(&self) -> &'static [u16] {
+                    match self {
+                        #( #request_name::#request_responses ),*
+                    }
+                }
+            }
+
+            #response_enum
+        }
+    });
+
+    let response_structs = variant_layouts.iter()
+        .filter(|(_, packet_number)| response_packet_numbers.contains(packet_number))
+        .map(|(variant, packet_number)| response_code(&name, variant, *packet_number));
+
+    let map_variant_to_packet_number = variants.iter().map(|variant| &variant.packet_number);
+    let map_variant_to_size = variants.iter().map(|variant| &variant.size);
+
     let expanded = quote! {
+        #payload_impl
+
+        impl #name {
+            const SCHEMA: &'static [lifx_serialization::PacketSchema] = &[ #( #schemas ),* ];
+
+            /// Payload layout of every known packet type.
+            pub fn schema() -> &'static [lifx_serialization::PacketSchema] {
+                Self::SCHEMA
+            }
+
+            pub fn schema_for(packet_number: u16) -> Option<&'static lifx_serialization::PacketSchema> {
+                Self::SCHEMA.iter().find(|schema| schema.packet_number == packet_number)
+            }
+
+            pub fn name_for(packet_number: u16) -> Option<&'static str> {
+                Self::schema_for(packet_number).map(|schema| schema.name)
+            }
+
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #( #name::#map_variant_to_name ),*
+                }
+            }
+
+            /// Packet numbers a device may answer this message with, besides an Acknowledgement.
+            pub fn response_packet_numbers(&self) -> &'static [u16] {
+                match self {
+                    #( #name::#map_variant_to_responses ),*
+                }
+            }
+
+            pub fn responses_for(packet_number: u16) -> &'static [u16] {
+                match packet_number {
+                    #( #map_packet_number_to_responses, )*
+                    _ => &[],
+                }
+            }
+        }
+
+        #direction_enums
+
+        /// Standalone payloads for each message that is sent in reply to another.
+        pub mod responses {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #( #response_structs )*
+        }
+
+        #[doc = #ref_doc]
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        #visibility enum #ref_name<'a> {
+            #( #ref_variants ),*
+        }
+
+        impl<'a> #ref_name<'a> {
+            pub fn from_bytes(packet_number: u16, bytes: &'a [u8]) -> Result<Self, lifx_serialization::LifxDeserializationError> {
+                match packet_number {
+                    #( #ref_deserialization ),*
+                    #unknown_ref_deserialization
+                }
+            }
+
+            pub fn to_bytes(&self, buffer: &mut [u8]) -> usize {
+                match self {
+                    #( #ref_serialization ),*
+                }
+            }
+
+            pub fn packet_number(&self) -> u16 {
+                match self {
+                    #( #ref_name::#map_variant_to_packet_number ),*
+                }
+            }
+
+            pub fn size(&self) -> usize {
+                match self {
+                    #( #ref_name::#map_variant_to_size ),*
+                }
+            }
+
+            /// Copies the borrowed fields into an owned message.
+            pub fn to_message(&self) -> Result<#name, lifx_serialization::LifxDeserializationError> {
+                match *self {
+                    #( #ref_to_owned ),*
+                }
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Direction {
+    Request,
+    Response,
+}
+
+// #[direction(request)] or #[direction(response)], for variants whose direction can't be told from #[response(..)]
+fn parse_direction(variant: &syn::Variant) -> Option<Direction> {
+    variant.attrs.iter().find(|attr| attr.path().is_ident("direction")).map(|attr| {
+        let direction: syn::Ident = attr.parse_args().expect("Direction must be `request` or `response`");
+
+        match direction.to_string().as_str() {
+            "request" => Direction::Request,
+            "response" => Direction::Response,
+            _ => panic!("Direction must be `request` or `response`"),
+        }
+    })
+}
+
+// #[directions(request = DeviceRequest, response = DeviceResponse)] on the enum itself
+fn parse_directions(attrs: &[syn::Attribute]) -> Option<(syn::Ident, syn::Ident)> {
+    let attr = attrs.iter().find(|attr| attr.path().is_ident("directions"))?;
+
+    let mut request = None;
+    let mut response = None;
+
+    attr.parse_nested_meta(|meta| {
+        let value: syn::Ident = meta.value()?.parse()?;
+
+        if meta.path.is_ident("request") {
+            request = Some(value);
+        } else if meta.path.is_ident("response") {
+            response = Some(value);
+        } else {
+            return Err(meta.error("Expected `request` or `response`"));
+        }
+
+        Ok(())
+    }).expect("Directions must look like #[directions(request = Name, response = Name)]");
+
+    Some((
+        request.expect("Directions must name the request enum"),
+        response.expect("Directions must name the response enum"),
+    ))
+}
+
+// The variant as written, minus the attributes only this derive understands
+fn variant_definition(variant: &syn::Variant) -> proc_macro2::TokenStream {
+    let variant_name = &variant.ident;
+
+    match &variant.fields {
+        Fields::Named(fields) => {
+            let field_names = fields.named.iter().map(|f| &f.ident);
+            let field_types = fields.named.iter().map(|f| &f.ty);
+
+            quote! { #variant_name { #( #field_names: #field_types ),* } }
+        }
+        _ => quote! { #variant_name },
+    }
+}
+
+fn variant_fields(variant: &syn::Variant) -> proc_macro2::TokenStream {
+    match &variant.fields {
+        Fields::Named(fields) => {
+            let field_names = fields.named.iter().map(|f| &f.ident);
+            quote! { { #( #field_names ),* } }
+        }
+        _ => quote! {},
+    }
+}
+
+// `excluded` lists packet numbers that must not fall through to the unknown variant
+fn payload_code<'a>(name: &syn::Ident, variants: impl Iterator<Item = &'a VariantCode> + Clone, excluded: &[u16]) -> proc_macro2::TokenStream {
+    let serialization = variants.clone().map(|variant| &variant.serialization);
+    let deserialization = variants.clone().filter_map(|variant| variant.deserialization.as_ref());
+    let map_variant_to_packet_number = variants.clone().map(|variant| &variant.packet_number);
+    let map_variant_to_size = variants.clone().map(|variant| &variant.size);
+
+    let excluded_deserialization = if excluded.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            #( #excluded )|* => Err(lifx_serialization::LifxDeserializationError::InvalidPacketNumber(packet_number)),
+        }
+    };
+
+    let unknown_deserialization = match variants.clone().find(|variant| variant.is_unknown) {
+        Some(VariantCode { ident, .. }) => quote! {
+            _ => Ok(Self::#ident {
+                packet_number,
+                payload: lifx_serialization::deserialize_bytes(bytes)?,
+            }),
+        },
+        None => quote! {
+            _ => Err(lifx_serialization::LifxDeserializationError::InvalidPacketNumber(packet_number)),
+        },
+    };
+
+    quote! {
         impl LifxPayload for #name {
             fn from_bytes(packet_number: u16, bytes: &[u8]) -> Result<Self, lifx_serialization::LifxDeserializationError> {
                 match packet_number {
-                    #( #deserialization ),*
-                    _ => Err(lifx_serialization::LifxDeserializationError::InvalidPacketNumber(packet_number)),
+                    #( #deserialization, )*
+                    #excluded_deserialization
+                    #unknown_deserialization
                 }
             }
 
             fn to_bytes(&self, buffer: &mut [u8]) -> usize {
                 match self {
-                    #( #serialization ),*
+                    #( #name::#serialization ),*
                 }
             }
 
             fn packet_number(&self) -> u16 {
                 match self {
-                    #( #map_variant_to_packet_number ),*
+                    #( #name::#map_variant_to_packet_number ),*
                 }
             }
 
             fn size(&self) -> usize {
                 match self {
-                    #( #map_variant_to_size ),*
+                    #( #name::#map_variant_to_size ),*
+                }
+            }
+        }
+    }
+}
+
+// A subset of the derived enum's variants as an enum of its own, with conversions to and from the full enum
+fn direction_code(name: &syn::Ident, visibility: &syn::Visibility, direction_name: &syn::Ident, doc: &str, variants: &[&VariantCode], all_variants: &[VariantCode]) -> proc_macro2::TokenStream {
+    // Known packets sent the other way are rejected rather than decoded as unknown
+    let excluded = all_variants.iter()
+        .filter(|variant| !variants.iter().any(|included| included.ident == variant.ident))
+        .filter_map(|variant| variant.number)
+        .collect::<Vec<_>>();
+
+    let payload_impl = payload_code(direction_name, variants.iter().copied(), &excluded);
+    let definitions = variants.iter().map(|variant| &variant.definition);
+    let map_variant_to_name = variants.iter().map(|variant| &variant.name);
+    let variant_names = variants.iter().map(|variant| &variant.ident).collect::<Vec<_>>();
+    let variant_fields = variants.iter().map(|variant| &variant.fields).collect::<Vec<_>>();
+
+    quote! {
+        #[doc = #doc]
+        #[derive(Debug, Clone, PartialEq)]
+        #visibility enum #direction_name {
+            #( #definitions ),*
+        }
+
+        #payload_impl
+
+        impl #direction_name {
+            pub fn name(&self) -> &'static str {
+                match self {
+                    #( #direction_name::#map_variant_to_name ),*
                 }
             }
         }
+
+        impl From<#direction_name> for #name {
+            fn from(message: #direction_name) -> Self {
+                match message {
+                    #( #direction_name::#variant_names #variant_fields => #name::#variant_names #variant_fields ),*
+                }
+            }
+        }
+
+        impl TryFrom<#name> for #direction_name {
+            type Error = #name;
+
+            /// Fails with the original message when it is sent the other way.
+            fn try_from(message: #name) -> Result<Self, #name> {
+                #[allow(unreachable_patterns)]
+                match message {
+                    #( #name::#variant_names #variant_fields => Ok(#direction_name::#variant_names #variant_fields), )*
+                    other => Err(other),
+                }
+            }
+        }
+    }
+}
+
+fn response_code(name: &syn::Ident, variant: &syn::Variant, packet_number: u16) -> proc_macro2::TokenStream {
+    let variant_name = &variant.ident;
+
+    let (definition, fields) = match &variant.fields {
+        Fields::Named(fields) => {
+            let field_names = fields.named.iter().map(|f| &f.ident).collect::<Vec<_>>();
+            let field_types = fields.named.iter().map(|f| &f.ty);
+
+            (quote! { pub struct #variant_name { #( pub #field_names: #field_types ),* } }, quote! { { #( #field_names ),* } })
+        }
+        _ => (quote! { pub struct #variant_name; }, quote! {}),
     };
 
-    TokenStream::from(expanded)
+    quote! {
+        #[derive(Debug, Clone, PartialEq)]
+        #definition
+
+        impl lifx_serialization::LifxResponse for #variant_name {
+            type Payload = #name;
+
+            const PACKET_NUMBER: u16 = #packet_number;
+
+            fn from_payload(payload: #name) -> Result<Self, #name> {
+                match payload {
+                    #name::#variant_name #fields => Ok(#variant_name #fields),
+                    other => Err(other),
+                }
+            }
+        }
+
+        impl From<#variant_name> for #name {
+            fn from(response: #variant_name) -> Self {
+                let #variant_name #fields = response;
+                #name::#variant_name #fields
+            }
+        }
+    }
 }
 
-fn generate_field_code(field: &syn::Field, variant_current_size: usize) -> (proc_macro2::TokenStream, proc_macro2::TokenStream, usize) {
+struct FieldCode {
+    serialization: proc_macro2::TokenStream,
+    deserialization: proc_macro2::TokenStream,
+    size: usize,
+    ty: proc_macro2::TokenStream,
+
+    ref_type: proc_macro2::TokenStream,
+    ref_serialization: proc_macro2::TokenStream,
+    ref_deserialization: proc_macro2::TokenStream,
+    ref_to_owned: proc_macro2::TokenStream,
+}
+
+fn generate_field_code(field: &syn::Field, variant_current_size: usize) -> FieldCode {
     let field_name = field.ident.as_ref().unwrap();
+    let field_type = &field.ty;
     let mut field_size: usize = 32;
-    let field_serialization: proc_macro2::TokenStream;
-    let field_deserialization: proc_macro2::TokenStream;
 
     field.attrs.iter().for_each(|attr| {
         if attr.path().is_ident("size") {
@@ -144,30 +727,60 @@ fn generate_field_code(field: &syn::Field, variant_current_size: usize) -> (proc
             let base_type = path.segments[0].ident.to_string();
 
             if base_type == "String" {
-                field_serialization = quote! {
-                    buffer[buffer_index..buffer_index+#field_size].copy_from_slice(#field_name.clone().into_bytes().as_slice());
-                };
+                // heapless strings carry their wire size as the capacity, e.g. String<32>
+                if let Some(capacity) = string_capacity(&path.segments[0]) {
+                    field_size = capacity;
+                }
 
-                field_deserialization = quote! {
-                    lifx_serialization::deserialize_string(&bytes[#variant_current_size..#variant_current_size + #field_size])?
+                let serialization = quote! {
+                    lifx_serialization::serialize_string(#field_name.as_str(), &mut buffer[buffer_index..buffer_index+#field_size]);
                 };
+
+                FieldCode {
+                    serialization,
+                    deserialization: quote! {
+                        lifx_serialization::deserialize_string(&bytes[#variant_current_size..#variant_current_size + #field_size])?
+                    },
+                    size: field_size,
+                    ty: quote! { lifx_serialization::FieldType::String },
+
+                    ref_type: quote! { &'a str },
+                    ref_serialization: quote! {
+                        lifx_serialization::serialize_string(#field_name, &mut buffer[buffer_index..buffer_index+#field_size]);
+                    },
+                    ref_deserialization: quote! {
+                        lifx_serialization::deserialize_str(&bytes[#variant_current_size..#variant_current_size + #field_size])?
+                    },
+                    ref_to_owned: quote! {
+                        lifx_serialization::deserialize_string(#field_name.as_bytes())?
+                    },
+                }
             } else {
-                let (deserialization, size) = match base_type.as_str() {
-                    "u8" => (quote! { bytes[#variant_current_size] }, 1),
-                    "u16" => (quote! { u16::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1]]) }, 2),
-                    "u32" => (quote! { u32::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1], bytes[#variant_current_size + 2], bytes[#variant_current_size + 3]]) }, 4),
-                    "u64" => (quote! { u64::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1], bytes[#variant_current_size + 2], bytes[#variant_current_size + 3], bytes[#variant_current_size + 4], bytes[#variant_current_size + 5], bytes[#variant_current_size + 6], bytes[#variant_current_size + 7]]) }, 8),
-                    "f32" => (quote! { f32::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1], bytes[#variant_current_size + 2], bytes[#variant_current_size + 3]]) }, 4),
-                    "i16" => (quote! { i16::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1]]) }, 2),
+                let (deserialization, size, ty) = match base_type.as_str() {
+                    "u8" => (quote! { bytes[#variant_current_size] }, 1, quote! { U8 }),
+                    "u16" => (quote! { u16::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1]]) }, 2, quote! { U16 }),
+                    "u32" => (quote! { u32::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1], bytes[#variant_current_size + 2], bytes[#variant_current_size + 3]]) }, 4, quote! { U32 }),
+                    "u64" => (quote! { u64::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1], bytes[#variant_current_size + 2], bytes[#variant_current_size + 3], bytes[#variant_current_size + 4], bytes[#variant_current_size + 5], bytes[#variant_current_size + 6], bytes[#variant_current_size + 7]]) }, 8, quote! { U64 }),
+                    "f32" => (quote! { f32::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1], bytes[#variant_current_size + 2], bytes[#variant_current_size + 3]]) }, 4, quote! { F32 }),
+                    "i16" => (quote! { i16::from_le_bytes([bytes[#variant_current_size], bytes[#variant_current_size + 1]]) }, 2, quote! { I16 }),
                     _ => panic!("Unsupported type: {}", base_type),
                 };
 
-                field_serialization = quote! {
+                let serialization = quote! {
                     buffer[buffer_index..buffer_index+#size].copy_from_slice(&#field_name.to_le_bytes());
                 };
 
-                field_deserialization = deserialization;
-                field_size = size;
+                FieldCode {
+                    serialization: serialization.clone(),
+                    deserialization: deserialization.clone(),
+                    size,
+                    ty: quote! { lifx_serialization::FieldType::#ty },
+
+                    ref_type: quote! { #field_type },
+                    ref_serialization: serialization,
+                    ref_deserialization: deserialization,
+                    ref_to_owned: quote! { #field_name },
+                }
             }
         },
         syn::Type::Array(array) => {
@@ -190,22 +803,102 @@ fn generate_field_code(field: &syn::Field, variant_current_size: usize) -> (proc
                     }
 
                     if base_type == "u8" {
-                        field_serialization = quote! {
-                            buffer[buffer_index..buffer_index+#field_size].copy_from_slice(#field_name);
-                        };
-
-                        field_deserialization = quote! {
-                            [#(#bytes),*]
-                        };
+                        FieldCode {
+                            serialization: quote! {
+                                buffer[buffer_index..buffer_index+#field_size].copy_from_slice(#field_name);
+                            },
+                            deserialization: quote! {
+                                [#(#bytes),*]
+                            },
+                            size: field_size,
+                            ty: quote! { lifx_serialization::FieldType::Bytes },
+
+                            ref_type: quote! { &'a [u8] },
+                            ref_serialization: quote! {
+                                lifx_serialization::serialize_bytes(#field_name, &mut buffer[buffer_index..buffer_index+#field_size]);
+                            },
+                            ref_deserialization: quote! {
+                                &bytes[#variant_current_size..#variant_current_size + #field_size]
+                            },
+                            ref_to_owned: quote! {
+                                #field_name.try_into().map_err(|_| lifx_serialization::LifxDeserializationError::InvalidPacketSize)?
+                            },
+                        }
                     } else {
-                        panic!("Array must contain only u8 elements.");
+                        element_array_code(field, path, field_size, variant_current_size)
                     }
                 },
                 _ => panic!("Unsupported array type"),
             }
         }
-        _ => panic!("Only u16, u32, u64, f32, String, and arrays of u8 are supported"),
+        _ => panic!("Only u8, u16, u32, u64, i16, f32, String and arrays are supported"),
+    }
+}
+
+// Arrays of anything other than bytes, such as the colours of every zone on a strip. Elements go through
+// `LifxField`; primitives know their own size, other element types need `#[size(..)]` with the size of the whole array.
+fn element_array_code(field: &syn::Field, element: &syn::Path, length: usize, variant_current_size: usize) -> FieldCode {
+    let field_name = field.ident.as_ref().unwrap();
+    let element_name = element.segments.last().unwrap().ident.to_string();
+
+    let size_attribute = field.attrs.iter().find(|attr| attr.path().is_ident("size")).map(|attr| {
+        let lit: syn::LitInt = attr.parse_args().expect("Size must be a usize");
+        lit.base10_parse::<usize>().expect("Size must be a usize")
+    });
+
+    let element_size = match (element_name.as_str(), size_attribute) {
+        ("u16" | "i16", None) => 2,
+        ("u32" | "f32", None) => 4,
+        ("u64", None) => 8,
+        (_, Some(size)) if size % length == 0 => size / length,
+        (_, Some(_)) => panic!("Size of `{}` must be a multiple of its length", field_name),
+        (_, None) => panic!("Arrays of `{}` need #[size(..)] with the size of the whole array in bytes", element_name),
     };
 
-    (field_serialization, field_deserialization, field_size)
-}
\ No newline at end of file
+    let size = element_size * length;
+    let element_name = format!("{}[{}]", element_name, length);
+
+    let deserialization = quote! {
+        {
+            const _: () = assert!(<#element as lifx_serialization::LifxField>::SIZE == #element_size, "#[size(..)] does not match the element's LifxField::SIZE");
+
+            core::array::from_fn(|index| {
+                let start = #variant_current_size + index * #element_size;
+                <#element as lifx_serialization::LifxField>::read(&bytes[start..start + #element_size])
+            })
+        }
+    };
+
+    let serialization = quote! {
+        for (index, element) in #field_name.iter().enumerate() {
+            let start = buffer_index + index * #element_size;
+            lifx_serialization::LifxField::write(element, &mut buffer[start..start + #element_size]);
+        }
+    };
+
+    FieldCode {
+        serialization: serialization.clone(),
+        deserialization: deserialization.clone(),
+        size,
+        ty: quote! { lifx_serialization::FieldType::Array { element: #element_name } },
+
+        // Elements are small and Copy, so the borrowed view holds its own copy rather than raw bytes
+        ref_type: quote! { [#element; #length] },
+        ref_serialization: serialization,
+        ref_deserialization: deserialization,
+        ref_to_owned: quote! { #field_name },
+    }
+}
+
+fn string_capacity(segment: &syn::PathSegment) -> Option<usize> {
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(arguments) => arguments.args.iter().find_map(|argument| match argument {
+            syn::GenericArgument::Const(syn::Expr::Lit(lit)) => match &lit.lit {
+                syn::Lit::Int(int) => Some(int.base10_parse().expect("String capacity must be a number")),
+                _ => None,
+            },
+            _ => None,
+        }),
+        _ => None,
+    }
+}
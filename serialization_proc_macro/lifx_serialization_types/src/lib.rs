@@ -1,15 +1,120 @@
-#[derive(thiserror::Error, Debug)]
+#![cfg_attr(feature = "no-std", no_std)]
+
+use core::fmt;
+
+#[derive(Debug)]
 pub enum LifxDeserializationError {
-    #[error("Invalid packet number: {0}")]
     InvalidPacketNumber(u16),
 
-    #[error("Invalid UTF-8 string")]
     InvalidUtf8String,
 
-    #[error("Invalid packet size")]
     InvalidPacketSize,
 }
 
+impl fmt::Display for LifxDeserializationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LifxDeserializationError::InvalidPacketNumber(packet_number) => {
+                write!(f, "Invalid packet number: {}", packet_number)
+            }
+            LifxDeserializationError::InvalidUtf8String => write!(f, "Invalid UTF-8 string"),
+            LifxDeserializationError::InvalidPacketSize => write!(f, "Invalid packet size"),
+        }
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl std::error::Error for LifxDeserializationError {}
+
+/// Wire type of a payload field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I16,
+    F32,
+    /// NUL padded UTF-8 string
+    String,
+    /// Fixed length byte array
+    Bytes,
+    /// Fixed length array of some other type, e.g. `element: "Hsbk[82]"`
+    Array { element: &'static str },
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FieldType::U8 => "u8",
+            FieldType::U16 => "u16",
+            FieldType::U32 => "u32",
+            FieldType::U64 => "u64",
+            FieldType::I16 => "i16",
+            FieldType::F32 => "f32",
+            FieldType::String => "string",
+            FieldType::Bytes => "bytes",
+            FieldType::Array { element } => *element,
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Layout of a single field within a packet payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSchema {
+    pub name: &'static str,
+    /// Byte offset from the start of the payload
+    pub offset: usize,
+    pub size: usize,
+    pub ty: FieldType,
+}
+
+/// Layout of a packet payload, as generated by `#[derive(LifxPayload)]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketSchema {
+    pub name: &'static str,
+    pub packet_number: u16,
+    /// Payload size in bytes, excluding the 36 byte header
+    pub size: usize,
+    pub fields: &'static [FieldSchema],
+    /// Packet numbers sent in reply to this packet
+    pub responses: &'static [u16],
+}
+
+/// A fixed size value that can be an element of an array in a payload, such as a colour in a list of zone colours.
+pub trait LifxField: Sized {
+    /// Bytes taken up on the wire
+    const SIZE: usize;
+
+    /// Reads the value from exactly `SIZE` bytes.
+    fn read(bytes: &[u8]) -> Self;
+
+    /// Writes the value into exactly `SIZE` bytes.
+    fn write(&self, buffer: &mut [u8]);
+}
+
+macro_rules! primitive_field {
+    ($($ty:ty),*) => {
+        $(
+            impl LifxField for $ty {
+                const SIZE: usize = core::mem::size_of::<$ty>();
+
+                fn read(bytes: &[u8]) -> Self {
+                    <$ty>::from_le_bytes(bytes.try_into().unwrap())
+                }
+
+                fn write(&self, buffer: &mut [u8]) {
+                    buffer.copy_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+primitive_field!(u16, u32, u64, i16, f32);
+
 pub trait LifxPayload {
     fn from_bytes(payload_number: u16, bytes: &[u8]) -> Result<Self, LifxDeserializationError>
     where
@@ -19,3 +124,13 @@ pub trait LifxPayload {
     fn packet_number(&self) -> u16;
     fn size(&self) -> usize;
 }
+
+/// A payload that is sent in reply to another, generated for every packet named in a `#[response(..)]` attribute.
+pub trait LifxResponse: Sized {
+    type Payload: LifxPayload;
+
+    const PACKET_NUMBER: u16;
+
+    /// Returns the payload back if it is a different packet.
+    fn from_payload(payload: Self::Payload) -> Result<Self, Self::Payload>;
+}
@@ -1,11 +1,22 @@
-pub use lifx_serialization_types::{LifxPayload, LifxDeserializationError};
+#![cfg_attr(feature = "no-std", no_std)]
+
+pub use lifx_serialization_types::{FieldSchema, FieldType, LifxDeserializationError, LifxField, LifxPayload, LifxResponse, PacketSchema};
 pub use lifx_serialization_macro::LifxPayload;
 
+fn nul_terminated(bytes: &[u8]) -> &[u8] {
+    let nul_range_end = bytes.iter()
+        .position(|&c| c == b'\0')
+        .unwrap_or(bytes.len());
+
+    &bytes[0..nul_range_end]
+}
 
 #[cfg(feature = "no-std")]
-pub fn deserialize_string<const N: usize>(bytes: &[u8], size: u8) -> Result<heapless::String<N>, LifxDeserializationError> {
-    let mut string_bytes = heapless::Vec::<u8, size>::new();
-    string_bytes.extend_from_slice(&bytes[..32]).unwrap();
+pub fn deserialize_string<const N: usize>(bytes: &[u8]) -> Result<heapless::String<N>, LifxDeserializationError> {
+    let mut string_bytes = heapless::Vec::<u8, N>::new();
+    if string_bytes.extend_from_slice(nul_terminated(bytes)).is_err() {
+        return Err(LifxDeserializationError::InvalidPacketSize);
+    }
 
     match heapless::String::from_utf8(string_bytes) {
         Ok(string) => Ok(string),
@@ -15,12 +26,45 @@ pub fn deserialize_string<const N: usize>(bytes: &[u8], size: u8) -> Result<heap
 
 #[cfg(not(feature = "no-std"))]
 pub fn deserialize_string(bytes: &[u8]) -> Result<String, LifxDeserializationError> {
-    let nul_range_end = bytes.iter()
-        .position(|&c| c == b'\0')
-        .unwrap_or(bytes.len()); 
+    match String::from_utf8(nul_terminated(bytes).to_vec()) {
+        Ok(string) => Ok(string),
+        Err(_) => Err(LifxDeserializationError::InvalidUtf8String),
+    }
+}
+
+#[cfg(feature = "no-std")]
+pub fn deserialize_bytes<const N: usize>(bytes: &[u8]) -> Result<heapless::Vec<u8, N>, LifxDeserializationError> {
+    heapless::Vec::from_slice(bytes).map_err(|_| LifxDeserializationError::InvalidPacketSize)
+}
 
-    match String::from_utf8(bytes[0..nul_range_end].to_vec()) {
+#[cfg(not(feature = "no-std"))]
+pub fn deserialize_bytes(bytes: &[u8]) -> Result<Vec<u8>, LifxDeserializationError> {
+    Ok(bytes.to_vec())
+}
+
+pub fn deserialize_str(bytes: &[u8]) -> Result<&str, LifxDeserializationError> {
+    match core::str::from_utf8(nul_terminated(bytes)) {
         Ok(string) => Ok(string),
         Err(_) => Err(LifxDeserializationError::InvalidUtf8String),
     }
-}
\ No newline at end of file
+}
+
+// Strings are fixed width on the wire: copy the whole characters that fit and zero-pad the rest.
+pub fn serialize_string(string: &str, buffer: &mut [u8]) {
+    let string_bytes = string.as_bytes();
+    let mut length = core::cmp::min(string_bytes.len(), buffer.len());
+    while !string.is_char_boundary(length) {
+        length -= 1;
+    }
+
+    buffer[..length].copy_from_slice(&string_bytes[..length]);
+    buffer[length..].fill(0);
+}
+
+// Byte fields borrowed from elsewhere may not have the wire size: copy what fits and zero-pad the rest.
+pub fn serialize_bytes(bytes: &[u8], buffer: &mut [u8]) {
+    let length = core::cmp::min(bytes.len(), buffer.len());
+
+    buffer[..length].copy_from_slice(&bytes[..length]);
+    buffer[length..].fill(0);
+}
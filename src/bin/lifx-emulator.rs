@@ -0,0 +1,46 @@
+// Runs an emulated bulb until interrupted: lifx-emulator [--bind 127.0.0.1:56700] [--label "Desk Lamp"]
+#[cfg(not(feature = "no-std"))]
+fn main() {
+    use std::{thread, time::Duration};
+
+    use lifx_lan::emulator::{Emulator, EmulatorConfig};
+
+    let mut bind = String::from("127.0.0.1:56700");
+    let mut config = EmulatorConfig::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match (arg.as_str(), args.next()) {
+            ("--bind", Some(value)) => bind = value,
+            ("--label", Some(value)) => config.label = value,
+            _ => {
+                eprintln!("Usage: lifx-emulator [--bind ADDRESS] [--label LABEL]");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let emulator = Emulator::bind(&bind, config).unwrap_or_else(|err| {
+        eprintln!("Failed to bind {}: {}", bind, err);
+        std::process::exit(1);
+    });
+
+    println!("Emulating {} on {}", emulator.state().label, emulator.address());
+
+    let mut last_state = emulator.state();
+    loop {
+        thread::sleep(Duration::from_millis(250));
+
+        let state = emulator.state();
+        if state.color != last_state.color || state.power != last_state.power || state.label != last_state.label {
+            println!("{:?} power={} label={:?}", state.color, state.power, state.label);
+        }
+
+        last_state = state;
+    }
+}
+
+#[cfg(feature = "no-std")]
+fn main() {
+    eprintln!("lifx-emulator needs the standard library; build without the no-std feature");
+}
@@ -0,0 +1,425 @@
+use std::time::Duration;
+
+use crate::{
+    client::{ClientError, Device, LifxClient},
+    light::milliseconds,
+    messages::{responses, TileDevice, TILE_COLORS},
+    products::{Capability, Unsupported},
+    scene, DeviceRequest, Hsbk,
+};
+
+/// Which way up a tile is mounted, as worked out from its accelerometer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Orientation {
+    #[default]
+    Upright,
+    /// Turned a quarter turn anticlockwise, so its top edge faces left
+    RotatedLeft,
+    /// Turned a quarter turn clockwise, so its top edge faces right
+    RotatedRight,
+    UpsideDown,
+    /// Lying flat, facing the ceiling; drawn as if upright
+    FaceUp,
+    /// Lying flat, facing the floor; drawn as if upright
+    FaceDown,
+}
+
+impl Orientation {
+    /// The orientation closest to what the accelerometer measures, whichever axis gravity pulls along most.
+    pub fn from_accelerometer(x: i16, y: i16, z: i16) -> Self {
+        // Tiles that haven't taken a reading report -1 on every axis
+        if (x, y, z) == (-1, -1, -1) {
+            return Orientation::Upright;
+        }
+
+        let (abs_x, abs_y, abs_z) = ((x as i32).abs(), (y as i32).abs(), (z as i32).abs());
+
+        if abs_x > abs_y && abs_x > abs_z {
+            if x > 0 {
+                Orientation::RotatedRight
+            } else {
+                Orientation::RotatedLeft
+            }
+        } else if abs_z > abs_x && abs_z > abs_y {
+            if z > 0 {
+                Orientation::FaceDown
+            } else {
+                Orientation::FaceUp
+            }
+        } else if y > 0 {
+            Orientation::UpsideDown
+        } else {
+            Orientation::Upright
+        }
+    }
+
+    fn is_rotated(self) -> bool {
+        matches!(self, Orientation::RotatedLeft | Orientation::RotatedRight)
+    }
+
+    // Where pixel (x, y) of a `width` by `height` tile, counted along the tile's own rows, lands within its
+    // footprint on the canvas
+    fn to_canvas(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            Orientation::Upright | Orientation::FaceUp | Orientation::FaceDown => (x, y),
+            Orientation::UpsideDown => (width - 1 - x, height - 1 - y),
+            Orientation::RotatedRight => (height - 1 - y, x),
+            Orientation::RotatedLeft => (y, width - 1 - x),
+        }
+    }
+}
+
+/// Where one tile sits on the canvas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TilePlacement {
+    /// Canvas column of the left edge of the tile's footprint
+    pub left: usize,
+    /// Canvas row of the top edge of the tile's footprint
+    pub top: usize,
+    /// Size of the tile in its own rows and columns, before turning it to its orientation
+    pub width: usize,
+    pub height: usize,
+    pub orientation: Orientation,
+}
+
+impl TilePlacement {
+    /// Whether the tile has no pixels to draw, as a chain entry reporting a width or height of 0 does.
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Columns and rows the tile covers on the canvas, which are swapped for a tile on its side.
+    pub fn footprint(&self) -> (usize, usize) {
+        if self.orientation.is_rotated() {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        }
+    }
+}
+
+/// How the tiles of a chain are arranged, from where the LIFX app says each one is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct TileLayout {
+    tiles: Vec<TilePlacement>,
+    width: usize,
+    height: usize,
+}
+
+impl TileLayout {
+    /// Lays the tiles out from their StateDeviceChain entries. `user_x` and `user_y` give the centre of each tile
+    /// in tile widths, with y growing upwards; the canvas has y growing downwards and starts at the top left
+    /// corner of the leftmost and topmost tiles.
+    pub fn from_chain(devices: &[TileDevice]) -> Self {
+        let placed: Vec<(i32, i32, TilePlacement)> = devices
+            .iter()
+            .map(|device| {
+                let orientation = Orientation::from_accelerometer(device.accel_meas_x, device.accel_meas_y, device.accel_meas_z);
+                let tile = TilePlacement { left: 0, top: 0, width: device.width as usize, height: device.height as usize, orientation };
+                let (columns, rows) = tile.footprint();
+
+                let centre_x = device.user_x * tile.width as f32;
+                let centre_y = -device.user_y * tile.height as f32;
+                let left = (centre_x - columns as f32 / 2.0).round() as i32;
+                let top = (centre_y - rows as f32 / 2.0).round() as i32;
+
+                (left, top, tile)
+            })
+            .collect();
+
+        // A tile reporting no pixels keeps its place in the chain, so later tiles keep their indices, but covers
+        // nothing and doesn't count towards the canvas size
+        let drawn = || placed.iter().filter(|(_, _, tile)| !tile.is_empty());
+        let min_left = drawn().map(|(left, _, _)| *left).min().unwrap_or(0);
+        let min_top = drawn().map(|(_, top, _)| *top).min().unwrap_or(0);
+        let mut layout = TileLayout::default();
+
+        for (left, top, mut tile) in placed {
+            if tile.is_empty() {
+                layout.tiles.push(tile);
+                continue;
+            }
+
+            tile.left = (left - min_left) as usize;
+            tile.top = (top - min_top) as usize;
+
+            let (columns, rows) = tile.footprint();
+            layout.width = layout.width.max(tile.left + columns);
+            layout.height = layout.height.max(tile.top + rows);
+            layout.tiles.push(tile);
+        }
+
+        layout
+    }
+
+    pub fn tiles(&self) -> &[TilePlacement] {
+        &self.tiles
+    }
+
+    /// Width of the smallest canvas covering every tile.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// A blank canvas covering every tile.
+    pub fn canvas(&self) -> Canvas {
+        Canvas::new(self.width, self.height)
+    }
+
+    /// The pixels of one tile, taken from `canvas` and put in the tile's own row order for Set64.
+    pub fn tile_pixels(&self, canvas: &Canvas, index: usize) -> Vec<Hsbk> {
+        let Some(tile) = self.tiles.get(index) else {
+            return Vec::new();
+        };
+
+        let mut pixels = Vec::with_capacity(tile.width * tile.height);
+        for y in 0..tile.height {
+            for x in 0..tile.width {
+                let (column, row) = tile.orientation.to_canvas(x, y, tile.width, tile.height);
+                pixels.push(canvas.get(tile.left + column, tile.top + row).unwrap_or_default());
+            }
+        }
+
+        pixels
+    }
+}
+
+/// A picture to show across a chain of tiles, in canvas pixels with the origin at the top left.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<Hsbk>,
+}
+
+impl Canvas {
+    /// A canvas of unlit pixels.
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas { width, height, pixels: vec![Hsbk::default(); width * height] }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Hsbk> {
+        (x < self.width && y < self.height).then(|| self.pixels[y * self.width + x])
+    }
+
+    /// Colours one pixel. Pixels outside the canvas are ignored, so shapes can be drawn partly off the edge.
+    pub fn set(&mut self, x: usize, y: usize, color: Hsbk) {
+        if x < self.width && y < self.height {
+            self.pixels[y * self.width + x] = color;
+        }
+    }
+
+    pub fn fill(&mut self, color: Hsbk) {
+        self.pixels.iter_mut().for_each(|pixel| *pixel = color);
+    }
+
+    /// Every pixel, row by row.
+    pub fn pixels(&self) -> &[Hsbk] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [Hsbk] {
+        &mut self.pixels
+    }
+}
+
+/// The off-screen frame buffer that each frame is drawn into before being copied onto the tiles
+const DRAW_BUFFER: u8 = 1;
+
+/// A chain of tiles to draw canvases on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileChain {
+    device: Device,
+    layout: TileLayout,
+}
+
+impl TileChain {
+    /// Asks the device how its tiles are arranged.
+    pub fn query(client: &mut LifxClient, device: &Device) -> Result<Self, ClientError> {
+        let chain: responses::StateDeviceChain = client.request(device, &DeviceRequest::GetDeviceChain)?;
+        let count = (chain.tile_devices_count as usize).min(chain.tile_devices.len());
+
+        Ok(TileChain::new(*device, TileLayout::from_chain(&chain.tile_devices[..count])))
+    }
+
+    pub fn new(device: Device, layout: TileLayout) -> Self {
+        TileChain { device, layout }
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn layout(&self) -> &TileLayout {
+        &self.layout
+    }
+
+    /// A blank canvas covering every tile.
+    pub fn canvas(&self) -> Canvas {
+        self.layout.canvas()
+    }
+
+    /// The messages that show `canvas`: a Set64 into the off-screen frame buffer for each block of 64 pixels,
+    /// then one CopyFrameBuffer that moves every tile's frame onto the display at once, fading over `duration`.
+    pub fn messages(&self, canvas: &Canvas, duration: Duration) -> Vec<DeviceRequest> {
+        let mut messages = Vec::new();
+        let (mut max_width, mut max_height) = (0, 0);
+
+        for (index, tile) in self.layout.tiles.iter().enumerate() {
+            if tile.is_empty() {
+                continue;
+            }
+
+            let pixels = self.layout.tile_pixels(canvas, index);
+            let rows_per_message = (TILE_COLORS / tile.width).max(1);
+            max_width = max_width.max(tile.width);
+            max_height = max_height.max(tile.height);
+
+            for (block, colors) in pixels.chunks(rows_per_message * tile.width).enumerate() {
+                messages.push(DeviceRequest::Set64 {
+                    tile_index: index as u8,
+                    length: 1,
+                    fb_index: DRAW_BUFFER,
+                    x: 0,
+                    y: (block * rows_per_message) as u8,
+                    width: tile.width as u8,
+                    duration_ms: 0,
+                    colors: core::array::from_fn(|pixel| colors.get(pixel).copied().unwrap_or_default()),
+                });
+            }
+        }
+
+        if !self.layout.tiles.is_empty() {
+            messages.push(DeviceRequest::CopyFrameBuffer {
+                tile_index: 0,
+                length: self.layout.tiles.len() as u8,
+                src_fb_index: DRAW_BUFFER,
+                dst_fb_index: 0,
+                src_x: 0,
+                src_y: 0,
+                dst_x: 0,
+                dst_y: 0,
+                width: max_width as u8,
+                height: max_height as u8,
+                duration_ms: milliseconds(duration),
+            });
+        }
+
+        messages
+    }
+
+    /// Shows `canvas` on the tiles. The frame is sent without acknowledgement, like a frame of an animation.
+    pub fn draw(&self, client: &mut LifxClient, canvas: &Canvas, duration: Duration) -> Result<(), ClientError> {
+        for message in self.messages(canvas, duration) {
+            client.send(&self.device, &message)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The zones of a strip or beam, drawn in a buffer and sent as only the zones that changed since the last commit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneStrip {
+    device: Device,
+    extended: bool,
+    zones: Vec<Hsbk>,
+    // What the strip was last known to show
+    shown: Vec<Hsbk>,
+}
+
+impl ZoneStrip {
+    /// Reads the strip's zones and whether its firmware takes SetExtendedColorZones. Fails with
+    /// [`ClientError::Unsupported`] for a device without zones.
+    pub fn query(client: &mut LifxClient, device: &Device) -> Result<Self, ClientError> {
+        match scene::read(client, device)? {
+            Some((state, features)) if !state.zones.is_empty() => Ok(ZoneStrip::new(*device, features.extended_multizone, state.zones)),
+            _ => Err(ClientError::Unsupported(Unsupported::Missing(Capability::Multizone))),
+        }
+    }
+
+    /// A strip showing `zones`, with `extended` saying whether it takes SetExtendedColorZones.
+    pub fn new(device: Device, extended: bool, zones: Vec<Hsbk>) -> Self {
+        ZoneStrip { device, extended, shown: zones.clone(), zones }
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    pub fn is_extended(&self) -> bool {
+        self.extended
+    }
+
+    pub fn len(&self) -> usize {
+        self.zones.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.zones.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Hsbk> {
+        self.zones.get(index).copied()
+    }
+
+    /// Colours one zone. Zones past the end of the strip are ignored.
+    pub fn set(&mut self, index: usize, color: Hsbk) {
+        if let Some(zone) = self.zones.get_mut(index) {
+            *zone = color;
+        }
+    }
+
+    pub fn fill(&mut self, color: Hsbk) {
+        self.zones.iter_mut().for_each(|zone| *zone = color);
+    }
+
+    /// Every zone as drawn, including changes not yet committed.
+    pub fn zones(&self) -> &[Hsbk] {
+        &self.zones
+    }
+
+    pub fn zones_mut(&mut self) -> &mut [Hsbk] {
+        &mut self.zones
+    }
+
+    /// The zones as of the last commit, or as read by [`ZoneStrip::query`].
+    pub fn shown(&self) -> &[Hsbk] {
+        &self.shown
+    }
+
+    /// Puts the buffer back to what the strip shows, dropping uncommitted changes.
+    pub fn revert(&mut self) {
+        self.zones.clone_from(&self.shown);
+    }
+
+    /// The messages that bring the strip from what it shows to the buffer, fading over `duration`. Every message
+    /// but the last holds its zones back, so the whole change shows at once.
+    pub fn messages(&self, duration: Duration) -> Vec<DeviceRequest> {
+        scene::zone_messages(&self.shown, &self.zones, self.extended, milliseconds(duration))
+    }
+
+    /// Sends the changed zones, each message acknowledged, and returns how many messages that took. If one isn't
+    /// acknowledged the strip is left as it was, since nothing before it was applied.
+    pub fn commit(&mut self, client: &mut LifxClient, duration: Duration) -> Result<usize, ClientError> {
+        let messages = self.messages(duration);
+        for message in &messages {
+            client.send_acked(&self.device, message)?;
+        }
+
+        self.shown.clone_from(&self.zones);
+        Ok(messages.len())
+    }
+}
@@ -0,0 +1,369 @@
+use std::{
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    client::{ClientError, Device, LifxClient},
+    light::Light,
+    products::TemperatureRange,
+    Hsbk,
+};
+
+/// Where on Earth the lights are, in degrees, with north and east positive.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Location {
+    pub fn new(latitude: f64, longitude: f64) -> Self {
+        Location { latitude, longitude }
+    }
+}
+
+// Julian date of the J2000 epoch, noon UTC on 1 January 2000, and of the Unix epoch
+const J2000: f64 = 2451545.0;
+const UNIX_EPOCH_JULIAN: f64 = 2440587.5;
+const SECONDS_PER_DAY: f64 = 86400.0;
+
+// The sun counts as risen when its upper edge clears the horizon, with refraction lifting it by about half a degree
+const SUNRISE_ELEVATION: f64 = -0.833;
+const AXIAL_TILT: f64 = 23.4397;
+
+/// When the sun rises and sets on one day, worked out from the standard sunrise equation.
+///
+/// The day is a solar day at the location, running from one solar midnight to the next, so no time zone is needed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SunTimes {
+    /// When the sun is highest
+    pub solar_noon: SystemTime,
+    /// `None` when the sun stays up or stays down all day, near the poles
+    pub sunrise: Option<SystemTime>,
+    pub sunset: Option<SystemTime>,
+    /// Height of the sun above the horizon at solar noon, in degrees, which tells a polar day (above the horizon)
+    /// from a polar night (below it) when there is no sunrise
+    pub noon_elevation: f64,
+}
+
+impl SunTimes {
+    /// The sun times of the solar day whose noon is nearest `at`.
+    pub fn on(location: Location, at: SystemTime) -> Self {
+        let julian = unix_seconds(at) / SECONDS_PER_DAY + UNIX_EPOCH_JULIAN;
+        let day = (julian - J2000 + location.longitude / 360.0).round();
+
+        let mean_noon = day - location.longitude / 360.0;
+        let anomaly = (357.5291 + 0.98560028 * mean_noon).rem_euclid(360.0).to_radians();
+        let center = 1.9148 * anomaly.sin() + 0.02 * (2.0 * anomaly).sin() + 0.0003 * (3.0 * anomaly).sin();
+        let ecliptic_longitude = (anomaly.to_degrees() + center + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+        let transit = J2000 + mean_noon + 0.0053 * anomaly.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+        let declination = (ecliptic_longitude.sin() * AXIAL_TILT.to_radians().sin()).asin();
+        let latitude = location.latitude.to_radians();
+        let hour_angle = (SUNRISE_ELEVATION.to_radians().sin() - latitude.sin() * declination.sin()) / (latitude.cos() * declination.cos());
+
+        let (sunrise, sunset) = if (-1.0..=1.0).contains(&hour_angle) {
+            let half_day = hour_angle.acos().to_degrees() / 360.0;
+            (Some(from_julian(transit - half_day)), Some(from_julian(transit + half_day)))
+        } else {
+            (None, None)
+        };
+
+        SunTimes {
+            solar_noon: from_julian(transit),
+            sunrise,
+            sunset,
+            noon_elevation: 90.0 - (location.latitude - declination.to_degrees()).abs(),
+        }
+    }
+
+    /// Whether the sun stays above the horizon all day.
+    pub fn is_polar_day(&self) -> bool {
+        self.sunrise.is_none() && self.noon_elevation > SUNRISE_ELEVATION
+    }
+
+    /// Whether the sun stays below the horizon all day.
+    pub fn is_polar_night(&self) -> bool {
+        self.sunrise.is_none() && self.noon_elevation <= SUNRISE_ELEVATION
+    }
+}
+
+fn unix_seconds(at: SystemTime) -> f64 {
+    match at.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs_f64(),
+        Err(err) => -err.duration().as_secs_f64(),
+    }
+}
+
+fn from_julian(julian: f64) -> SystemTime {
+    let seconds = (julian - UNIX_EPOCH_JULIAN) * SECONDS_PER_DAY;
+    if seconds >= 0.0 {
+        UNIX_EPOCH + Duration::from_secs_f64(seconds)
+    } else {
+        UNIX_EPOCH - Duration::from_secs_f64(-seconds)
+    }
+}
+
+/// How far through the day or night `at` is: 1 at solar noon, 0 at sunrise and sunset and -1 at solar midnight,
+/// following a parabola in between. A polar day stays above 0 and a polar night below it.
+pub fn day_position(location: Location, at: SystemTime) -> f64 {
+    const HALF_DAY: f64 = SECONDS_PER_DAY / 2.0;
+
+    let sun = SunTimes::on(location, at);
+    let noon = unix_seconds(sun.solar_noon);
+    let (sunrise, sunset) = match (sun.sunrise, sun.sunset) {
+        (Some(sunrise), Some(sunset)) => (unix_seconds(sunrise), unix_seconds(sunset)),
+        _ if sun.is_polar_day() => (noon - HALF_DAY, noon + HALF_DAY),
+        _ => (noon, noon),
+    };
+
+    let now = unix_seconds(at);
+    let (middle, edge) = if now < sunrise {
+        (noon - HALF_DAY, sunrise)
+    } else if now > sunset {
+        (noon + HALF_DAY, sunset)
+    } else if now < noon {
+        (noon, sunrise)
+    } else {
+        (noon, sunset)
+    };
+
+    let half = (edge - middle).abs();
+    let position = if half > 0.0 { (1.0 - ((now - middle) / half).powi(2)).clamp(0.0, 1.0) } else { 0.0 };
+
+    if (sunrise..=sunset).contains(&now) {
+        position
+    } else {
+        -position
+    }
+}
+
+/// How colour temperature and brightness follow the sun.
+///
+/// Through the day the temperature rises from `min_kelvin` at sunrise to `max_kelvin` at noon and back, at full
+/// `max_brightness`. Through the night it stays at `min_kelvin` while brightness dims to `min_brightness` at
+/// midnight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Curve {
+    pub min_kelvin: u16,
+    pub max_kelvin: u16,
+    /// Brightness from 0.0 to 1.0
+    pub min_brightness: f32,
+    pub max_brightness: f32,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Curve { min_kelvin: 2200, max_kelvin: 5500, min_brightness: 0.3, max_brightness: 1.0 }
+    }
+}
+
+impl Curve {
+    /// The white to show at `at`.
+    pub fn color_at(&self, location: Location, at: SystemTime) -> Hsbk {
+        let position = day_position(location, at) as f32;
+        let (kelvin, brightness) = if position > 0.0 {
+            (self.min_kelvin as f32 + (self.max_kelvin as f32 - self.min_kelvin as f32) * position, self.max_brightness)
+        } else {
+            (self.min_kelvin as f32, self.max_brightness + (self.min_brightness - self.max_brightness) * -position)
+        };
+
+        Hsbk::new(0, 0, (brightness.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16, kelvin.round() as u16)
+    }
+}
+
+// How far a light's colour can drift from what it was sent, through rounding in the firmware, before it counts as
+// changed by someone else
+const BRIGHTNESS_TOLERANCE: u16 = 655;
+const KELVIN_TOLERANCE: u16 = 50;
+
+// The curve's white with its kelvin brought within what the light can show, which the client would otherwise refuse
+fn within(color: Hsbk, range: Option<TemperatureRange>) -> Hsbk {
+    match range {
+        Some(range) => Hsbk { kelvin: color.kelvin.clamp(range.min, range.max), ..color },
+        None => color,
+    }
+}
+
+fn close(a: Hsbk, b: Hsbk) -> bool {
+    let hue_matters = a.saturation > BRIGHTNESS_TOLERANCE || b.saturation > BRIGHTNESS_TOLERANCE;
+
+    a.saturation.abs_diff(b.saturation) <= BRIGHTNESS_TOLERANCE
+        && a.brightness.abs_diff(b.brightness) <= BRIGHTNESS_TOLERANCE
+        && a.kelvin.abs_diff(b.kelvin) <= KELVIN_TOLERANCE
+        && (!hue_matters || a.hue.abs_diff(b.hue).min(u16::MAX - a.hue.abs_diff(b.hue)) <= BRIGHTNESS_TOLERANCE)
+}
+
+#[derive(Debug)]
+struct Tracked {
+    light: Light,
+    // What the light was last sent, and when its transition to it ends
+    sent: Option<(Hsbk, Instant)>,
+    overridden_until: Option<Instant>,
+}
+
+/// What one [`CircadianScheduler::apply`] did.
+#[derive(Debug, Default)]
+pub struct Applied {
+    /// Lights sent a new colour
+    pub updated: Vec<Device>,
+    /// Lights left alone because someone else changed them
+    pub overridden: Vec<Device>,
+    pub failed: Vec<(Device, ClientError)>,
+}
+
+/// Keeps a set of lights on the white that suits the time of day, fading gently from one to the next.
+///
+/// A light someone changes by other means, such as the LIFX app or a switch, is left alone for the override hold
+/// and then brought back onto the curve. Lights that are off get their colour without a fade, so they come on at
+/// the right white; their power is never changed.
+#[derive(Debug)]
+pub struct CircadianScheduler {
+    location: Location,
+    curve: Curve,
+    transition: Duration,
+    interval: Duration,
+    override_hold: Duration,
+    lights: Vec<Tracked>,
+}
+
+impl CircadianScheduler {
+    /// A scheduler with no lights, fading over 30 seconds on every update, updating once a minute and holding off
+    /// overridden lights for an hour.
+    pub fn new(location: Location, curve: Curve) -> Self {
+        CircadianScheduler {
+            location,
+            curve,
+            transition: Duration::from_secs(30),
+            interval: Duration::from_secs(60),
+            override_hold: Duration::from_secs(3600),
+            lights: Vec::new(),
+        }
+    }
+
+    pub fn set_transition(&mut self, transition: Duration) -> &mut Self {
+        self.transition = transition;
+        self
+    }
+
+    /// How often [`CircadianScheduler::run`] updates the lights.
+    pub fn set_interval(&mut self, interval: Duration) -> &mut Self {
+        self.interval = interval;
+        self
+    }
+
+    /// How long a light changed by someone else is left alone.
+    pub fn set_override_hold(&mut self, hold: Duration) -> &mut Self {
+        self.override_hold = hold;
+        self
+    }
+
+    pub fn location(&self) -> Location {
+        self.location
+    }
+
+    pub fn curve(&self) -> &Curve {
+        &self.curve
+    }
+
+    pub fn add(&mut self, device: Device) {
+        if !self.lights.iter().any(|tracked| tracked.light.device().target == device.target) {
+            self.lights.push(Tracked { light: Light::new(device), sent: None, overridden_until: None });
+        }
+    }
+
+    pub fn remove(&mut self, device: &Device) {
+        self.lights.retain(|tracked| tracked.light.device().target != device.target);
+    }
+
+    pub fn is_overridden(&self, device: &Device) -> bool {
+        let now = Instant::now();
+        self.tracked(device).and_then(|tracked| tracked.overridden_until).is_some_and(|until| until > now)
+    }
+
+    /// Brings an overridden light back onto the curve at the next update, without waiting out the hold.
+    pub fn resume(&mut self, device: &Device) {
+        if let Some(tracked) = self.lights.iter_mut().find(|tracked| tracked.light.device().target == device.target) {
+            tracked.overridden_until = None;
+        }
+    }
+
+    fn tracked(&self, device: &Device) -> Option<&Tracked> {
+        self.lights.iter().find(|tracked| tracked.light.device().target == device.target)
+    }
+
+    /// Reads every light and sends the ones that aren't on the curve for `at` their new colour, with the kelvin
+    /// kept within what each light can show.
+    pub fn apply(&mut self, client: &mut LifxClient, at: SystemTime) -> Applied {
+        let color = self.curve.color_at(self.location, at);
+        let now = Instant::now();
+        let mut applied = Applied::default();
+
+        for tracked in &mut self.lights {
+            let device = *tracked.light.device();
+            if tracked.overridden_until.is_some_and(|until| until > now) {
+                applied.overridden.push(device);
+                continue;
+            }
+
+            let state = match tracked.light.get_state(client) {
+                Ok(state) => state.clone(),
+                Err(err) => {
+                    applied.failed.push((device, err));
+                    continue;
+                }
+            };
+            let target = match client.product(&device) {
+                Ok(info) => within(color, info.and_then(|info| info.features.temperature_range)),
+                Err(err) => {
+                    applied.failed.push((device, err));
+                    continue;
+                }
+            };
+
+            // Mid-fade the light is somewhere between colours, so it can only be checked once the fade is over
+            if let Some((sent, settled_at)) = tracked.sent {
+                if now >= settled_at && !close(state.color, sent) {
+                    tracked.sent = None;
+                    tracked.overridden_until = Some(now + self.override_hold);
+                    applied.overridden.push(device);
+                    continue;
+                }
+            }
+
+            if close(state.color, target) {
+                tracked.sent = Some((state.color, now));
+                continue;
+            }
+
+            let transition = if state.is_on() { self.transition } else { Duration::ZERO };
+            match tracked.light.set_color(client, target, transition) {
+                Ok(()) => {
+                    tracked.sent = Some((target, now + transition));
+                    applied.updated.push(device);
+                }
+                Err(err) => applied.failed.push((device, err)),
+            }
+        }
+
+        applied
+    }
+
+    /// Updates the lights once per interval until `limit` has passed, returning how many updates were sent.
+    /// Lights that fail are tried again at the next update.
+    pub fn run(&mut self, client: &mut LifxClient, limit: Duration) -> usize {
+        let start = Instant::now();
+        let mut updates = 0;
+
+        loop {
+            updates += self.apply(client, SystemTime::now()).updated.len();
+
+            let elapsed = start.elapsed();
+            if elapsed >= limit {
+                return updates;
+            }
+            thread::sleep(self.interval.min(limit - elapsed));
+        }
+    }
+}
@@ -0,0 +1,467 @@
+use std::{
+    collections::HashMap,
+    fmt, io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use lifx_serialization::{LifxDeserializationError, LifxPayload, LifxResponse};
+
+use crate::{
+    deserialize_lifx_packet_as,
+    firmware_effects::{MultiZoneEffect, TileEffect},
+    messages::{responses, DeviceRequest, DeviceResponse, Message},
+    products::{self, ProductInfo, Unsupported},
+    serialize_lifx_packet, LifxHeader, LifxRequestOptions,
+};
+
+pub const MAX_PACKET_SIZE: usize = 36 + crate::messages::MAX_PAYLOAD_SIZE;
+
+/// A device on the network, identified by its serial number and the address it answers from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Device {
+    pub target: [u8; 8],
+    pub address: SocketAddr,
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Io(io::Error),
+    Deserialization(LifxDeserializationError),
+    /// No reply arrived after every retry
+    Timeout,
+    /// The device does not support the packet type that was sent
+    Unhandled(u16),
+    /// The requested response type is never sent in reply to this message
+    ResponseMismatch { request: u16, response: u16 },
+    /// The device replied with a packet other than the one requested, boxed as zone and tile replies are large
+    UnexpectedResponse(Box<DeviceResponse>),
+    /// The device can't carry out the message, so it was not sent
+    Unsupported(Unsupported),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Io(err) => write!(f, "I/O error: {}", err),
+            ClientError::Deserialization(err) => write!(f, "Failed to decode reply: {}", err),
+            ClientError::Timeout => write!(f, "Timed out waiting for a reply"),
+            ClientError::Unhandled(packet_number) => write!(f, "Device does not handle packet #{}", packet_number),
+            ClientError::ResponseMismatch { request, response } => {
+                write!(f, "Packet #{} is not a response to packet #{}", response, request)
+            }
+            ClientError::UnexpectedResponse(message) => write!(f, "Unexpected response: {}", message.name()),
+            ClientError::Unsupported(reason) => write!(f, "Unsupported message: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Io(err) => Some(err),
+            ClientError::Deserialization(err) => Some(err),
+            ClientError::Unsupported(reason) => Some(reason),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(err: io::Error) -> Self {
+        ClientError::Io(err)
+    }
+}
+
+impl From<LifxDeserializationError> for ClientError {
+    fn from(err: LifxDeserializationError) -> Self {
+        ClientError::Deserialization(err)
+    }
+}
+
+/// How a burst of packets from [`LifxClient::send_burst`] went out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Burst {
+    /// Packets put on the wire, which is one for a broadcast
+    pub packets: usize,
+    /// Time between sending the first packet and the last
+    pub skew: Duration,
+}
+
+/// Blocking UDP client that sends messages to devices and matches up their replies.
+pub struct LifxClient {
+    socket: UdpSocket,
+    source: u32,
+    sequence: u8,
+    timeout: Duration,
+    retries: u8,
+    min_interval: Option<Duration>,
+    last_sent: HashMap<SocketAddr, Instant>,
+    validate: bool,
+    // `None` for devices the product catalogue doesn't know
+    products: HashMap<[u8; 8], Option<ProductInfo>>,
+    effects_started: u32,
+}
+
+impl LifxClient {
+    pub fn bind<A: ToSocketAddrs>(address: A) -> Result<Self, ClientError> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_broadcast(true)?;
+
+        Ok(LifxClient {
+            socket,
+            source: LifxRequestOptions::default().source,
+            sequence: 0,
+            timeout: Duration::from_millis(500),
+            retries: 2,
+            // LIFX recommends sending no more than 20 messages a second to each device
+            min_interval: Some(Duration::from_millis(50)),
+            last_sent: HashMap::new(),
+            validate: true,
+            products: HashMap::new(),
+            effects_started: 0,
+        })
+    }
+
+    pub fn set_source(&mut self, source: u32) -> &mut Self {
+        self.source = source;
+        self
+    }
+
+    /// How long to wait for each reply before retrying.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// How many times a message is resent when no reply arrives.
+    pub fn set_retries(&mut self, retries: u8) -> &mut Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Caps how many packets a second are sent to any one address, or removes the cap with `None`.
+    pub fn set_rate_limit(&mut self, per_second: Option<u32>) -> &mut Self {
+        self.min_interval = per_second.map(|rate| Duration::from_secs(1) / rate.max(1));
+        self
+    }
+
+    /// Whether messages are checked against what the device supports before they are sent. Turn this off to send
+    /// them anyway.
+    pub fn set_validation(&mut self, validate: bool) -> &mut Self {
+        self.validate = validate;
+        self
+    }
+
+    /// Looks the device up in the product catalogue, asking for its version and firmware the first time.
+    pub fn product(&mut self, device: &Device) -> Result<Option<ProductInfo>, ClientError> {
+        if let Some(info) = self.products.get(&device.target) {
+            return Ok(*info);
+        }
+
+        let info = match self.request::<responses::Version>(device, &DeviceRequest::GetVersion) {
+            Ok(version) => {
+                let firmware: responses::HostFirmware = self.request(device, &DeviceRequest::GetHostFirmware)?;
+                products::product_info(version.vendor, version.product, &firmware)
+            }
+            // Not a LIFX device as far as the catalogue is concerned
+            Err(ClientError::Unhandled(_)) => None,
+            Err(err) => return Err(err),
+        };
+
+        self.products.insert(device.target, info);
+        Ok(info)
+    }
+
+    /// Starts a firmware effect on a strip or beam, replacing whichever one it was playing.
+    pub fn set_multizone_effect(&mut self, device: &Device, effect: &MultiZoneEffect) -> Result<(), ClientError> {
+        let message = effect.to_message(self.next_effect_instance());
+        self.send_acked(device, &message)
+    }
+
+    /// The firmware effect a strip or beam is playing.
+    pub fn multizone_effect(&mut self, device: &Device) -> Result<MultiZoneEffect, ClientError> {
+        let state: responses::StateMultiZoneEffect = self.request(device, &DeviceRequest::GetMultiZoneEffect)?;
+        Ok(MultiZoneEffect::from(&state))
+    }
+
+    /// Starts a firmware effect on a matrix device, replacing whichever one it was playing.
+    pub fn set_tile_effect(&mut self, device: &Device, effect: &TileEffect) -> Result<(), ClientError> {
+        let message = effect.to_message(self.next_effect_instance());
+        self.send_acked(device, &message)
+    }
+
+    /// The firmware effect a matrix device is playing.
+    pub fn tile_effect(&mut self, device: &Device) -> Result<TileEffect, ClientError> {
+        let message = DeviceRequest::GetTileEffect { reserved_6: 0, reserved_7: 0 };
+        let state: responses::StateTileEffect = self.request(device, &message)?;
+        Ok(TileEffect::from(&state))
+    }
+
+    // Unique to this client, since the source is random, so every effect it starts is a new instance
+    fn next_effect_instance(&mut self) -> u32 {
+        self.effects_started = self.effects_started.wrapping_add(1);
+        self.source.wrapping_add(self.effects_started)
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, ClientError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    /// Broadcasts GetService to `broadcast` and returns every device that answers within the timeout, resending
+    /// once per retry so devices whose reply was lost get another chance.
+    pub fn discover<A: ToSocketAddrs>(&mut self, broadcast: A) -> Result<Vec<Device>, ClientError> {
+        let broadcast = broadcast
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no broadcast address given"))?;
+
+        let options = self.next_options([0; 8], false, false);
+        let mut devices: Vec<Device> = Vec::new();
+
+        for _ in 0..=self.retries {
+            self.send_packet(&options, &DeviceRequest::GetService, broadcast)?;
+
+            let deadline = Instant::now() + self.timeout;
+            while let Some((header, reply, from)) = self.receive_until(deadline)? {
+                if header.source != options.source || header.sequence != options.sequence {
+                    continue;
+                }
+
+                // Service 1 is UDP, the only transport devices offer in practice
+                if let DeviceResponse::StateService { service: 1, port } = reply {
+                    if !devices.iter().any(|device| device.target == header.target) {
+                        devices.push(Device { target: header.target, address: SocketAddr::new(from.ip(), port as u16) });
+                    }
+                }
+            }
+        }
+
+        Ok(devices)
+    }
+
+    /// Sends a message without waiting for any reply.
+    pub fn send(&mut self, device: &Device, message: &DeviceRequest) -> Result<(), ClientError> {
+        self.check(device, message)?;
+        let options = self.next_options(device.target, false, false);
+        self.send_packet(&options, message, device.address)
+    }
+
+    /// Sends a message with ack_required set and waits for the Acknowledgement.
+    pub fn send_acked(&mut self, device: &Device, message: &DeviceRequest) -> Result<(), ClientError> {
+        self.check(device, message)?;
+        let options = self.next_options(device.target, true, false);
+
+        self.exchange(&options, message, device.address, |reply| match reply {
+            DeviceResponse::Acknowledgement => Some(Ok(())),
+            _ => None,
+        })
+    }
+
+    /// Sends a message and returns the device's reply, which must be one of the responses listed for it.
+    pub fn request_message(&mut self, device: &Device, message: &DeviceRequest) -> Result<DeviceResponse, ClientError> {
+        self.check(device, message)?;
+
+        // Get messages always reply, but Set messages only do so with res_required
+        let options = self.next_options(device.target, false, true);
+        let expected = message.response_packet_numbers();
+
+        self.exchange(&options, message, device.address, |reply| {
+            if expected.contains(&reply.packet_number()) {
+                Some(Ok(reply))
+            } else {
+                match reply {
+                    DeviceResponse::Acknowledgement => None,
+                    other => Some(Err(ClientError::UnexpectedResponse(Box::new(other)))),
+                }
+            }
+        })
+    }
+
+    /// Sends a message answered by several packets, such as GetColorZones, collecting replies until `complete` says
+    /// they are all in. Replies that arrive after a resend are added to the ones already collected.
+    pub fn request_replies(
+        &mut self,
+        device: &Device,
+        message: &DeviceRequest,
+        mut complete: impl FnMut(&[DeviceResponse]) -> bool,
+    ) -> Result<Vec<DeviceResponse>, ClientError> {
+        self.check(device, message)?;
+
+        let options = self.next_options(device.target, false, true);
+        let expected = message.response_packet_numbers();
+        let mut replies = Vec::new();
+
+        self.exchange(&options, message, device.address, |reply| {
+            if !expected.contains(&reply.packet_number()) {
+                return None;
+            }
+
+            replies.push(reply);
+            complete(&replies).then(|| Ok(core::mem::take(&mut replies)))
+        })
+    }
+
+    /// Sends a message and decodes the reply as `R`, e.g. `client.request::<responses::LightState>(&device, &DeviceRequest::GetColor)`.
+    pub fn request<R: LifxResponse<Payload = Message>>(&mut self, device: &Device, message: &DeviceRequest) -> Result<R, ClientError> {
+        if !message.response_packet_numbers().contains(&R::PACKET_NUMBER) {
+            return Err(ClientError::ResponseMismatch {
+                request: message.packet_number(),
+                response: R::PACKET_NUMBER,
+            });
+        }
+
+        let reply = self.request_message(device, message)?;
+
+        // The reply came off the wire as a DeviceResponse, so converting it back cannot fail
+        R::from_payload(reply.into()).map_err(|other| ClientError::UnexpectedResponse(Box::new(other.try_into().unwrap())))
+    }
+
+    /// Sends each message to its device as close together as possible, so a scene change lands everywhere at once
+    /// instead of rippling across the room. Packets are validated and encoded up front and sent without waiting
+    /// for replies.
+    ///
+    /// If every message is the same and `broadcast` is given, one tagged packet goes to that address instead.
+    /// Every device on that network acts on it, not only the ones listed, so only pass it when that is intended.
+    pub fn send_burst(&mut self, messages: &[(Device, DeviceRequest)], broadcast: Option<SocketAddr>) -> Result<Burst, ClientError> {
+        for (device, message) in messages {
+            self.check(device, message)?;
+        }
+
+        let mut packets = Vec::new();
+        match (broadcast, messages.first()) {
+            (Some(address), Some((_, first))) if messages.iter().all(|(_, message)| message == first) => {
+                let options = self.next_options([0; 8], false, false);
+                packets.push((encode(&options, first), address));
+            }
+            _ => {
+                for (device, message) in messages {
+                    let options = self.next_options(device.target, false, false);
+                    packets.push((encode(&options, message), device.address));
+                }
+            }
+        }
+
+        // Wait out the rate limit for every device first so nothing holds up the burst itself
+        if let Some(interval) = self.min_interval {
+            let ready = packets.iter().filter_map(|(_, address)| self.last_sent.get(address)).max().map(|last| *last + interval);
+            if let Some(wait) = ready.and_then(|ready| ready.checked_duration_since(Instant::now())) {
+                std::thread::sleep(wait);
+            }
+        }
+
+        let start = Instant::now();
+        let mut end = start;
+        for (packet, address) in &packets {
+            self.socket.send_to(packet, address)?;
+            end = Instant::now();
+        }
+
+        for (_, address) in &packets {
+            self.last_sent.insert(*address, end);
+        }
+
+        Ok(Burst { packets: packets.len(), skew: end - start })
+    }
+
+    fn check(&mut self, device: &Device, message: &DeviceRequest) -> Result<(), ClientError> {
+        // Broadcasts reach every kind of device, so there is no one product to check against
+        if !self.validate || device.target == [0; 8] || !products::depends_on_features(message) {
+            return Ok(());
+        }
+
+        match self.product(device)? {
+            Some(info) => info.features.check(message).map_err(ClientError::Unsupported),
+            None => Ok(()),
+        }
+    }
+
+    fn next_options(&mut self, target: [u8; 8], ack_required: bool, res_required: bool) -> LifxRequestOptions {
+        self.sequence = self.sequence.wrapping_add(1);
+
+        LifxRequestOptions {
+            tagged: target == [0; 8],
+            source: self.source,
+            target,
+            ack_required,
+            res_required,
+            sequence: self.sequence,
+        }
+    }
+
+    fn send_packet(&mut self, options: &LifxRequestOptions, message: &DeviceRequest, address: SocketAddr) -> Result<(), ClientError> {
+        if let (Some(interval), Some(last)) = (self.min_interval, self.last_sent.get(&address)) {
+            let ready = *last + interval;
+            let now = Instant::now();
+            if ready > now {
+                std::thread::sleep(ready - now);
+            }
+        }
+
+        self.socket.send_to(&encode(options, message), address)?;
+        self.last_sent.insert(address, Instant::now());
+        Ok(())
+    }
+
+    /// Receives the next packet that arrives before `deadline`, or `None` on timeout.
+    fn receive_until(&self, deadline: Instant) -> Result<Option<(LifxHeader, DeviceResponse, SocketAddr)>, ClientError> {
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+
+            self.socket.set_read_timeout(Some(deadline - now))?;
+
+            let (size, address) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+            match deserialize_lifx_packet_as::<DeviceResponse>(&buffer[..size]) {
+                Ok((header, message)) => return Ok(Some((header, message, address))),
+                Err(err) => log::debug!("Dropping undecodable packet from {}: {}", address, err),
+            }
+        }
+    }
+
+    // Sends `message` and feeds every reply to it into `handle` until that returns a result, resending on timeout.
+    fn exchange<T>(
+        &mut self,
+        options: &LifxRequestOptions,
+        message: &DeviceRequest,
+        address: SocketAddr,
+        mut handle: impl FnMut(DeviceResponse) -> Option<Result<T, ClientError>>,
+    ) -> Result<T, ClientError> {
+        for _ in 0..=self.retries {
+            self.send_packet(options, message, address)?;
+
+            let deadline = Instant::now() + self.timeout;
+            while let Some((header, reply, _)) = self.receive_until(deadline)? {
+                if header.source != options.source || header.sequence != options.sequence {
+                    continue;
+                }
+
+                if let DeviceResponse::Unhandled { unhandled_type } = reply {
+                    return Err(ClientError::Unhandled(unhandled_type));
+                }
+
+                if let Some(result) = handle(reply) {
+                    return result;
+                }
+            }
+        }
+
+        Err(ClientError::Timeout)
+    }
+}
+
+fn encode(options: &LifxRequestOptions, message: &DeviceRequest) -> Vec<u8> {
+    let mut buffer = [0u8; MAX_PACKET_SIZE];
+    serialize_lifx_packet(options, message, &mut buffer);
+
+    buffer[..36 + message.size()].to_vec()
+}
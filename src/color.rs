@@ -0,0 +1,87 @@
+use lifx_serialization::LifxField;
+
+/// A colour as LIFX devices represent it: hue, saturation and brightness scaled to the full u16 range, plus
+/// kelvin for whites.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Hsbk {
+    pub hue: u16,
+    pub saturation: u16,
+    pub brightness: u16,
+    pub kelvin: u16,
+}
+
+impl Hsbk {
+    pub const fn new(hue: u16, saturation: u16, brightness: u16, kelvin: u16) -> Self {
+        Hsbk { hue, saturation, brightness, kelvin }
+    }
+
+    /// The colour of an 8 bit RGB pixel, as found in images. Greys and white come out with no saturation, so
+    /// they show as a white of `kelvin`.
+    pub fn from_rgb(red: u8, green: u8, blue: u8, kelvin: u16) -> Hsbk {
+        let (red, green, blue) = (red as f32 / 255.0, green as f32 / 255.0, blue as f32 / 255.0);
+        let max = red.max(green).max(blue);
+        let delta = max - red.min(green).min(blue);
+
+        // Hue in sixths of the way round the wheel
+        let sixths = if delta == 0.0 {
+            0.0
+        } else if max == red {
+            let sixths = (green - blue) / delta;
+            if sixths < 0.0 {
+                sixths + 6.0
+            } else {
+                sixths
+            }
+        } else if max == green {
+            (blue - red) / delta + 2.0
+        } else {
+            (red - green) / delta + 4.0
+        };
+
+        Hsbk {
+            hue: (sixths / 6.0 * 65536.0 + 0.5) as u32 as u16,
+            saturation: if max == 0.0 { 0 } else { (delta / max * 65535.0 + 0.5) as u16 },
+            brightness: (max * 65535.0 + 0.5) as u16,
+            kelvin,
+        }
+    }
+
+    /// Colour part way between `self` (at 0.0) and `to` (at 1.0), turning hue the shorter way around the wheel.
+    pub fn lerp(&self, to: &Hsbk, progress: f32) -> Hsbk {
+        let progress = progress.clamp(0.0, 1.0);
+
+        let hue_distance = to.hue.wrapping_sub(self.hue) as i16;
+
+        Hsbk {
+            hue: self.hue.wrapping_add((hue_distance as f32 * progress) as i16 as u16),
+            saturation: lerp_u16(self.saturation, to.saturation, progress),
+            brightness: lerp_u16(self.brightness, to.brightness, progress),
+            kelvin: lerp_u16(self.kelvin, to.kelvin, progress),
+        }
+    }
+}
+
+impl LifxField for Hsbk {
+    const SIZE: usize = 8;
+
+    fn read(bytes: &[u8]) -> Self {
+        Hsbk {
+            hue: u16::read(&bytes[0..2]),
+            saturation: u16::read(&bytes[2..4]),
+            brightness: u16::read(&bytes[4..6]),
+            kelvin: u16::read(&bytes[6..8]),
+        }
+    }
+
+    fn write(&self, buffer: &mut [u8]) {
+        self.hue.write(&mut buffer[0..2]);
+        self.saturation.write(&mut buffer[2..4]);
+        self.brightness.write(&mut buffer[4..6]);
+        self.kelvin.write(&mut buffer[6..8]);
+    }
+}
+
+pub(crate) fn lerp_u16(from: u16, to: u16, progress: f32) -> u16 {
+    (from as f32 + (to as f32 - from as f32) * progress.clamp(0.0, 1.0) + 0.5) as u16
+}
@@ -0,0 +1,505 @@
+use std::{
+    f32::consts::TAU,
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    client::{ClientError, Device, LifxClient},
+    light::milliseconds,
+    messages::TILE_COLORS,
+    products::{Capability, Unsupported},
+    rng::Rng,
+    scene::{self, DeviceScene},
+    DeviceRequest, Hsbk,
+};
+
+/// Shapes of SetWaveform, which the firmware plays by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Waveform {
+    Saw = 0,
+    Sine = 1,
+    HalfSine = 2,
+    Triangle = 3,
+    Pulse = 4,
+}
+
+/// An effect a single light can play from one SetWaveform instead of being sent every frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NativeEffect {
+    /// Colour the light is set to first and returns to after each cycle
+    pub base: Hsbk,
+    /// Colour the waveform swings to
+    pub color: Hsbk,
+    pub period: Duration,
+    pub cycles: f32,
+    /// From 0.0 to 1.0; for [`Waveform::Pulse`] the share of each cycle spent at `color`
+    pub skew_ratio: f32,
+    pub waveform: Waveform,
+}
+
+impl NativeEffect {
+    /// The SetColor to `base` and the SetWaveform that together play the effect.
+    pub fn messages(&self) -> [DeviceRequest; 2] {
+        let Hsbk { hue, saturation, brightness, kelvin } = self.base;
+        let base = DeviceRequest::SetColor { reserved_6: 0, hue, saturation, brightness, kelvin, duration_ms: 0 };
+
+        let Hsbk { hue, saturation, brightness, kelvin } = self.color;
+        let waveform = DeviceRequest::SetWaveform {
+            reserved_6: 0,
+            transient: 1,
+            hue,
+            saturation,
+            brightness,
+            kelvin,
+            period_ms: milliseconds(self.period),
+            cycles: self.cycles,
+            // Scaled from 0.0..1.0 onto the whole i16 range
+            skew_ratio: (self.skew_ratio.clamp(0.0, 1.0) * 65535.0 - 32768.0) as i16,
+            waveform: self.waveform as u8,
+        };
+
+        [base, waveform]
+    }
+}
+
+/// Something that changes colours over time.
+///
+/// Each frame every pixel of the device is rendered in order: one for a bulb, one per zone for a strip, and 64 per
+/// tile, row by row, for a tile chain.
+pub trait Effect {
+    /// Draws the frame `elapsed` into the effect. `pixels` arrive holding what is underneath: the layers below, or
+    /// the device's colours from before the effect started.
+    fn render(&mut self, elapsed: Duration, pixels: &mut [Hsbk]);
+
+    /// How long the effect plays, or `None` to play until stopped.
+    fn duration(&self) -> Option<Duration> {
+        None
+    }
+
+    /// The same effect as a SetWaveform, if the firmware can play it by itself.
+    fn native(&self) -> Option<NativeEffect> {
+        None
+    }
+}
+
+/// How a layer combines with the layers below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Blend {
+    /// The layer's colours replace the ones below
+    Replace,
+    /// Only the layer's brightness is used, scaling the brightness of the colours below, so a breathing layer can
+    /// pulse a colour cycle
+    Brightness,
+}
+
+struct Layer {
+    effect: Box<dyn Effect>,
+    blend: Blend,
+    opacity: f32,
+}
+
+/// Effects stacked on top of each other, bottom first. Layers are an effect themselves, so they nest.
+#[derive(Default)]
+pub struct Layers {
+    layers: Vec<Layer>,
+}
+
+impl Layers {
+    pub fn new() -> Self {
+        Layers::default()
+    }
+
+    /// Adds `effect` on top, mixed with the layers below by `opacity` from 0.0 to 1.0.
+    pub fn with(mut self, effect: impl Effect + 'static, blend: Blend, opacity: f32) -> Self {
+        self.layers.push(Layer { effect: Box::new(effect), blend, opacity: opacity.clamp(0.0, 1.0) });
+        self
+    }
+}
+
+impl Effect for Layers {
+    fn render(&mut self, elapsed: Duration, pixels: &mut [Hsbk]) {
+        let mut layer_pixels = pixels.to_vec();
+
+        for layer in &mut self.layers {
+            layer_pixels.copy_from_slice(pixels);
+            layer.effect.render(elapsed, &mut layer_pixels);
+
+            for (pixel, drawn) in pixels.iter_mut().zip(&layer_pixels) {
+                let blended = match layer.blend {
+                    Blend::Replace => *drawn,
+                    Blend::Brightness => Hsbk {
+                        brightness: (pixel.brightness as u32 * drawn.brightness as u32 / u16::MAX as u32) as u16,
+                        ..*pixel
+                    },
+                };
+                *pixel = pixel.lerp(&blended, layer.opacity);
+            }
+        }
+    }
+
+    /// Until the longest layer finishes, or forever if any layer runs forever.
+    fn duration(&self) -> Option<Duration> {
+        self.layers.iter().try_fold(Duration::ZERO, |longest, layer| Some(longest.max(layer.effect.duration()?)))
+    }
+
+    /// A single opaque layer can still play natively.
+    fn native(&self) -> Option<NativeEffect> {
+        match self.layers.as_slice() {
+            [Layer { effect, blend: Blend::Replace, opacity }] if *opacity >= 1.0 => effect.native(),
+            _ => None,
+        }
+    }
+}
+
+fn fill(pixels: &mut [Hsbk], color: Hsbk) {
+    pixels.iter_mut().for_each(|pixel| *pixel = color);
+}
+
+// How far through its current cycle an effect with this period is, from 0.0 to 1.0
+fn phase(elapsed: Duration, period: Duration) -> f32 {
+    if period.is_zero() {
+        return 0.0;
+    }
+
+    (elapsed.as_secs_f64() / period.as_secs_f64()).fract() as f32
+}
+
+// How long `cycles` periods take. Negative or NaN counts take no time, and a count too large for a `Duration`
+// runs until stopped.
+fn cycles_duration(period: Duration, cycles: f32) -> Option<Duration> {
+    Duration::try_from_secs_f32((period.as_secs_f32() * cycles).max(0.0)).ok()
+}
+
+/// Fades smoothly from one colour to another and back, once per period.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Breathe {
+    pub from: Hsbk,
+    pub to: Hsbk,
+    pub period: Duration,
+    /// Number of breaths, or `None` to breathe until stopped
+    pub cycles: Option<f32>,
+}
+
+impl Effect for Breathe {
+    fn render(&mut self, elapsed: Duration, pixels: &mut [Hsbk]) {
+        let progress = (1.0 - (phase(elapsed, self.period) * TAU).cos()) / 2.0;
+        fill(pixels, self.from.lerp(&self.to, progress));
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.cycles.and_then(|cycles| cycles_duration(self.period, cycles))
+    }
+
+    fn native(&self) -> Option<NativeEffect> {
+        Some(NativeEffect {
+            base: self.from,
+            color: self.to,
+            period: self.period,
+            cycles: self.cycles.unwrap_or(f32::MAX),
+            skew_ratio: 0.5,
+            waveform: Waveform::Sine,
+        })
+    }
+}
+
+/// Flashes a colour on and off over whatever is underneath.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strobe {
+    pub color: Hsbk,
+    pub period: Duration,
+    /// Share of each period the colour is on, from 0.0 to 1.0
+    pub duty: f32,
+    pub cycles: Option<f32>,
+}
+
+impl Effect for Strobe {
+    fn render(&mut self, elapsed: Duration, pixels: &mut [Hsbk]) {
+        if phase(elapsed, self.period) < self.duty {
+            fill(pixels, self.color);
+        } else {
+            pixels.iter_mut().for_each(|pixel| pixel.brightness = 0);
+        }
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        self.cycles.and_then(|cycles| cycles_duration(self.period, cycles))
+    }
+
+    fn native(&self) -> Option<NativeEffect> {
+        Some(NativeEffect {
+            base: Hsbk { brightness: 0, ..self.color },
+            color: self.color,
+            period: self.period,
+            cycles: self.cycles.unwrap_or(f32::MAX),
+            skew_ratio: self.duty,
+            waveform: Waveform::Pulse,
+        })
+    }
+}
+
+/// Turns the hue around the colour wheel once per period. With a `spread` the hues also fan out across the pixels,
+/// making a rainbow along a strip.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorCycle {
+    pub period: Duration,
+    /// How many times round the wheel the hues go from the first pixel to the last
+    pub spread: f32,
+    pub saturation: u16,
+    pub brightness: u16,
+    pub kelvin: u16,
+}
+
+impl Effect for ColorCycle {
+    fn render(&mut self, elapsed: Duration, pixels: &mut [Hsbk]) {
+        let start = phase(elapsed, self.period);
+        let count = pixels.len() as f32;
+
+        for (index, pixel) in pixels.iter_mut().enumerate() {
+            let turn = (start + self.spread * index as f32 / count).fract();
+            *pixel = Hsbk::new((turn * 65536.0) as u16, self.saturation, self.brightness, self.kelvin);
+        }
+    }
+}
+
+/// The uneven brightness of a candle flame, with every pixel flickering on its own.
+pub struct Candle {
+    color: Hsbk,
+    flicker: f32,
+    rng: Rng,
+    levels: Vec<f32>,
+}
+
+impl Candle {
+    /// A flame of `color` that dims by up to `flicker` of its brightness, from 0.0 to 1.0. The same seed always
+    /// flickers the same way.
+    pub fn new(color: Hsbk, flicker: f32, seed: u64) -> Self {
+        Candle { color, flicker: flicker.clamp(0.0, 1.0), rng: Rng::new(seed), levels: Vec::new() }
+    }
+}
+
+impl Effect for Candle {
+    fn render(&mut self, _elapsed: Duration, pixels: &mut [Hsbk]) {
+        self.levels.resize(pixels.len(), 0.0);
+
+        for (pixel, level) in pixels.iter_mut().zip(&mut self.levels) {
+            // Drift towards a new random level each frame, so the flame wavers rather than jumps
+            *level += (self.rng.unit() - *level) * 0.3;
+            let brightness = self.color.brightness as f32 * (1.0 - self.flicker * *level);
+            *pixel = Hsbk { brightness: brightness as u16, ..self.color };
+        }
+    }
+}
+
+/// Fades through a list of colours at set points in time, then holds the last one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Keyframes {
+    /// Times from the start, in order, with the colour to be at then
+    pub frames: Vec<(Duration, Hsbk)>,
+}
+
+impl Keyframes {
+    /// Deep red through orange to a white of `kelvin` at full brightness, over `duration`.
+    pub fn sunrise(duration: Duration, kelvin: u16) -> Self {
+        Keyframes {
+            frames: vec![
+                (Duration::ZERO, Hsbk::new(0, 65535, 0, 2500)),
+                (duration.mul_f32(0.3), Hsbk::new(2731, 65535, 13107, 2500)),
+                (duration.mul_f32(0.6), Hsbk::new(5461, 45875, 32768, 2500)),
+                (duration.mul_f32(0.8), Hsbk::new(7282, 19661, 52428, 2700)),
+                (duration, Hsbk::new(7282, 0, 65535, kelvin)),
+            ],
+        }
+    }
+
+    fn color_at(&self, elapsed: Duration) -> Option<Hsbk> {
+        let next = self.frames.iter().position(|(at, _)| *at > elapsed);
+
+        match next {
+            Some(0) => self.frames.first().map(|(_, color)| *color),
+            Some(index) => {
+                let ((from_at, from), (to_at, to)) = (self.frames[index - 1], self.frames[index]);
+                let progress = (elapsed - from_at).as_secs_f32() / (to_at - from_at).as_secs_f32();
+                Some(from.lerp(&to, progress))
+            }
+            None => self.frames.last().map(|(_, color)| *color),
+        }
+    }
+}
+
+impl Effect for Keyframes {
+    fn render(&mut self, elapsed: Duration, pixels: &mut [Hsbk]) {
+        if let Some(color) = self.color_at(elapsed) {
+            fill(pixels, color);
+        }
+    }
+
+    fn duration(&self) -> Option<Duration> {
+        Some(self.frames.last().map(|(at, _)| *at).unwrap_or_default())
+    }
+}
+
+/// The pixels a device has for effects to draw on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Surface {
+    Light,
+    Zones { count: usize, extended: bool },
+    Tiles { count: usize },
+}
+
+impl Surface {
+    pub fn pixels(&self) -> usize {
+        match *self {
+            Surface::Light => 1,
+            Surface::Zones { count, .. } => count,
+            Surface::Tiles { count } => count * TILE_COLORS,
+        }
+    }
+
+    // The messages that take a device from showing `from` to showing `to`
+    fn messages(&self, from: &[Hsbk], to: &[Hsbk], duration_ms: u32) -> Vec<DeviceRequest> {
+        match *self {
+            Surface::Light if from != to => {
+                let Hsbk { hue, saturation, brightness, kelvin } = to[0];
+                vec![DeviceRequest::SetColor { reserved_6: 0, hue, saturation, brightness, kelvin, duration_ms }]
+            }
+            Surface::Light => Vec::new(),
+            Surface::Zones { extended, .. } => scene::zone_messages(from, to, extended, duration_ms),
+            Surface::Tiles { .. } => {
+                let tiles = |pixels: &[Hsbk]| pixels.chunks(TILE_COLORS).map(<[Hsbk]>::to_vec).collect::<Vec<_>>();
+                scene::tile_messages(&tiles(from), &tiles(to), duration_ms)
+            }
+        }
+    }
+}
+
+struct Output {
+    device: Device,
+    surface: Surface,
+    effect: Box<dyn Effect>,
+    // What the device showed before the effect started, which every frame is drawn over
+    base: Vec<Hsbk>,
+    // What the device was last sent
+    shown: Vec<Hsbk>,
+    native: Option<NativeEffect>,
+    finished: bool,
+}
+
+/// What [`EffectEngine::run`] did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Played {
+    pub frames: usize,
+    pub messages: usize,
+}
+
+/// Plays effects on lights, strips and tiles by sending a frame at a steady rate.
+///
+/// Only what changed since the previous frame is sent, and each change fades over one frame so motion looks
+/// smooth. Frames are sent without acknowledgement, since a lost one is soon replaced by the next. The client's
+/// rate limit still applies, so a tile chain, which takes a message per tile, may need it raised to keep up.
+pub struct EffectEngine {
+    interval: Duration,
+    outputs: Vec<Output>,
+}
+
+impl EffectEngine {
+    pub fn new(frames_per_second: u32) -> Self {
+        EffectEngine { interval: Duration::from_secs(1) / frames_per_second.max(1), outputs: Vec::new() }
+    }
+
+    /// Plays `effect` on `device`, reading the device first to find its zones or tiles and what it shows now.
+    /// A bulb plays the effect natively when the effect allows it, and is then sent nothing more.
+    pub fn add(&mut self, client: &mut LifxClient, device: &Device, effect: impl Effect + 'static) -> Result<(), ClientError> {
+        let Some((scene, features)) = scene::read(client, device)? else {
+            return Err(ClientError::Unsupported(Unsupported::Missing(Capability::Light)));
+        };
+
+        let DeviceScene { color, zones, tiles, .. } = scene;
+        let (surface, base) = if !zones.is_empty() {
+            (Surface::Zones { count: zones.len(), extended: features.extended_multizone }, zones)
+        } else if !tiles.is_empty() {
+            (Surface::Tiles { count: tiles.len() }, tiles.concat())
+        } else {
+            (Surface::Light, vec![color])
+        };
+
+        self.add_surface(*device, surface, base, effect);
+        Ok(())
+    }
+
+    /// Plays `effect` on a device whose shape and current colours are already known.
+    pub fn add_surface(&mut self, device: Device, surface: Surface, base: Vec<Hsbk>, effect: impl Effect + 'static) {
+        let native = effect.native().filter(|_| surface == Surface::Light);
+
+        self.outputs.push(Output {
+            device,
+            surface,
+            effect: Box::new(effect),
+            shown: base.clone(),
+            base,
+            native,
+            finished: false,
+        });
+    }
+
+    /// Whether every effect has played to the end. Effects without an end never finish, unless they were handed
+    /// to the firmware to play.
+    pub fn is_finished(&self) -> bool {
+        self.outputs.iter().all(|output| output.finished)
+    }
+
+    /// Renders and sends the frame `elapsed` into the effects, returning how many messages that took.
+    pub fn frame(&mut self, client: &mut LifxClient, elapsed: Duration) -> Result<usize, ClientError> {
+        let duration_ms = milliseconds(self.interval);
+        let mut sent = 0;
+
+        for output in self.outputs.iter_mut().filter(|output| !output.finished) {
+            let end = output.effect.duration();
+
+            if let Some(native) = output.native.take() {
+                for message in native.messages() {
+                    client.send(&output.device, &message)?;
+                    sent += 1;
+                }
+
+                // The firmware plays it from here
+                output.finished = true;
+                continue;
+            }
+
+            // The last frame is drawn exactly at the end, so the effect always finishes where it should
+            let elapsed = end.map_or(elapsed, |end| elapsed.min(end));
+            output.finished = end.is_some_and(|end| elapsed >= end);
+
+            let mut pixels = output.base.clone();
+            output.effect.render(elapsed, &mut pixels);
+
+            for message in output.surface.messages(&output.shown, &pixels, duration_ms) {
+                client.send(&output.device, &message)?;
+                sent += 1;
+            }
+            output.shown = pixels;
+        }
+
+        Ok(sent)
+    }
+
+    /// Plays every effect for up to `limit`, or until they all finish.
+    pub fn run(&mut self, client: &mut LifxClient, limit: Duration) -> Result<Played, ClientError> {
+        let start = Instant::now();
+        let mut played = Played::default();
+
+        loop {
+            let elapsed = start.elapsed();
+            played.messages += self.frame(client, elapsed.min(limit))?;
+            played.frames += 1;
+
+            if elapsed >= limit || self.is_finished() {
+                return Ok(played);
+            }
+
+            let next = self.interval * played.frames as u32;
+            thread::sleep(next.saturating_sub(start.elapsed()));
+        }
+    }
+}
@@ -0,0 +1,666 @@
+use std::{
+    io,
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use lifx_serialization::LifxPayload;
+
+use crate::{
+    client::{Device, MAX_PACKET_SIZE},
+    color::{lerp_u16, Hsbk},
+    deserialize_lifx_packet_as,
+    groups::Membership,
+    messages::{TileDevice, EXTENDED_MULTIZONE_COLORS, MULTIZONE_APPLY_ONLY, MULTIZONE_COLORS, MULTIZONE_NO_APPLY, TILE_COLORS},
+    serialize_lifx_packet, DeviceRequest, DeviceResponse, LifxHeader, LifxRequestOptions,
+};
+
+/// Width and height of an emulated tile
+const TILE_SIZE: usize = 8;
+
+/// How an emulated bulb starts out.
+#[derive(Debug, Clone)]
+pub struct EmulatorConfig {
+    pub target: [u8; 8],
+    pub label: String,
+    pub vendor: u32,
+    pub product: u32,
+    pub firmware_major: u16,
+    pub firmware_minor: u16,
+    pub color: Hsbk,
+    pub power: u16,
+    /// Devices without a light, such as switches, answer light messages with StateUnhandled
+    pub light: bool,
+    pub group: Membership,
+    pub location: Membership,
+    /// Number of zones on a strip, or 0 for a device without zones. Zone changes apply immediately, ignoring the
+    /// transition duration.
+    pub zones: usize,
+    /// Number of 8x8 tiles in the chain, or 0 for a device without a matrix. Like zones, tile pixels change
+    /// immediately.
+    pub tiles: usize,
+}
+
+impl Default for EmulatorConfig {
+    fn default() -> Self {
+        EmulatorConfig {
+            target: [0xD0, 0x73, 0xD5, 0x00, 0x00, 0x01, 0, 0],
+            label: String::from("Emulated Bulb"),
+            // LIFX A19
+            vendor: 1,
+            product: 27,
+            firmware_major: 3,
+            firmware_minor: 70,
+            color: Hsbk::new(0, 0, 65535, 3500),
+            power: 0,
+            light: true,
+            group: Membership::default(),
+            location: Membership::default(),
+            zones: 0,
+            tiles: 0,
+        }
+    }
+}
+
+/// Snapshot of an emulated bulb, with any running transition evaluated at the time it was taken.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmulatorState {
+    pub label: String,
+    pub color: Hsbk,
+    pub power: u16,
+    pub group: Membership,
+    pub location: Membership,
+    /// Zone colours as shown, not counting any sent without being applied yet
+    pub zones: Vec<Hsbk>,
+    /// Pixels of each tile, row by row
+    pub tiles: Vec<[Hsbk; TILE_COLORS]>,
+    /// Every request received so far, oldest first
+    pub requests: Vec<DeviceRequest>,
+}
+
+struct Transition<T> {
+    from: T,
+    to: T,
+    start: Instant,
+    duration: Duration,
+}
+
+impl<T: Copy> Transition<T> {
+    fn immediate(value: T) -> Self {
+        Transition { from: value, to: value, start: Instant::now(), duration: Duration::ZERO }
+    }
+
+    fn progress(&self, now: Instant) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+
+        now.saturating_duration_since(self.start).as_secs_f32() / self.duration.as_secs_f32()
+    }
+}
+
+/// The behaviour of a single bulb, independent of how packets reach it.
+pub(crate) struct VirtualDevice {
+    pub(crate) config: EmulatorConfig,
+    label: String,
+    color: Transition<Hsbk>,
+    power: Transition<u16>,
+    group: Membership,
+    location: Membership,
+    zones: Vec<Hsbk>,
+    // Zone colours received with MULTIZONE_NO_APPLY, shown by the next message that applies them
+    pending_zones: Vec<Hsbk>,
+    tiles: Vec<[Hsbk; TILE_COLORS]>,
+    offscreen_tiles: Vec<[Hsbk; TILE_COLORS]>,
+    // The running firmware effects, kept as the State messages that report them. They are not animated.
+    multizone_effect: DeviceResponse,
+    tile_effect: DeviceResponse,
+    requests: Vec<DeviceRequest>,
+    started: Instant,
+}
+
+impl VirtualDevice {
+    pub(crate) fn new(config: EmulatorConfig) -> Self {
+        VirtualDevice {
+            label: config.label.clone(),
+            color: Transition::immediate(config.color),
+            power: Transition::immediate(config.power),
+            group: config.group.clone(),
+            location: config.location.clone(),
+            zones: vec![config.color; config.zones],
+            pending_zones: vec![config.color; config.zones],
+            tiles: vec![[config.color; TILE_COLORS]; config.tiles],
+            offscreen_tiles: vec![[config.color; TILE_COLORS]; config.tiles],
+            multizone_effect: DeviceResponse::StateMultiZoneEffect {
+                instance_id: 0,
+                effect_type: 0,
+                reserved_6: [0; 2],
+                speed_ms: 0,
+                duration_ns: 0,
+                reserved_7: [0; 4],
+                reserved_8: [0; 4],
+                reserved_9: [0; 4],
+                direction: 0,
+                reserved_10: [0; 24],
+            },
+            tile_effect: DeviceResponse::StateTileEffect {
+                reserved_6: 0,
+                instance_id: 0,
+                effect_type: 0,
+                speed_ms: 0,
+                duration_ns: 0,
+                reserved_7: [0; 4],
+                reserved_8: [0; 4],
+                sky_type: 0,
+                reserved_9: [0; 3],
+                cloud_saturation_min: 0,
+                reserved_10: [0; 3],
+                cloud_saturation_max: 0,
+                reserved_11: [0; 23],
+                palette_count: 0,
+                palette: [Hsbk::default(); 16],
+            },
+            requests: Vec::new(),
+            started: Instant::now(),
+            config,
+        }
+    }
+
+    pub(crate) fn color_at(&self, now: Instant) -> Hsbk {
+        self.color.from.lerp(&self.color.to, self.color.progress(now))
+    }
+
+    pub(crate) fn power_at(&self, now: Instant) -> u16 {
+        lerp_u16(self.power.from, self.power.to, self.power.progress(now))
+    }
+
+    pub(crate) fn state(&self, now: Instant) -> EmulatorState {
+        EmulatorState {
+            label: self.label.clone(),
+            color: self.color_at(now),
+            power: self.power_at(now),
+            group: self.group.clone(),
+            location: self.location.clone(),
+            zones: self.zones.clone(),
+            tiles: self.tiles.clone(),
+            requests: self.requests.clone(),
+        }
+    }
+
+    /// Whether a packet with this header is meant for this device.
+    pub(crate) fn is_addressed_by(&self, header: &LifxHeader) -> bool {
+        header.tagged || header.target == [0; 8] || header.target == self.config.target
+    }
+
+    fn light_state(&self, now: Instant) -> DeviceResponse {
+        let color = self.color_at(now);
+
+        DeviceResponse::LightState {
+            hue: color.hue,
+            saturation: color.saturation,
+            brightness: color.brightness,
+            kelvin: color.kelvin,
+            reserved_6: [0; 2],
+            power: self.power_at(now),
+            label: self.label.clone(),
+            reserved_7: [0; 8],
+        }
+    }
+
+    fn group_state(&self) -> DeviceResponse {
+        DeviceResponse::Group { group: self.group.id, label: self.group.label.clone(), updated_at: self.group.updated_at }
+    }
+
+    fn location_state(&self) -> DeviceResponse {
+        DeviceResponse::Location {
+            location: self.location.id,
+            label: self.location.label.clone(),
+            updated_at: self.location.updated_at,
+        }
+    }
+
+    /// Applies a request and returns the replies in the order they should be sent.
+    pub(crate) fn handle(&mut self, header: &LifxHeader, request: DeviceRequest, port: u16, now: Instant) -> Vec<DeviceResponse> {
+        self.requests.push(request.clone());
+
+        let mut replies = Vec::new();
+        if header.is_ack_required() {
+            replies.push(DeviceResponse::Acknowledgement);
+        }
+
+        if let Some((states, is_get)) = self.handle_pixels(&request) {
+            if is_get || header.is_res_required() {
+                replies.extend(states);
+            }
+            return replies;
+        }
+
+        let is_light_message = matches!(
+            request,
+            DeviceRequest::GetLightPower | DeviceRequest::SetLightPower { .. } | DeviceRequest::GetColor | DeviceRequest::SetColor { .. }
+        );
+
+        // Get messages always answer, Set messages only when asked to
+        let (state, is_get) = match request {
+            other if is_light_message && !self.config.light => {
+                (DeviceResponse::Unhandled { unhandled_type: other.packet_number() }, true)
+            }
+            DeviceRequest::GetService => (DeviceResponse::StateService { service: 1, port: port as u32 }, true),
+            DeviceRequest::GetHostFirmware => (
+                DeviceResponse::HostFirmware {
+                    build: 0,
+                    reserved_6: [0; 8],
+                    version_minor: self.config.firmware_minor,
+                    version_major: self.config.firmware_major,
+                },
+                true,
+            ),
+            DeviceRequest::GetVersion => (
+                DeviceResponse::Version { vendor: self.config.vendor, product: self.config.product, reserved_6: 0 },
+                true,
+            ),
+            DeviceRequest::GetInfo => {
+                let uptime = now.saturating_duration_since(self.started).as_nanos() as u64;
+                (DeviceResponse::Info { time: 0, uptime, downtime: 0 }, true)
+            }
+            DeviceRequest::GetLabel => (DeviceResponse::Label { label: self.label.clone() }, true),
+            DeviceRequest::SetLabel { label } => {
+                self.label = label;
+                (DeviceResponse::Label { label: self.label.clone() }, false)
+            }
+            DeviceRequest::GetGroup => (self.group_state(), true),
+            DeviceRequest::SetGroup { group, label, updated_at } => {
+                self.group = Membership { id: group, label, updated_at };
+                (self.group_state(), false)
+            }
+            DeviceRequest::GetLocation => (self.location_state(), true),
+            DeviceRequest::SetLocation { location, label, updated_at } => {
+                self.location = Membership { id: location, label, updated_at };
+                (self.location_state(), false)
+            }
+            DeviceRequest::GetPower => (DeviceResponse::Power { level: self.power_at(now) }, true),
+            DeviceRequest::SetPower { level } => {
+                // State messages answering a Set report the value from before it was applied
+                let previous = self.power_at(now);
+                self.power = Transition::immediate(level);
+                (DeviceResponse::Power { level: previous }, false)
+            }
+            DeviceRequest::GetLightPower => (DeviceResponse::LightPower { level: self.power_at(now) }, true),
+            DeviceRequest::SetLightPower { level, duration_ms } => {
+                let previous = self.power_at(now);
+                self.power = Transition { from: previous, to: level, start: now, duration: Duration::from_millis(duration_ms as u64) };
+                (DeviceResponse::LightPower { level: previous }, false)
+            }
+            DeviceRequest::GetColor => (self.light_state(now), true),
+            DeviceRequest::SetColor { hue, saturation, brightness, kelvin, duration_ms, .. } => {
+                let previous = self.light_state(now);
+                self.color = Transition {
+                    from: self.color_at(now),
+                    to: Hsbk { hue, saturation, brightness, kelvin },
+                    start: now,
+                    duration: Duration::from_millis(duration_ms as u64),
+                };
+                (previous, false)
+            }
+            DeviceRequest::EchoRequest { echoing } => (DeviceResponse::EchoResponse { echoing }, true),
+            other => (DeviceResponse::Unhandled { unhandled_type: other.packet_number() }, true),
+        };
+
+        if is_get || header.is_res_required() {
+            replies.push(state);
+        }
+
+        replies
+    }
+
+    // Zone and tile messages, which can need more than one reply. `None` for any other message, or when the device
+    // doesn't have zones or tiles to answer with.
+    fn handle_pixels(&mut self, request: &DeviceRequest) -> Option<(Vec<DeviceResponse>, bool)> {
+        let has_zones = !self.zones.is_empty();
+        let has_tiles = !self.tiles.is_empty();
+
+        Some(match *request {
+            DeviceRequest::GetColorZones { start_index, end_index } if has_zones => (self.zone_states(start_index, end_index), true),
+            DeviceRequest::SetColorZones { start_index, end_index, hue, saturation, brightness, kelvin, apply, .. } if has_zones => {
+                let states = self.zone_states(start_index, end_index);
+                let end = (end_index as usize).min(self.zones.len() - 1);
+                if apply != MULTIZONE_APPLY_ONLY {
+                    for zone in self.pending_zones.iter_mut().take(end + 1).skip(start_index as usize) {
+                        *zone = Hsbk { hue, saturation, brightness, kelvin };
+                    }
+                }
+                self.apply_zones(apply);
+                (states, false)
+            }
+            DeviceRequest::GetExtendedColorZones if has_zones => (self.extended_zone_states(), true),
+            DeviceRequest::SetExtendedColorZones { apply, zone_index, colors_count, ref colors, .. } if has_zones => {
+                let states = self.extended_zone_states();
+                let count = (colors_count as usize).min(EXTENDED_MULTIZONE_COLORS);
+                if apply != MULTIZONE_APPLY_ONLY {
+                    for (zone, color) in self.pending_zones.iter_mut().skip(zone_index as usize).zip(&colors[..count]) {
+                        *zone = *color;
+                    }
+                }
+                self.apply_zones(apply);
+                (states, false)
+            }
+            DeviceRequest::GetMultiZoneEffect if has_zones => (vec![self.multizone_effect.clone()], true),
+            DeviceRequest::SetMultiZoneEffect { instance_id, effect_type, speed_ms, duration_ns, direction, .. } if has_zones => {
+                let effect = DeviceResponse::StateMultiZoneEffect {
+                    instance_id,
+                    effect_type,
+                    reserved_6: [0; 2],
+                    speed_ms,
+                    duration_ns,
+                    reserved_7: [0; 4],
+                    reserved_8: [0; 4],
+                    reserved_9: [0; 4],
+                    direction,
+                    reserved_10: [0; 24],
+                };
+                (vec![core::mem::replace(&mut self.multizone_effect, effect)], false)
+            }
+            DeviceRequest::GetTileEffect { .. } if has_tiles => (vec![self.tile_effect.clone()], true),
+            DeviceRequest::SetTileEffect {
+                instance_id,
+                effect_type,
+                speed_ms,
+                duration_ns,
+                sky_type,
+                cloud_saturation_min,
+                cloud_saturation_max,
+                palette_count,
+                palette,
+                ..
+            } if has_tiles => {
+                let effect = DeviceResponse::StateTileEffect {
+                    reserved_6: 0,
+                    instance_id,
+                    effect_type,
+                    speed_ms,
+                    duration_ns,
+                    reserved_7: [0; 4],
+                    reserved_8: [0; 4],
+                    sky_type,
+                    reserved_9: [0; 3],
+                    cloud_saturation_min,
+                    reserved_10: [0; 3],
+                    cloud_saturation_max,
+                    reserved_11: [0; 23],
+                    palette_count,
+                    palette,
+                };
+                (vec![core::mem::replace(&mut self.tile_effect, effect)], false)
+            }
+            DeviceRequest::GetDeviceChain if has_tiles => (vec![self.chain_state()], true),
+            DeviceRequest::Get64 { tile_index, length, x, y, width, .. } if has_tiles => {
+                let tiles = (tile_index as usize..self.tiles.len()).take(length as usize);
+                (tiles.map(|tile| self.tile_state(tile, x, y, width)).collect(), true)
+            }
+            DeviceRequest::Set64 { tile_index, length, fb_index, x, y, width, ref colors, .. } if has_tiles => {
+                let count = self.tiles.len();
+                let buffer = self.frame_buffer(fb_index);
+                for tile in buffer.iter_mut().take(count).skip(tile_index as usize).take(length as usize) {
+                    for (index, color) in colors.iter().enumerate() {
+                        if let Some(pixel) = pixel_index(x, y, width, index) {
+                            tile[pixel] = *color;
+                        }
+                    }
+                }
+                (Vec::new(), false)
+            }
+            DeviceRequest::CopyFrameBuffer {
+                tile_index,
+                length,
+                src_fb_index,
+                dst_fb_index,
+                src_x,
+                src_y,
+                dst_x,
+                dst_y,
+                width,
+                height,
+                ..
+            } if has_tiles => {
+                for tile in (tile_index as usize..self.tiles.len()).take(length as usize) {
+                    let source = self.frame_buffer(src_fb_index)[tile];
+                    let destination = &mut self.frame_buffer(dst_fb_index)[tile];
+
+                    for row in 0..height as usize {
+                        for column in 0..width as usize {
+                            let (from_x, from_y) = (src_x as usize + column, src_y as usize + row);
+                            let (to_x, to_y) = (dst_x as usize + column, dst_y as usize + row);
+                            if from_x.max(from_y).max(to_x).max(to_y) < TILE_SIZE {
+                                destination[to_y * TILE_SIZE + to_x] = source[from_y * TILE_SIZE + from_x];
+                            }
+                        }
+                    }
+                }
+                (Vec::new(), false)
+            }
+            _ => return None,
+        })
+    }
+
+    fn apply_zones(&mut self, apply: u8) {
+        if apply != MULTIZONE_NO_APPLY {
+            self.zones.clone_from(&self.pending_zones);
+        }
+    }
+
+    // Frame buffer 0 is what the tiles show; any other index is the one off-screen buffer
+    fn frame_buffer(&mut self, fb_index: u8) -> &mut [[Hsbk; TILE_COLORS]] {
+        match fb_index {
+            0 => &mut self.tiles,
+            _ => &mut self.offscreen_tiles,
+        }
+    }
+
+    // A StateZone for a single zone, otherwise StateMultiZone packets covering the range eight zones at a time
+    fn zone_states(&self, start_index: u8, end_index: u8) -> Vec<DeviceResponse> {
+        let count = self.zones.len();
+        let zones_count = count.min(u8::MAX as usize) as u8;
+        let start = start_index as usize;
+        let end = (end_index as usize).min(count - 1);
+
+        if start == end {
+            let color = self.zones[start];
+            return vec![DeviceResponse::StateZone {
+                zones_count,
+                zone_index: start_index,
+                hue: color.hue,
+                saturation: color.saturation,
+                brightness: color.brightness,
+                kelvin: color.kelvin,
+            }];
+        }
+
+        (start..=end)
+            .step_by(MULTIZONE_COLORS)
+            .map(|first| DeviceResponse::StateMultiZone {
+                zones_count,
+                zone_index: first as u8,
+                colors: core::array::from_fn(|offset| self.zones.get(first + offset).copied().unwrap_or_default()),
+            })
+            .collect()
+    }
+
+    fn extended_zone_states(&self) -> Vec<DeviceResponse> {
+        (0..self.zones.len())
+            .step_by(EXTENDED_MULTIZONE_COLORS)
+            .map(|first| {
+                let colors = &self.zones[first..(first + EXTENDED_MULTIZONE_COLORS).min(self.zones.len())];
+                DeviceResponse::StateExtendedColorZones {
+                    zones_count: self.zones.len() as u16,
+                    zone_index: first as u16,
+                    colors_count: colors.len() as u8,
+                    colors: core::array::from_fn(|index| colors.get(index).copied().unwrap_or_default()),
+                }
+            })
+            .collect()
+    }
+
+    fn chain_state(&self) -> DeviceResponse {
+        DeviceResponse::StateDeviceChain {
+            start_index: 0,
+            tile_devices: core::array::from_fn(|index| match index < self.tiles.len() {
+                true => TileDevice {
+                    user_x: index as f32,
+                    width: TILE_SIZE as u8,
+                    height: TILE_SIZE as u8,
+                    device_version_vendor: self.config.vendor,
+                    device_version_product: self.config.product,
+                    firmware_version_minor: self.config.firmware_minor,
+                    firmware_version_major: self.config.firmware_major,
+                    ..TileDevice::default()
+                },
+                false => TileDevice::default(),
+            }),
+            tile_devices_count: self.tiles.len() as u8,
+        }
+    }
+
+    fn tile_state(&self, tile: usize, x: u8, y: u8, width: u8) -> DeviceResponse {
+        DeviceResponse::State64 {
+            tile_index: tile as u8,
+            reserved_6: 0,
+            x,
+            y,
+            width,
+            colors: core::array::from_fn(|index| {
+                pixel_index(x, y, width, index).map(|pixel| self.tiles[tile][pixel]).unwrap_or_default()
+            }),
+        }
+    }
+}
+
+// Where the `index`th colour of a Set64 or State64 rectangle falls on an 8x8 tile, if it falls on it at all
+fn pixel_index(x: u8, y: u8, width: u8, index: usize) -> Option<usize> {
+    let width = (width as usize).max(1);
+    let (column, row) = (x as usize + index % width, y as usize + index / width);
+
+    (column < TILE_SIZE && row < TILE_SIZE).then_some(row * TILE_SIZE + column)
+}
+
+/// Encodes a reply to the request described by `header`, sent from the device `target`.
+pub(crate) fn reply_packet(target: [u8; 8], header: &LifxHeader, reply: &DeviceResponse) -> Vec<u8> {
+    let options = LifxRequestOptions {
+        tagged: false,
+        source: header.source,
+        target,
+        ack_required: false,
+        res_required: false,
+        sequence: header.sequence,
+    };
+
+    let mut buffer = [0u8; MAX_PACKET_SIZE];
+    serialize_lifx_packet(&options, reply, &mut buffer);
+    buffer[..36 + reply.size()].to_vec()
+}
+
+/// A fake LIFX bulb listening on a local UDP port, for testing clients without real hardware.
+pub struct Emulator {
+    address: SocketAddr,
+    target: [u8; 8],
+    device: Arc<Mutex<VirtualDevice>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Emulator {
+    /// Starts a bulb on a free port on localhost.
+    pub fn start(config: EmulatorConfig) -> io::Result<Self> {
+        Self::bind("127.0.0.1:0", config)
+    }
+
+    pub fn bind(address: &str, config: EmulatorConfig) -> io::Result<Self> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_read_timeout(Some(Duration::from_millis(20)))?;
+
+        let address = socket.local_addr()?;
+        let target = config.target;
+        let device = Arc::new(Mutex::new(VirtualDevice::new(config)));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread = {
+            let device = device.clone();
+            let running = running.clone();
+
+            thread::spawn(move || serve(socket, device, running))
+        };
+
+        Ok(Emulator { address, target, device, running, thread: Some(thread) })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// The bulb as a client would address it.
+    pub fn device(&self) -> Device {
+        Device { target: self.target, address: self.address }
+    }
+
+    pub fn state(&self) -> EmulatorState {
+        self.device.lock().unwrap().state(Instant::now())
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn serve(socket: UdpSocket, device: Arc<Mutex<VirtualDevice>>, running: Arc<AtomicBool>) {
+    let port = socket.local_addr().map(|address| address.port()).unwrap_or(56700);
+    let mut buffer = [0u8; MAX_PACKET_SIZE];
+
+    while running.load(Ordering::Relaxed) {
+        let (size, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => continue,
+            Err(err) => {
+                log::error!("Emulator stopped: {}", err);
+                return;
+            }
+        };
+
+        let (header, request) = match deserialize_lifx_packet_as::<DeviceRequest>(&buffer[..size]) {
+            Ok(packet) => packet,
+            Err(err) => {
+                log::debug!("Emulator dropping packet from {}: {}", from, err);
+                continue;
+            }
+        };
+
+        let mut device = device.lock().unwrap();
+        if !device.is_addressed_by(&header) {
+            continue;
+        }
+
+        let target = device.config.target;
+        for reply in device.handle(&header, request, port, Instant::now()) {
+            if let Err(err) = socket.send_to(&reply_packet(target, &header, &reply), from) {
+                log::warn!("Emulator failed to reply to {}: {}", from, err);
+            }
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use core::{fmt, time::Duration};
+
+use crate::{
+    messages::responses,
+    products::{self, Features},
+};
+
+/// A firmware version as reported by StateHostFirmware or StateWifiFirmware.
+///
+/// Versions order by major, then minor, then build time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FirmwareVersion {
+    pub major: u16,
+    pub minor: u16,
+    /// When the firmware was built, in nanoseconds since the Unix epoch
+    pub build: u64,
+}
+
+impl FirmwareVersion {
+    pub const fn new(major: u16, minor: u16) -> Self {
+        FirmwareVersion { major, minor, build: 0 }
+    }
+
+    /// Time since the Unix epoch when the firmware was built.
+    pub fn build_time(&self) -> Duration {
+        Duration::from_nanos(self.build)
+    }
+
+    #[cfg(not(feature = "no-std"))]
+    pub fn built_at(&self) -> std::time::SystemTime {
+        std::time::UNIX_EPOCH + self.build_time()
+    }
+
+    /// Whether this version is `major.minor` or later, regardless of build.
+    pub fn is_at_least(&self, major: u16, minor: u16) -> bool {
+        (self.major, self.minor) >= (major, minor)
+    }
+
+    /// What a product can do when running this version, or `None` if the product catalogue doesn't know it.
+    pub fn features(&self, vendor: u32, product: u32) -> Option<Features> {
+        products::product_info(vendor, product, *self).map(|info| info.features)
+    }
+
+    /// Whether a product running this version can set all of its zones with one extended multizone message.
+    pub fn supports_extended_multizone(&self, vendor: u32, product: u32) -> bool {
+        self.features(vendor, product).is_some_and(|features| features.extended_multizone)
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+impl From<(u16, u16)> for FirmwareVersion {
+    fn from((major, minor): (u16, u16)) -> Self {
+        FirmwareVersion::new(major, minor)
+    }
+}
+
+impl From<&responses::HostFirmware> for FirmwareVersion {
+    fn from(firmware: &responses::HostFirmware) -> Self {
+        FirmwareVersion { major: firmware.version_major, minor: firmware.version_minor, build: firmware.build }
+    }
+}
+
+impl From<&responses::WifiFirmware> for FirmwareVersion {
+    fn from(firmware: &responses::WifiFirmware) -> Self {
+        FirmwareVersion { major: firmware.version_major, minor: firmware.version_minor, build: firmware.build }
+    }
+}
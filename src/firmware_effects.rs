@@ -0,0 +1,198 @@
+use core::time::Duration;
+
+use crate::{
+    messages::{responses, TILE_EFFECT_PALETTE_COLORS},
+    DeviceRequest, Hsbk,
+};
+
+/// Which way a [`MultiZoneEffect::Move`] scrolls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    /// Towards the last zone
+    #[default]
+    Right,
+    /// Towards the first zone
+    Left,
+}
+
+/// An effect a strip or beam plays by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MultiZoneEffect {
+    Off,
+    /// Scrolls the zone colours along the strip, taking `speed` for one full lap
+    Move {
+        speed: Duration,
+        direction: Direction,
+        /// How long to play for, or `None` until told otherwise
+        duration: Option<Duration>,
+    },
+    /// An effect type this crate doesn't know, as reported by the device
+    Unknown(u8),
+}
+
+impl MultiZoneEffect {
+    /// The SetMultiZoneEffect that starts this effect. A device only restarts an effect when `instance_id`
+    /// differs from the one it is running.
+    pub fn to_message(&self, instance_id: u32) -> DeviceRequest {
+        let (effect_type, speed, direction, duration) = match *self {
+            MultiZoneEffect::Off => (0, Duration::ZERO, Direction::Right, None),
+            MultiZoneEffect::Move { speed, direction, duration } => (1, speed, direction, duration),
+            MultiZoneEffect::Unknown(effect_type) => (effect_type, Duration::ZERO, Direction::Right, None),
+        };
+
+        DeviceRequest::SetMultiZoneEffect {
+            instance_id,
+            effect_type,
+            reserved_6: [0; 2],
+            speed_ms: milliseconds(speed),
+            duration_ns: nanoseconds(duration),
+            reserved_7: [0; 4],
+            reserved_8: [0; 4],
+            reserved_9: [0; 4],
+            direction: direction as u32,
+            reserved_10: [0; 24],
+        }
+    }
+}
+
+impl From<&responses::StateMultiZoneEffect> for MultiZoneEffect {
+    fn from(state: &responses::StateMultiZoneEffect) -> Self {
+        match state.effect_type {
+            0 => MultiZoneEffect::Off,
+            1 => MultiZoneEffect::Move {
+                speed: Duration::from_millis(state.speed_ms as u64),
+                direction: if state.direction == 1 { Direction::Left } else { Direction::Right },
+                duration: duration(state.duration_ns),
+            },
+            other => MultiZoneEffect::Unknown(other),
+        }
+    }
+}
+
+/// The sky a [`TileEffect::Sky`] shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SkyType {
+    Sunrise,
+    Sunset,
+    /// Clouds drifting across a blue sky, with saturation between `cloud_saturation_min` and `cloud_saturation_max`
+    Clouds { cloud_saturation_min: u8, cloud_saturation_max: u8 },
+}
+
+/// Up to 16 colours for a tile effect to draw from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Palette {
+    colors: [Hsbk; TILE_EFFECT_PALETTE_COLORS],
+    count: u8,
+}
+
+impl Palette {
+    /// A palette of the first 16 of `colors`.
+    pub fn new(colors: &[Hsbk]) -> Self {
+        let count = colors.len().min(TILE_EFFECT_PALETTE_COLORS);
+        let mut palette = Palette { colors: [Hsbk::default(); TILE_EFFECT_PALETTE_COLORS], count: count as u8 };
+        palette.colors[..count].copy_from_slice(&colors[..count]);
+        palette
+    }
+
+    pub fn colors(&self) -> &[Hsbk] {
+        &self.colors[..self.count as usize]
+    }
+}
+
+/// An effect a tile chain, candle or ceiling plays by itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TileEffect {
+    Off,
+    /// Blobs of colour from `palette` flowing into each other
+    Morph { speed: Duration, duration: Option<Duration>, palette: Palette },
+    Flame { speed: Duration, duration: Option<Duration> },
+    /// Only supported by the LIFX Ceiling
+    Sky { speed: Duration, duration: Option<Duration>, sky: SkyType },
+    /// An effect type this crate doesn't know, as reported by the device
+    Unknown(u8),
+}
+
+impl TileEffect {
+    /// The SetTileEffect that starts this effect. A device only restarts an effect when `instance_id` differs
+    /// from the one it is running.
+    pub fn to_message(&self, instance_id: u32) -> DeviceRequest {
+        let (effect_type, speed, duration, palette, sky) = match *self {
+            TileEffect::Off => (0, Duration::ZERO, None, Palette::default(), None),
+            TileEffect::Morph { speed, duration, palette } => (2, speed, duration, palette, None),
+            TileEffect::Flame { speed, duration } => (3, speed, duration, Palette::default(), None),
+            TileEffect::Sky { speed, duration, sky } => (5, speed, duration, Palette::default(), Some(sky)),
+            TileEffect::Unknown(effect_type) => (effect_type, Duration::ZERO, None, Palette::default(), None),
+        };
+
+        let (sky_type, cloud_saturation_min, cloud_saturation_max) = match sky {
+            None | Some(SkyType::Sunrise) => (0, 0, 0),
+            Some(SkyType::Sunset) => (1, 0, 0),
+            Some(SkyType::Clouds { cloud_saturation_min, cloud_saturation_max }) => (2, cloud_saturation_min, cloud_saturation_max),
+        };
+
+        DeviceRequest::SetTileEffect {
+            reserved_6: 0,
+            reserved_7: 0,
+            instance_id,
+            effect_type,
+            speed_ms: milliseconds(speed),
+            duration_ns: nanoseconds(duration),
+            reserved_8: [0; 4],
+            reserved_9: [0; 4],
+            sky_type,
+            reserved_10: [0; 3],
+            cloud_saturation_min,
+            reserved_11: [0; 3],
+            cloud_saturation_max,
+            reserved_12: [0; 23],
+            palette_count: palette.count,
+            palette: palette.colors,
+        }
+    }
+}
+
+impl From<&responses::StateTileEffect> for TileEffect {
+    fn from(state: &responses::StateTileEffect) -> Self {
+        let speed = Duration::from_millis(state.speed_ms as u64);
+        let duration = duration(state.duration_ns);
+
+        match state.effect_type {
+            0 => TileEffect::Off,
+            2 => {
+                let count = (state.palette_count as usize).min(TILE_EFFECT_PALETTE_COLORS);
+                TileEffect::Morph { speed, duration, palette: Palette::new(&state.palette[..count]) }
+            }
+            3 => TileEffect::Flame { speed, duration },
+            5 => {
+                let sky = match state.sky_type {
+                    1 => SkyType::Sunset,
+                    2 => SkyType::Clouds {
+                        cloud_saturation_min: state.cloud_saturation_min,
+                        cloud_saturation_max: state.cloud_saturation_max,
+                    },
+                    _ => SkyType::Sunrise,
+                };
+                TileEffect::Sky { speed, duration, sky }
+            }
+            other => TileEffect::Unknown(other),
+        }
+    }
+}
+
+fn milliseconds(duration: Duration) -> u32 {
+    duration.as_millis().min(u32::MAX as u128) as u32
+}
+
+// Effects encode "play until stopped" as a duration of zero
+fn nanoseconds(duration: Option<Duration>) -> u64 {
+    duration.map_or(0, |duration| duration.as_nanos().min(u64::MAX as u128) as u64)
+}
+
+fn duration(nanoseconds: u64) -> Option<Duration> {
+    (nanoseconds > 0).then(|| Duration::from_nanos(nanoseconds))
+}
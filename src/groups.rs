@@ -0,0 +1,380 @@
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    client::{Burst, ClientError, Device, LifxClient},
+    light::Light,
+    messages::responses,
+    DeviceRequest,
+};
+
+/// A device's record of the group or location it belongs to.
+///
+/// Every member keeps its own copy, so members can disagree about the label after a rename that didn't reach them
+/// all; the copy with the newest `updated_at` is the right one.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Membership {
+    pub id: [u8; 16],
+    pub label: String,
+    /// Nanoseconds since the Unix epoch
+    pub updated_at: u64,
+}
+
+impl Membership {
+    /// A new group or location with a random ID, stamped with the current time.
+    pub fn new(label: &str) -> Self {
+        Membership { id: random_id(), label: String::from(label), updated_at: now() }
+    }
+}
+
+// A version 4 UUID, seeded from the randomly keyed hasher std uses for HashMap
+fn random_id() -> [u8; 16] {
+    let mut id = [0u8; 16];
+
+    for (index, half) in id.chunks_mut(8).enumerate() {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_usize(index);
+        hasher.write_u64(now());
+        half.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+
+    id[6] = (id[6] & 0x0F) | 0x40;
+    id[8] = (id[8] & 0x3F) | 0x80;
+    id
+}
+
+// Bytes in the label field of the group and location messages
+const MAX_LABEL_LENGTH: usize = 32;
+
+fn check_label(label: &str) -> Result<(), GroupError> {
+    if label.len() > MAX_LABEL_LENGTH {
+        return Err(GroupError::LabelTooLong(String::from(label)));
+    }
+
+    Ok(())
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_nanos() as u64).unwrap_or(0)
+}
+
+/// Groups and locations are managed the same way, with their own messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    Group,
+    Location,
+}
+
+impl Kind {
+    fn set(self, membership: &Membership) -> DeviceRequest {
+        let Membership { id, label, updated_at } = membership.clone();
+
+        match self {
+            Kind::Group => DeviceRequest::SetGroup { group: id, label, updated_at },
+            Kind::Location => DeviceRequest::SetLocation { location: id, label, updated_at },
+        }
+    }
+
+    fn query(self, client: &mut LifxClient, device: &Device) -> Result<Membership, ClientError> {
+        Ok(match self {
+            Kind::Group => client.request::<responses::Group>(device, &DeviceRequest::GetGroup)?.into(),
+            Kind::Location => client.request::<responses::Location>(device, &DeviceRequest::GetLocation)?.into(),
+        })
+    }
+}
+
+/// Why a member couldn't be moved or renamed.
+#[derive(Debug)]
+pub enum MemberError {
+    Client(ClientError),
+    /// The device acknowledged the change but reported this membership when asked afterwards
+    NotApplied(Membership),
+}
+
+impl fmt::Display for MemberError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemberError::Client(err) => err.fmt(f),
+            MemberError::NotApplied(membership) => write!(f, "Change not applied, device still reports {:?}", membership.label),
+        }
+    }
+}
+
+impl std::error::Error for MemberError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MemberError::Client(err) => Some(err),
+            MemberError::NotApplied(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GroupError {
+    /// No group or location has this label
+    NotFound(String),
+    /// The label is longer than the 32 bytes a device can store; nothing was sent
+    LabelTooLong(String),
+    /// Some members were not updated; the rest were
+    Members(Vec<(Device, MemberError)>),
+}
+
+impl fmt::Display for GroupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GroupError::NotFound(label) => write!(f, "No group or location called {:?}", label),
+            GroupError::LabelTooLong(label) => write!(f, "Label {:?} is longer than {} bytes", label, MAX_LABEL_LENGTH),
+            GroupError::Members(failures) => write!(f, "{} devices were not updated", failures.len()),
+        }
+    }
+}
+
+impl std::error::Error for GroupError {}
+
+// Sets the membership on one device and reads it back to make sure it took.
+fn apply(client: &mut LifxClient, kind: Kind, device: &Device, membership: &Membership) -> Result<(), MemberError> {
+    client.send_acked(device, &kind.set(membership)).map_err(MemberError::Client)?;
+
+    match kind.query(client, device).map_err(MemberError::Client)? {
+        reported if reported == *membership => Ok(()),
+        reported => Err(MemberError::NotApplied(reported)),
+    }
+}
+
+impl From<responses::Group> for Membership {
+    fn from(group: responses::Group) -> Self {
+        Membership { id: group.group, label: group.label, updated_at: group.updated_at }
+    }
+}
+
+impl From<responses::Location> for Membership {
+    fn from(location: responses::Location) -> Self {
+        Membership { id: location.location, label: location.label, updated_at: location.updated_at }
+    }
+}
+
+/// A group or location and the devices in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collection {
+    pub id: [u8; 16],
+    pub label: String,
+    pub updated_at: u64,
+    pub members: Vec<Device>,
+}
+
+impl Collection {
+    pub fn lights(&self) -> Vec<Light> {
+        self.members.iter().map(|device| Light::new(*device)).collect()
+    }
+
+    /// Sends `message` to every member without waiting for replies.
+    pub fn send(&self, client: &mut LifxClient, message: &DeviceRequest) -> Result<(), ClientError> {
+        for device in &self.members {
+            client.send(device, message)?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends `message` to every member in one burst so they all change at the same moment.
+    /// See [`LifxClient::send_burst`].
+    pub fn send_synchronized(&self, client: &mut LifxClient, message: &DeviceRequest) -> Result<Burst, ClientError> {
+        let messages: Vec<_> = self.members.iter().map(|device| (*device, message.clone())).collect();
+        client.send_burst(&messages, None)
+    }
+
+    /// Sends `message` to every member in turn, waiting for each to acknowledge it, and returns the members that
+    /// failed with why. One unreachable member doesn't stop the rest from getting the message.
+    pub fn send_acked(&self, client: &mut LifxClient, message: &DeviceRequest) -> Vec<(Device, ClientError)> {
+        self.members
+            .iter()
+            .filter_map(|device| client.send_acked(device, message).err().map(|err| (*device, err)))
+            .collect()
+    }
+
+    fn add(&mut self, device: Device, membership: &Membership) {
+        if membership.updated_at > self.updated_at {
+            self.label = membership.label.clone();
+            self.updated_at = membership.updated_at;
+        }
+
+        self.members.push(device);
+    }
+}
+
+fn query_memberships(client: &mut LifxClient, device: &Device) -> Result<(Membership, Membership), ClientError> {
+    let group: responses::Group = client.request(device, &DeviceRequest::GetGroup)?;
+    let location: responses::Location = client.request(device, &DeviceRequest::GetLocation)?;
+
+    Ok((group.into(), location.into()))
+}
+
+fn resolve(collections: &mut Vec<Collection>, device: Device, membership: &Membership) {
+    // An all zero ID means the device was never put in one
+    if membership.id == [0; 16] {
+        return;
+    }
+
+    match collections.iter_mut().find(|collection| collection.id == membership.id) {
+        Some(collection) => collection.add(device, membership),
+        None => collections.push(Collection {
+            id: membership.id,
+            label: membership.label.clone(),
+            updated_at: membership.updated_at,
+            members: vec![device],
+        }),
+    }
+}
+
+/// The groups and locations a set of devices are organised into, so they can be addressed by name.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Directory {
+    groups: Vec<Collection>,
+    locations: Vec<Collection>,
+    unreachable: Vec<Device>,
+}
+
+impl Directory {
+    /// Asks every device which group and location it is in. Devices that don't answer are left out of both and
+    /// listed in [`Directory::unreachable`] instead.
+    pub fn query(client: &mut LifxClient, devices: &[Device]) -> Result<Self, ClientError> {
+        let mut memberships = Vec::with_capacity(devices.len());
+        let mut unreachable = Vec::new();
+
+        for device in devices {
+            match query_memberships(client, device) {
+                Ok((group, location)) => memberships.push((*device, group, location)),
+                Err(ClientError::Io(err)) => return Err(ClientError::Io(err)),
+                Err(err) => {
+                    log::debug!("No group or location from {:?}: {}", device.address, err);
+                    unreachable.push(*device);
+                }
+            }
+        }
+
+        let mut directory = Self::from_memberships(memberships);
+        directory.unreachable = unreachable;
+        Ok(directory)
+    }
+
+    /// Builds the directory from each device's group and location memberships.
+    pub fn from_memberships(memberships: impl IntoIterator<Item = (Device, Membership, Membership)>) -> Self {
+        let mut directory = Directory::default();
+
+        for (device, group, location) in memberships {
+            resolve(&mut directory.groups, device, &group);
+            resolve(&mut directory.locations, device, &location);
+        }
+
+        directory.groups.sort_by(|a, b| a.label.cmp(&b.label));
+        directory.locations.sort_by(|a, b| a.label.cmp(&b.label));
+        directory
+    }
+
+    /// Every group, sorted by label.
+    pub fn groups(&self) -> &[Collection] {
+        &self.groups
+    }
+
+    /// Every location, sorted by label.
+    pub fn locations(&self) -> &[Collection] {
+        &self.locations
+    }
+
+    pub fn group(&self, label: &str) -> Option<&Collection> {
+        self.groups.iter().find(|group| group.label == label)
+    }
+
+    pub fn location(&self, label: &str) -> Option<&Collection> {
+        self.locations.iter().find(|location| location.label == label)
+    }
+
+    /// The groups with members in `location`.
+    pub fn groups_in<'a>(&'a self, location: &'a Collection) -> impl Iterator<Item = &'a Collection> + 'a {
+        self.groups.iter().filter(|group| group.members.iter().any(|device| location.members.contains(device)))
+    }
+
+    fn collections(&self, kind: Kind) -> &[Collection] {
+        match kind {
+            Kind::Group => &self.groups,
+            Kind::Location => &self.locations,
+        }
+    }
+
+    fn collections_mut(&mut self, kind: Kind) -> &mut Vec<Collection> {
+        match kind {
+            Kind::Group => &mut self.groups,
+            Kind::Location => &mut self.locations,
+        }
+    }
+
+    /// Creates a group or location with a new ID and moves `members` into it. Returns the new ID.
+    pub fn create(&mut self, client: &mut LifxClient, kind: Kind, label: &str, members: &[Device]) -> Result<[u8; 16], GroupError> {
+        check_label(label)?;
+        let membership = Membership::new(label);
+        self.assign(client, kind, members, &membership)?;
+
+        Ok(membership.id)
+    }
+
+    /// Relabels a group or location on every one of its members.
+    pub fn rename(&mut self, client: &mut LifxClient, kind: Kind, label: &str, new_label: &str) -> Result<(), GroupError> {
+        check_label(new_label)?;
+        let collection = self.find(kind, label)?;
+        let members = collection.members.clone();
+        // The newer timestamp makes this label win over any copy a member missed the change for
+        let membership = Membership { id: collection.id, label: String::from(new_label), updated_at: now() };
+
+        self.assign(client, kind, &members, &membership)
+    }
+
+    /// Moves a device into an existing group or location, out of whichever it was in before.
+    pub fn move_device(&mut self, client: &mut LifxClient, kind: Kind, device: &Device, to: &str) -> Result<(), GroupError> {
+        let collection = self.find(kind, to)?;
+        let membership = Membership { id: collection.id, label: collection.label.clone(), updated_at: now() };
+        self.assign(client, kind, core::slice::from_ref(device), &membership)
+    }
+
+    fn find(&self, kind: Kind, label: &str) -> Result<&Collection, GroupError> {
+        self.collections(kind)
+            .iter()
+            .find(|collection| collection.label == label)
+            .ok_or_else(|| GroupError::NotFound(String::from(label)))
+    }
+
+    // Sends the membership to each device and records the ones that took it
+    fn assign(&mut self, client: &mut LifxClient, kind: Kind, devices: &[Device], membership: &Membership) -> Result<(), GroupError> {
+        let mut failures = Vec::new();
+
+        for device in devices {
+            if let Err(err) = apply(client, kind, device, membership) {
+                failures.push((*device, err));
+                continue;
+            }
+
+            let collections = self.collections_mut(kind);
+            for collection in collections.iter_mut() {
+                collection.members.retain(|member| member != device);
+            }
+            collections.retain(|collection| !collection.members.is_empty());
+
+            resolve(collections, *device, membership);
+            collections.sort_by(|a, b| a.label.cmp(&b.label));
+        }
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(GroupError::Members(failures))
+        }
+    }
+
+    /// Devices that didn't say which group or location they are in.
+    pub fn unreachable(&self) -> &[Device] {
+        &self.unreachable
+    }
+}
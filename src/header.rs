@@ -1,4 +1,5 @@
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LifxHeader {
     pub size: u16,           // 16 bits
     pub protocol: u16,       // 16 bits
@@ -6,13 +7,17 @@ pub struct LifxHeader {
     pub tagged: bool,        // 1 bit
     pub origin: u8,          // 2 bits
     pub source: u32,         // 32 bits
+    #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
     pub target: [u8; 8],     // 64 bits (8 bytes)
+    #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
     pub _reserved_1: [u8; 6], // 48 bits (6 bytes)
     // Combined 8 bits for res_required, ack_required, and reserved_2
     pub flags_and_reserved_2: u8, // 8 bits
     pub sequence: u8,           // 8 bits
+    #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
     pub _reserved_3: [u8; 8],    // 64 bits (8 bytes)
     pub packet_number: u16,          // 16 bits
+    #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
     pub _reserved_4: [u8; 2],    // 16 bits (2 bytes)
 }
 
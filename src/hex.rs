@@ -0,0 +1,103 @@
+// serde helpers rendering byte arrays as lowercase hex strings, used via `#[serde(with = "crate::hex")]`
+use core::fmt;
+
+use serde::{de, Deserializer, Serializer};
+
+const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+pub fn serialize<S, T>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: AsRef<[u8]>,
+{
+    serializer.collect_str(&Hex(bytes.as_ref()))
+}
+
+pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromHex,
+{
+    deserializer.deserialize_str(HexVisitor(core::marker::PhantomData))
+}
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in self.0 {
+            let digits = [DIGITS[(byte >> 4) as usize], DIGITS[(byte & 0xF) as usize]];
+            f.write_str(core::str::from_utf8(&digits).unwrap())?;
+        }
+
+        Ok(())
+    }
+}
+
+fn decode_nibble(digit: u8) -> Option<u8> {
+    match digit {
+        b'0'..=b'9' => Some(digit - b'0'),
+        b'a'..=b'f' => Some(digit - b'a' + 10),
+        b'A'..=b'F' => Some(digit - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn decode(hex: &str) -> impl Iterator<Item = Option<u8>> + '_ {
+    hex.as_bytes().chunks(2).map(|pair| match pair {
+        [high, low] => Some((decode_nibble(*high)? << 4) | decode_nibble(*low)?),
+        _ => None,
+    })
+}
+
+pub trait FromHex: Sized {
+    fn from_hex(hex: &str) -> Option<Self>;
+}
+
+impl<const N: usize> FromHex for [u8; N] {
+    fn from_hex(hex: &str) -> Option<Self> {
+        if hex.len() != N * 2 {
+            return None;
+        }
+
+        let mut bytes = [0u8; N];
+        for (byte, decoded) in bytes.iter_mut().zip(decode(hex)) {
+            *byte = decoded?;
+        }
+
+        Some(bytes)
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl FromHex for Vec<u8> {
+    fn from_hex(hex: &str) -> Option<Self> {
+        decode(hex).collect()
+    }
+}
+
+#[cfg(feature = "no-std")]
+impl<const N: usize> FromHex for heapless::Vec<u8, N> {
+    fn from_hex(hex: &str) -> Option<Self> {
+        let mut bytes = heapless::Vec::new();
+        for decoded in decode(hex) {
+            bytes.push(decoded?).ok()?;
+        }
+
+        Some(bytes)
+    }
+}
+
+struct HexVisitor<T>(core::marker::PhantomData<T>);
+
+impl<T: FromHex> de::Visitor<'_> for HexVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a hex encoded byte string")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        T::from_hex(value).ok_or_else(|| E::invalid_value(de::Unexpected::Str(value), &self))
+    }
+}
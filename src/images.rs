@@ -0,0 +1,166 @@
+use std::{fmt, fs::File, io::BufReader, path::Path, thread, time::Duration};
+
+use image::{
+    codecs::gif::GifDecoder,
+    imageops::{self, FilterType},
+    AnimationDecoder, ImageFormat, ImageReader, RgbaImage,
+};
+
+use crate::{
+    canvas::{Canvas, TileChain},
+    client::{ClientError, LifxClient},
+    Hsbk,
+};
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    /// The file isn't a PNG or GIF, or is damaged
+    Decode(image::ImageError),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "I/O error: {}", err),
+            ImageError::Decode(err) => write!(f, "Failed to decode image: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io(err) => Some(err),
+            ImageError::Decode(err) => Some(err),
+        }
+    }
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(err: std::io::Error) -> Self {
+        ImageError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for ImageError {
+    fn from(err: image::ImageError) -> Self {
+        ImageError::Decode(err)
+    }
+}
+
+/// How an image is fitted to a canvas of a different shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Fit {
+    /// Scaled to exactly the canvas size, distorting it if the shapes differ
+    Stretch,
+    /// Scaled to fit inside the canvas and centred, leaving unlit bars on two sides
+    #[default]
+    Contain,
+    /// Scaled to cover the whole canvas and centred, cropping two sides
+    Cover,
+}
+
+/// How to turn an image into canvas pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageOptions {
+    pub fit: Fit,
+    /// Blends neighbouring pixels when scaling, which suits photos; without it every canvas pixel takes the
+    /// nearest image pixel, which keeps pixel art sharp
+    pub smooth: bool,
+    /// Temperature for the white and grey pixels
+    pub kelvin: u16,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        ImageOptions { fit: Fit::Contain, smooth: false, kelvin: 6500 }
+    }
+}
+
+/// One frame of an animation, and how long to show it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub canvas: Canvas,
+    pub delay: Duration,
+}
+
+/// Loads a PNG or GIF, taking the first frame of an animation, as a `width` by `height` canvas.
+pub fn load(path: impl AsRef<Path>, width: usize, height: usize, options: &ImageOptions) -> Result<Canvas, ImageError> {
+    let image = ImageReader::open(path)?.with_guessed_format()?.decode()?;
+    Ok(to_canvas(&image.to_rgba8(), width, height, options))
+}
+
+/// Loads every frame of a GIF with its delay. Other images load as a single frame with no delay.
+pub fn load_frames(path: impl AsRef<Path>, width: usize, height: usize, options: &ImageOptions) -> Result<Vec<Frame>, ImageError> {
+    let path = path.as_ref();
+    let reader = ImageReader::open(path)?.with_guessed_format()?;
+
+    if reader.format() != Some(ImageFormat::Gif) {
+        return Ok(vec![Frame { canvas: load(path, width, height, options)?, delay: Duration::ZERO }]);
+    }
+
+    let decoder = GifDecoder::new(BufReader::new(File::open(path)?))?;
+    let mut frames = Vec::new();
+
+    // Frames come out already composited over the ones before, as the GIF's disposal methods say
+    for frame in decoder.into_frames() {
+        let frame = frame?;
+        let (numerator, denominator) = frame.delay().numer_denom_ms();
+        let delay = Duration::from_micros(numerator as u64 * 1000 / denominator.max(1) as u64);
+
+        frames.push(Frame { canvas: to_canvas(frame.buffer(), width, height, options), delay });
+    }
+
+    Ok(frames)
+}
+
+/// Scales an image to a `width` by `height` canvas. Transparent pixels dim towards unlit.
+pub fn to_canvas(image: &RgbaImage, width: usize, height: usize, options: &ImageOptions) -> Canvas {
+    let mut canvas = Canvas::new(width, height);
+    if width == 0 || height == 0 || image.width() == 0 || image.height() == 0 {
+        return canvas;
+    }
+
+    let (image_width, image_height) = (image.width() as f32, image.height() as f32);
+    let scale_x = width as f32 / image_width;
+    let scale_y = height as f32 / image_height;
+    let (scaled_width, scaled_height) = match options.fit {
+        Fit::Stretch => (width as f32, height as f32),
+        Fit::Contain => (image_width * scale_x.min(scale_y), image_height * scale_x.min(scale_y)),
+        Fit::Cover => (image_width * scale_x.max(scale_y), image_height * scale_x.max(scale_y)),
+    };
+
+    let filter = if options.smooth { FilterType::Triangle } else { FilterType::Nearest };
+    let scaled = imageops::resize(image, (scaled_width.round() as u32).max(1), (scaled_height.round() as u32).max(1), filter);
+
+    // Centre the scaled image, which may hang off the canvas on two sides
+    let offset_x = (width as i64 - scaled.width() as i64) / 2;
+    let offset_y = (height as i64 - scaled.height() as i64) / 2;
+
+    for (x, y, pixel) in scaled.enumerate_pixels() {
+        let (canvas_x, canvas_y) = (x as i64 + offset_x, y as i64 + offset_y);
+        if canvas_x < 0 || canvas_y < 0 {
+            continue;
+        }
+
+        let [red, green, blue, alpha] = pixel.0;
+        let color = Hsbk::from_rgb(red, green, blue, options.kelvin);
+        let brightness = (color.brightness as u32 * alpha as u32 / 255) as u16;
+        canvas.set(canvas_x as usize, canvas_y as usize, Hsbk { brightness, ..color });
+    }
+
+    canvas
+}
+
+/// Shows the frames on a tile chain, each for its delay, going through them `loops` times.
+pub fn play(client: &mut LifxClient, chain: &TileChain, frames: &[Frame], loops: usize) -> Result<(), ClientError> {
+    for _ in 0..loops {
+        for frame in frames {
+            chain.draw(client, &frame.canvas, Duration::ZERO)?;
+            thread::sleep(frame.delay);
+        }
+    }
+
+    Ok(())
+}
@@ -1,19 +1,59 @@
+#![cfg_attr(feature = "no-std", no_std)]
+
 #[cfg(feature = "no-std")]
 extern crate heapless;
 
 use lifx_serialization::{LifxDeserializationError, LifxPayload};
 
+pub use color::Hsbk;
+pub use firmware::FirmwareVersion;
 pub use header::LifxHeader;
-pub use messages::Message;
+pub use messages::{DeviceRequest, DeviceResponse, Message, MessageRef};
 pub use request_options::LifxRequestOptions;
 
+#[cfg(feature = "serde")]
+mod arrays;
+#[cfg(not(feature = "no-std"))]
+pub mod canvas;
+#[cfg(not(feature = "no-std"))]
+pub mod circadian;
+#[cfg(not(feature = "no-std"))]
+pub mod client;
+pub mod color;
+#[cfg(not(feature = "no-std"))]
+pub mod effects;
+#[cfg(not(feature = "no-std"))]
+pub mod emulator;
+pub mod firmware;
+pub mod firmware_effects;
+#[cfg(not(feature = "no-std"))]
+pub mod groups;
 pub mod header;
+#[cfg(feature = "serde")]
+mod hex;
+#[cfg(all(feature = "images", not(feature = "no-std")))]
+pub mod images;
+#[cfg(not(feature = "no-std"))]
+pub mod light;
 pub mod messages;
+#[cfg(not(feature = "no-std"))]
+pub mod monitor;
+pub mod products;
 pub mod request_options;
-
-pub fn serialize_lifx_packet(
+#[cfg(not(feature = "no-std"))]
+mod rng;
+#[cfg(not(feature = "no-std"))]
+pub mod scene;
+#[cfg(not(feature = "no-std"))]
+pub mod schedule;
+#[cfg(not(feature = "no-std"))]
+pub mod simulator;
+#[cfg(feature = "serde")]
+mod tagging;
+
+pub fn serialize_lifx_packet<P: LifxPayload>(
     request_options: &LifxRequestOptions,
-    payload: &messages::Message,
+    payload: &P,
     buffer: &mut [u8],
 ) {
     let packet_number = payload.packet_number();
@@ -57,21 +97,41 @@ pub fn serialize_lifx_packet(
 pub fn deserialize_lifx_packet(
     bytes: &[u8],
 ) -> Result<(LifxHeader, messages::Message), LifxDeserializationError> {
-    let header = LifxHeader::from_bytes(&bytes[0..36])?;
-    let payload = match messages::Message::from_bytes(header.packet_number, &bytes[36..]) {
-        Ok(payload) => payload,
-        Err(err) => {
-            match err {
-                LifxDeserializationError::InvalidPacketNumber(packet_number) => {
-                    log::debug!("Unrecognized Packet: ##{}", packet_number);
-                    log::debug!("Bytes: {:?}", bytes);
-                }
-                _ => {}
-            }
-
-            return Err(err);
-        }
-    };
+    let (header, payload) = deserialize_lifx_packet_as::<messages::Message>(bytes)?;
+
+    if let messages::Message::Unknown { packet_number, .. } = payload {
+        log::debug!("Unrecognized Packet: ##{}", packet_number);
+        log::debug!("Bytes: {:?}", bytes);
+    }
 
     Ok((header, payload))
 }
+
+/// Decodes a packet as any payload type, e.g. a [`DeviceRequest`] on the device side of a connection.
+pub fn deserialize_lifx_packet_as<P: LifxPayload>(bytes: &[u8]) -> Result<(LifxHeader, P), LifxDeserializationError> {
+    let header = LifxHeader::from_bytes(bytes)?;
+    let payload = P::from_bytes(header.packet_number, payload_bytes(&header, bytes)?)?;
+
+    Ok((header, payload))
+}
+
+/// Decodes a packet without copying: string and byte array fields borrow from `bytes`.
+pub fn deserialize_lifx_packet_ref(
+    bytes: &[u8],
+) -> Result<(LifxHeader, messages::MessageRef<'_>), LifxDeserializationError> {
+    let header = LifxHeader::from_bytes(bytes)?;
+    let payload = messages::MessageRef::from_bytes(header.packet_number, payload_bytes(&header, bytes)?)?;
+
+    Ok((header, payload))
+}
+
+// The receive buffer may be longer than the packet, so only hand the payload the header describes to the decoder.
+fn payload_bytes<'a>(header: &LifxHeader, bytes: &'a [u8]) -> Result<&'a [u8], LifxDeserializationError> {
+    let size = header.size as usize;
+
+    if size < 36 || bytes.len() < size {
+        return Err(LifxDeserializationError::InvalidPacketSize);
+    }
+
+    Ok(&bytes[36..size])
+}
@@ -0,0 +1,146 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    client::{ClientError, Device, LifxClient},
+    messages::responses,
+    DeviceRequest, Hsbk,
+};
+
+/// What a light last reported about itself, or was last successfully told to become.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LightState {
+    pub color: Hsbk,
+    pub power: u16,
+    pub label: String,
+}
+
+impl LightState {
+    pub fn is_on(&self) -> bool {
+        self.power > 0
+    }
+}
+
+impl From<responses::LightState> for LightState {
+    fn from(state: responses::LightState) -> Self {
+        LightState {
+            color: Hsbk::new(state.hue, state.saturation, state.brightness, state.kelvin),
+            power: state.power,
+            label: state.label,
+        }
+    }
+}
+
+/// A single light, remembering its state between calls so that changing one property doesn't need a round trip
+/// to find out the others.
+///
+/// Every method takes the client to send with, so any number of lights can share one socket.
+#[derive(Debug, Clone)]
+pub struct Light {
+    device: Device,
+    state: Option<LightState>,
+    updated_at: Option<Instant>,
+}
+
+impl Light {
+    pub fn new(device: Device) -> Self {
+        Light { device, state: None, updated_at: None }
+    }
+
+    pub fn device(&self) -> &Device {
+        &self.device
+    }
+
+    /// The cached state, without asking the light.
+    pub fn state(&self) -> Option<&LightState> {
+        self.state.as_ref()
+    }
+
+    /// How long ago the cached state was last fetched or acknowledged, or `None` if it never has been fetched.
+    pub fn age(&self) -> Option<Duration> {
+        self.updated_at.map(|updated_at| updated_at.elapsed())
+    }
+
+    /// Whether the cached state is missing or older than `max_age`.
+    pub fn is_stale(&self, max_age: Duration) -> bool {
+        !matches!(self.age(), Some(age) if age <= max_age)
+    }
+
+    /// Asks the light for its current state and caches it.
+    pub fn get_state(&mut self, client: &mut LifxClient) -> Result<&LightState, ClientError> {
+        let state: responses::LightState = client.request(&self.device, &DeviceRequest::GetColor)?;
+        self.updated_at = Some(Instant::now());
+
+        Ok(self.state.insert(state.into()))
+    }
+
+    /// Returns the cached state, asking the light first if it is missing or older than `max_age`.
+    pub fn get_state_within(&mut self, client: &mut LifxClient, max_age: Duration) -> Result<&LightState, ClientError> {
+        if self.is_stale(max_age) {
+            return self.get_state(client);
+        }
+
+        Ok(self.state.as_ref().unwrap())
+    }
+
+    pub fn power_on(&mut self, client: &mut LifxClient, duration: Duration) -> Result<(), ClientError> {
+        self.set_power(client, u16::MAX, duration)
+    }
+
+    pub fn power_off(&mut self, client: &mut LifxClient, duration: Duration) -> Result<(), ClientError> {
+        self.set_power(client, 0, duration)
+    }
+
+    pub fn set_power(&mut self, client: &mut LifxClient, level: u16, duration: Duration) -> Result<(), ClientError> {
+        client.send_acked(&self.device, &DeviceRequest::SetLightPower { level, duration_ms: milliseconds(duration) })?;
+        self.update(|state| state.power = level);
+
+        Ok(())
+    }
+
+    pub fn set_color(&mut self, client: &mut LifxClient, color: Hsbk, duration: Duration) -> Result<(), ClientError> {
+        let message = DeviceRequest::SetColor {
+            reserved_6: 0,
+            hue: color.hue,
+            saturation: color.saturation,
+            brightness: color.brightness,
+            kelvin: color.kelvin,
+            duration_ms: milliseconds(duration),
+        };
+
+        client.send_acked(&self.device, &message)?;
+        self.update(|state| state.color = color);
+
+        Ok(())
+    }
+
+    /// Changes brightness and keeps the rest of the colour, fetching it first if nothing is cached.
+    pub fn set_brightness(&mut self, client: &mut LifxClient, brightness: u16, duration: Duration) -> Result<(), ClientError> {
+        let color = self.current_color(client)?;
+        self.set_color(client, Hsbk { brightness, ..color }, duration)
+    }
+
+    /// Switches to a white of the given temperature at the current brightness.
+    pub fn set_kelvin(&mut self, client: &mut LifxClient, kelvin: u16, duration: Duration) -> Result<(), ClientError> {
+        let color = self.current_color(client)?;
+        self.set_color(client, Hsbk { saturation: 0, kelvin, ..color }, duration)
+    }
+
+    fn current_color(&mut self, client: &mut LifxClient) -> Result<Hsbk, ClientError> {
+        match &self.state {
+            Some(state) => Ok(state.color),
+            None => Ok(self.get_state(client)?.color),
+        }
+    }
+
+    // Applies a change the light has acknowledged. With nothing cached there is no full state to apply it to.
+    fn update(&mut self, change: impl FnOnce(&mut LightState)) {
+        if let Some(state) = &mut self.state {
+            change(state);
+            self.updated_at = Some(Instant::now());
+        }
+    }
+}
+
+pub(crate) fn milliseconds(duration: Duration) -> u32 {
+    duration.as_millis().min(u32::MAX as u128) as u32
+}
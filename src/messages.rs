@@ -1,16 +1,25 @@
 #[cfg(feature = "no-std")]
-use heapless::String;
+use heapless::{String, Vec};
 
-use lifx_serialization::LifxPayload;
+use lifx_serialization::{LifxField, LifxPayload};
 
-#[derive(LifxPayload, Debug, Clone)]
+use crate::color::Hsbk;
+
+#[derive(LifxPayload, Debug, Clone, PartialEq)]
+// With `serde`, the derived code only backs the impls in `tagging`, which add the packet number
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(remote = "Self"))]
+// Zone and tile colours are stored inline, as is the Unknown payload without an allocator
+#[allow(clippy::large_enum_variant)]
+#[directions(request = DeviceRequest, response = DeviceResponse)]
 pub enum Message {
     #[packet_number(1)]
+    #[direction(response)]
     Service { service: u8, port: u32 },
     // technically same as above, but new version?
     #[packet_number(3)]
     StateService { service: u8, port: u32 },
     #[packet_number(12)]
+    #[response(13)]
     GetMeshInfo {},
     #[packet_number(13)]
     MeshInfo {
@@ -22,6 +31,7 @@ pub enum Message {
     #[packet_number(15)]
     HostFirmware {
         build: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
         reserved_6: [u8; 8],
         version_minor: u16,
         version_major: u16,
@@ -36,12 +46,16 @@ pub enum Message {
     #[packet_number(19)]
     WifiFirmware {
         build: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
         reserved_6: [u8; 8],
         version_minor: u16,
         version_major: u16,
     },
     #[packet_number(22)]
     Power { level: u16 },
+    #[packet_number(45)]
+    #[direction(response)]
+    Acknowledgement,
     #[packet_number(25)]
     Label {
         #[cfg(feature = "no-std")]
@@ -63,6 +77,7 @@ pub enum Message {
     },
     #[packet_number(50)]
     Location {
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
         location: [u8; 16],
         #[cfg(feature = "no-std")]
         label: String<32>,
@@ -72,6 +87,7 @@ pub enum Message {
     },
     #[packet_number(53)]
     Group {
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
         group: [u8; 16],
         #[cfg(feature = "no-std")]
         label: String<32>,
@@ -80,8 +96,12 @@ pub enum Message {
         updated_at: u64,
     },
     #[packet_number(59)]
-    EchoResponse { echoing: [u8; 64] },
+    EchoResponse {
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        echoing: [u8; 64],
+    },
     #[packet_number(223)]
+    #[direction(response)]
     Unhandled { unhandled_type: u16 },
     #[packet_number(107)]
     LightState {
@@ -89,12 +109,14 @@ pub enum Message {
         saturation: u16,
         brightness: u16,
         kelvin: u16,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
         reserved_6: [u8; 2],
         power: u16,
         #[cfg(feature = "no-std")]
         label: String<32>,
         #[cfg(not(feature = "no-std"))]
         label: String,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
         reserved_7: [u8; 8],
     },
     #[packet_number(118)]
@@ -111,21 +133,115 @@ pub enum Message {
     HevCycleConfig { indication: u8, duration_s: u32 },
     #[packet_number(149)]
     LastHevCycleResult { result: u8 },
+    #[packet_number(503)]
+    StateZone {
+        zones_count: u8,
+        zone_index: u8,
+        hue: u16,
+        saturation: u16,
+        brightness: u16,
+        kelvin: u16,
+    },
+    #[packet_number(506)]
+    StateMultiZone {
+        zones_count: u8,
+        zone_index: u8,
+        #[size(64)]
+        colors: [Hsbk; 8],
+    },
+    #[packet_number(512)]
+    StateExtendedColorZones {
+        zones_count: u16,
+        zone_index: u16,
+        colors_count: u8,
+        #[size(656)]
+        #[cfg_attr(feature = "serde", serde(with = "crate::arrays"))]
+        colors: [Hsbk; 82],
+    },
+    #[packet_number(702)]
+    StateDeviceChain {
+        start_index: u8,
+        #[size(880)]
+        #[cfg_attr(feature = "serde", serde(with = "crate::arrays"))]
+        tile_devices: [TileDevice; 16],
+        tile_devices_count: u8,
+    },
+    #[packet_number(711)]
+    State64 {
+        tile_index: u8,
+        reserved_6: u8,
+        x: u8,
+        y: u8,
+        width: u8,
+        #[size(512)]
+        #[cfg_attr(feature = "serde", serde(with = "crate::arrays"))]
+        colors: [Hsbk; 64],
+    },
+    #[packet_number(509)]
+    StateMultiZoneEffect {
+        instance_id: u32,
+        effect_type: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_6: [u8; 2],
+        speed_ms: u32,
+        duration_ns: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_7: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_8: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_9: [u8; 4],
+        direction: u32,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_10: [u8; 24],
+    },
+    #[packet_number(720)]
+    StateTileEffect {
+        reserved_6: u8,
+        instance_id: u32,
+        effect_type: u8,
+        speed_ms: u32,
+        duration_ns: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_7: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_8: [u8; 4],
+        sky_type: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_9: [u8; 3],
+        cloud_saturation_min: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_10: [u8; 3],
+        cloud_saturation_max: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_11: [u8; 23],
+        palette_count: u8,
+        #[size(128)]
+        palette: [Hsbk; 16],
+    },
     #[packet_number(2)]
+    #[response(3)]
     GetService,
     #[packet_number(14)]
+    #[response(15)]
     GetHostFirmware,
     #[packet_number(16)]
+    #[response(17)]
     GetWifiInfo,
     #[packet_number(18)]
+    #[response(19)]
     GetWifiFirmware,
     #[packet_number(20)]
+    #[response(22)]
     GetPower,
     #[packet_number(21)]
+    #[response(22)]
     SetPower { level: u16 },
     #[packet_number(23)]
+    #[response(25)]
     GetLabel,
     #[packet_number(24)]
+    #[response(25)]
     SetLabel {
         #[cfg(feature = "no-std")]
         label: String<32>,
@@ -133,15 +249,21 @@ pub enum Message {
         label: String,
     },
     #[packet_number(32)]
+    #[response(33)]
     GetVersion,
     #[packet_number(34)]
+    #[response(35)]
     GetInfo,
     #[packet_number(38)]
+    #[direction(request)]
     SetReboot,
     #[packet_number(48)]
+    #[response(50)]
     GetLocation,
     #[packet_number(49)]
+    #[response(50)]
     SetLocation {
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
         location: [u8; 16],
         #[cfg(feature = "no-std")]
         label: String<32>,
@@ -150,9 +272,12 @@ pub enum Message {
         updated_at: u64,
     },
     #[packet_number(51)]
+    #[response(53)]
     GetGroup,
     #[packet_number(52)]
+    #[response(53)]
     SetGroup {
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
         group: [u8; 16],
         #[cfg(feature = "no-std")]
         label: String<32>,
@@ -161,10 +286,16 @@ pub enum Message {
         updated_at: u64,
     },
     #[packet_number(58)]
-    EchoRequest { echoing: [u8; 64] },
+    #[response(59)]
+    EchoRequest {
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        echoing: [u8; 64],
+    },
     #[packet_number(101)]
+    #[response(107)]
     GetColor,
     #[packet_number(102)]
+    #[response(107)]
     SetColor {
         reserved_6: u8,
         hue: u16,
@@ -174,6 +305,7 @@ pub enum Message {
         duration_ms: u32,
     },
     #[packet_number(103)]
+    #[response(107)]
     SetWaveform {
         reserved_6: u8,
         transient: u8,
@@ -187,10 +319,13 @@ pub enum Message {
         waveform: u8,
     },
     #[packet_number(116)]
+    #[response(118)]
     GetLightPower,
     #[packet_number(117)]
+    #[response(118)]
     SetLightPower { level: u16, duration_ms: u32 },
     #[packet_number(119)]
+    #[response(107)]
     SetWaveformOptional {
         reserved_6: u8,
         transient: u8,
@@ -208,21 +343,155 @@ pub enum Message {
         set_kelvin: u8,
     },
     #[packet_number(120)]
+    #[response(121)]
     GetInfrared,
     #[packet_number(122)]
+    #[response(121)]
     SetInfrared { brightness: u16 },
     #[packet_number(142)]
+    #[response(144)]
     GetHevCycle,
     #[packet_number(143)]
+    #[response(144)]
     SetHevCycle { duration_s: u32 },
     #[packet_number(145)]
+    #[response(147)]
     GetHevCycleConfiguration,
     #[packet_number(146)]
+    #[response(147)]
     SetHevCycleConfiguration { indication: u8, duration_s: u32 },
     #[packet_number(148)]
+    #[response(149)]
     GetLastHevCycleResult,
 
+    // Multizone strips and beams
+    #[packet_number(501)]
+    #[response(503, 506)]
+    SetColorZones {
+        start_index: u8,
+        end_index: u8,
+        hue: u16,
+        saturation: u16,
+        brightness: u16,
+        kelvin: u16,
+        duration_ms: u32,
+        apply: u8,
+    },
+    #[packet_number(502)]
+    #[response(503, 506)]
+    GetColorZones { start_index: u8, end_index: u8 },
+    #[packet_number(510)]
+    #[response(512)]
+    SetExtendedColorZones {
+        duration_ms: u32,
+        apply: u8,
+        zone_index: u16,
+        colors_count: u8,
+        #[size(656)]
+        #[cfg_attr(feature = "serde", serde(with = "crate::arrays"))]
+        colors: [Hsbk; 82],
+    },
+    #[packet_number(511)]
+    #[response(512)]
+    GetExtendedColorZones,
+    #[packet_number(508)]
+    #[response(509)]
+    SetMultiZoneEffect {
+        instance_id: u32,
+        effect_type: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_6: [u8; 2],
+        speed_ms: u32,
+        duration_ns: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_7: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_8: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_9: [u8; 4],
+        direction: u32,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_10: [u8; 24],
+    },
+    #[packet_number(507)]
+    #[response(509)]
+    GetMultiZoneEffect,
+
+    // Matrix devices: tiles, candles and ceilings
+    #[packet_number(701)]
+    #[response(702)]
+    GetDeviceChain,
+    #[packet_number(707)]
+    #[response(711)]
+    Get64 {
+        tile_index: u8,
+        length: u8,
+        reserved_6: u8,
+        x: u8,
+        y: u8,
+        width: u8,
+    },
+    #[packet_number(715)]
+    #[direction(request)]
+    Set64 {
+        tile_index: u8,
+        length: u8,
+        fb_index: u8,
+        x: u8,
+        y: u8,
+        width: u8,
+        duration_ms: u32,
+        #[size(512)]
+        #[cfg_attr(feature = "serde", serde(with = "crate::arrays"))]
+        colors: [Hsbk; 64],
+    },
+    #[packet_number(716)]
+    #[direction(request)]
+    CopyFrameBuffer {
+        tile_index: u8,
+        length: u8,
+        src_fb_index: u8,
+        dst_fb_index: u8,
+        src_x: u8,
+        src_y: u8,
+        dst_x: u8,
+        dst_y: u8,
+        width: u8,
+        height: u8,
+        duration_ms: u32,
+    },
+    #[packet_number(719)]
+    #[response(720)]
+    SetTileEffect {
+        reserved_6: u8,
+        reserved_7: u8,
+        instance_id: u32,
+        effect_type: u8,
+        speed_ms: u32,
+        duration_ns: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_8: [u8; 4],
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_9: [u8; 4],
+        sky_type: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_10: [u8; 3],
+        cloud_saturation_min: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_11: [u8; 3],
+        cloud_saturation_max: u8,
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        reserved_12: [u8; 23],
+        palette_count: u8,
+        #[size(128)]
+        palette: [Hsbk; 16],
+    },
+    #[packet_number(718)]
+    #[response(720)]
+    GetTileEffect { reserved_6: u8, reserved_7: u8 },
+
     #[packet_number(305)]
+    #[direction(request)]
     SetAccessPoint {
         interface: u8, // 1 for access point, 2 for station
 
@@ -240,6 +509,106 @@ pub enum Message {
 
         protocol: u8, // docs below
     },
+
+    // Any packet type not listed above, kept as raw bytes so it can be forwarded unchanged
+    #[unknown]
+    Unknown {
+        packet_number: u16,
+        #[cfg(feature = "no-std")]
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        payload: Vec<u8, MAX_PAYLOAD_SIZE>,
+        #[cfg(not(feature = "no-std"))]
+        #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+        payload: Vec<u8>,
+    },
+}
+
+// Largest payload an Unknown message can hold in no-std mode
+pub const MAX_PAYLOAD_SIZE: usize = 1024;
+
+/// Zones per StateMultiZone packet
+pub const MULTIZONE_COLORS: usize = 8;
+/// Zones per SetExtendedColorZones or StateExtendedColorZones packet
+pub const EXTENDED_MULTIZONE_COLORS: usize = 82;
+/// SetColorZones and SetExtendedColorZones `apply` values. Colours sent with `MULTIZONE_NO_APPLY` are buffered until
+/// a message with `MULTIZONE_APPLY` or `MULTIZONE_APPLY_ONLY` shows them all at once.
+pub const MULTIZONE_NO_APPLY: u8 = 0;
+pub const MULTIZONE_APPLY: u8 = 1;
+/// Shows the buffered colours and ignores the ones in this message
+pub const MULTIZONE_APPLY_ONLY: u8 = 2;
+/// Colours in a SetTileEffect or StateTileEffect palette
+pub const TILE_EFFECT_PALETTE_COLORS: usize = 16;
+/// Pixels per Set64 or State64 packet, enough for a whole 8x8 tile
+pub const TILE_COLORS: usize = 64;
+
+/// One device in a chain of tiles, as listed in StateDeviceChain.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileDevice {
+    pub accel_meas_x: i16,
+    pub accel_meas_y: i16,
+    pub accel_meas_z: i16,
+    pub reserved_6: i16,
+    /// Position relative to the other tiles, in tile widths
+    pub user_x: f32,
+    pub user_y: f32,
+    pub width: u8,
+    pub height: u8,
+    pub reserved_7: u8,
+    pub device_version_vendor: u32,
+    pub device_version_product: u32,
+    pub reserved_8: u32,
+    pub firmware_build: u64,
+    pub reserved_9: u64,
+    pub firmware_version_minor: u16,
+    pub firmware_version_major: u16,
+    pub reserved_10: u32,
+}
+
+impl LifxField for TileDevice {
+    const SIZE: usize = 55;
+
+    fn read(bytes: &[u8]) -> Self {
+        TileDevice {
+            accel_meas_x: i16::read(&bytes[0..2]),
+            accel_meas_y: i16::read(&bytes[2..4]),
+            accel_meas_z: i16::read(&bytes[4..6]),
+            reserved_6: i16::read(&bytes[6..8]),
+            user_x: f32::read(&bytes[8..12]),
+            user_y: f32::read(&bytes[12..16]),
+            width: bytes[16],
+            height: bytes[17],
+            reserved_7: bytes[18],
+            device_version_vendor: u32::read(&bytes[19..23]),
+            device_version_product: u32::read(&bytes[23..27]),
+            reserved_8: u32::read(&bytes[27..31]),
+            firmware_build: u64::read(&bytes[31..39]),
+            reserved_9: u64::read(&bytes[39..47]),
+            firmware_version_minor: u16::read(&bytes[47..49]),
+            firmware_version_major: u16::read(&bytes[49..51]),
+            reserved_10: u32::read(&bytes[51..55]),
+        }
+    }
+
+    fn write(&self, buffer: &mut [u8]) {
+        self.accel_meas_x.write(&mut buffer[0..2]);
+        self.accel_meas_y.write(&mut buffer[2..4]);
+        self.accel_meas_z.write(&mut buffer[4..6]);
+        self.reserved_6.write(&mut buffer[6..8]);
+        self.user_x.write(&mut buffer[8..12]);
+        self.user_y.write(&mut buffer[12..16]);
+        buffer[16] = self.width;
+        buffer[17] = self.height;
+        buffer[18] = self.reserved_7;
+        self.device_version_vendor.write(&mut buffer[19..23]);
+        self.device_version_product.write(&mut buffer[23..27]);
+        self.reserved_8.write(&mut buffer[27..31]);
+        self.firmware_build.write(&mut buffer[31..39]);
+        self.reserved_9.write(&mut buffer[39..47]);
+        self.firmware_version_minor.write(&mut buffer[47..49]);
+        self.firmware_version_major.write(&mut buffer[49..51]);
+        self.reserved_10.write(&mut buffer[51..55]);
+    }
 }
 
 // enum INTERFACE : byte
@@ -284,9 +653,9 @@ pub fn get_wifi_signal_quality(signal: f32) -> WifiSignalQuality {
         }
     } else if rssi == 4 || rssi == 5 || rssi == 6 {
         WifiSignalQuality::VeryLow
-    } else if rssi >= 7 && rssi <= 11 {
+    } else if (7..=11).contains(&rssi) {
         WifiSignalQuality::Low
-    } else if rssi >= 12 && rssi <= 16 {
+    } else if (12..=16).contains(&rssi) {
         WifiSignalQuality::Average
     } else if rssi > 16 {
         WifiSignalQuality::High
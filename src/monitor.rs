@@ -0,0 +1,271 @@
+use std::{
+    io,
+    net::{SocketAddr, ToSocketAddrs, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    client::{ClientError, Device, LifxClient, MAX_PACKET_SIZE},
+    deserialize_lifx_packet_as,
+    light::LightState,
+    messages::responses,
+    DeviceRequest, DeviceResponse, Hsbk,
+};
+
+/// Something the monitor noticed about a device.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// The device answered for the first time, or again after being offline; its state is in the monitor's table
+    Online { device: Device },
+    Power { device: Device, from: u16, to: u16 },
+    Color { device: Device, from: Hsbk, to: Hsbk },
+    Label { device: Device, from: String, to: String },
+    /// The device stopped answering polls
+    Offline { device: Device },
+}
+
+impl Change {
+    pub fn device(&self) -> &Device {
+        match self {
+            Change::Online { device }
+            | Change::Power { device, .. }
+            | Change::Color { device, .. }
+            | Change::Label { device, .. }
+            | Change::Offline { device } => device,
+        }
+    }
+}
+
+/// What the monitor knows about one device.
+#[derive(Debug, Clone)]
+pub struct Watched {
+    pub device: Device,
+    /// `None` until the device first answers, and for devices without a light, such as switches
+    pub state: Option<LightState>,
+    pub online: bool,
+    /// When the device last answered a poll or was heard on the network
+    pub last_seen: Option<Instant>,
+    missed_polls: u32,
+    next_poll: Instant,
+}
+
+/// Keeps a live table of device state and reports every change to it.
+///
+/// Two things feed the table. Devices answering a request sent with source 0, as the LIFX app and switches do,
+/// broadcast the reply for everyone to hear, so a monitor listening on port 56700 learns at once that a device
+/// was changed. Those replies describe the device from before a Set message, so what is heard only prompts a
+/// fresh poll of the device. Every device is also polled regularly, which catches changes made quietly and tells
+/// when a device has gone.
+///
+/// Polls wait for their replies, so a device that has gone away holds the monitor up for the client's timeout
+/// and retries on each poll until it is marked offline.
+pub struct StateMonitor {
+    client: LifxClient,
+    listener: Option<UdpSocket>,
+    devices: Vec<Watched>,
+    poll_interval: Duration,
+    offline_after: u32,
+    device_port: Option<u16>,
+}
+
+// The port LIFX devices send from
+const DEVICE_PORT: u16 = 56700;
+
+impl StateMonitor {
+    /// A monitor that polls with `client` every 30 seconds and marks a device offline after 3 missed polls.
+    pub fn new(client: LifxClient) -> Self {
+        StateMonitor {
+            client,
+            listener: None,
+            devices: Vec::new(),
+            poll_interval: Duration::from_secs(30),
+            offline_after: 3,
+            device_port: Some(DEVICE_PORT),
+        }
+    }
+
+    /// Listens for broadcast replies on `address`, normally `0.0.0.0:56700`. Devices heard that aren't being
+    /// watched yet are added.
+    pub fn listen<A: ToSocketAddrs>(&mut self, address: A) -> Result<&mut Self, ClientError> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_broadcast(true)?;
+        self.listener = Some(socket);
+
+        Ok(self)
+    }
+
+    pub fn set_poll_interval(&mut self, interval: Duration) -> &mut Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// How many polls in a row a device can miss before it counts as offline.
+    pub fn set_offline_after(&mut self, polls: u32) -> &mut Self {
+        self.offline_after = polls.max(1);
+        self
+    }
+
+    /// The port a heard packet must come from to count as a device, 56700 by default. Apps send from other ports,
+    /// so their requests aren't taken for devices. `None` accepts any port, for simulated devices, which each
+    /// have a port of their own.
+    pub fn set_device_port(&mut self, port: Option<u16>) -> &mut Self {
+        self.device_port = port;
+        self
+    }
+
+    /// Starts watching a device, polling it at the next step.
+    pub fn watch(&mut self, device: Device) {
+        if !self.devices.iter().any(|watched| watched.device.target == device.target) {
+            self.devices.push(Watched { device, state: None, online: false, last_seen: None, missed_polls: 0, next_poll: Instant::now() });
+        }
+    }
+
+    pub fn unwatch(&mut self, device: &Device) {
+        self.devices.retain(|watched| watched.device.target != device.target);
+    }
+
+    /// The whole table.
+    pub fn devices(&self) -> &[Watched] {
+        &self.devices
+    }
+
+    pub fn get(&self, device: &Device) -> Option<&Watched> {
+        self.devices.iter().find(|watched| watched.device.target == device.target)
+    }
+
+    /// The address the monitor listens on, if it does.
+    pub fn listen_addr(&self) -> Result<Option<SocketAddr>, ClientError> {
+        Ok(self.listener.as_ref().map(UdpSocket::local_addr).transpose()?)
+    }
+
+    /// Polls every device now, whether or not it is due.
+    pub fn refresh(&mut self, mut on_change: impl FnMut(Change)) {
+        for index in 0..self.devices.len() {
+            self.poll(index, &mut on_change);
+        }
+    }
+
+    /// Polls the devices that are due, then listens until the next one is due or `deadline` passes, whichever is
+    /// first. Devices heard while listening are polled straight away.
+    pub fn step(&mut self, deadline: Instant, mut on_change: impl FnMut(Change)) -> Result<(), ClientError> {
+        let now = Instant::now();
+        for index in 0..self.devices.len() {
+            if self.devices[index].next_poll <= now {
+                self.poll(index, &mut on_change);
+            }
+        }
+
+        let next_poll = self.devices.iter().map(|watched| watched.next_poll).min();
+        let until = next_poll.map_or(deadline, |next_poll| next_poll.min(deadline));
+
+        while let Some(heard) = self.receive_until(until)? {
+            let index = match self.devices.iter().position(|watched| watched.device.target == heard.target) {
+                Some(index) => index,
+                None => {
+                    self.watch(heard);
+                    self.devices.len() - 1
+                }
+            };
+
+            self.poll(index, &mut on_change);
+        }
+
+        Ok(())
+    }
+
+    /// Keeps the table up to date until `limit` has passed, handing every change to `on_change`. To follow the
+    /// changes from another thread, send them down a channel from `on_change`.
+    pub fn run(&mut self, limit: Duration, mut on_change: impl FnMut(Change)) -> Result<(), ClientError> {
+        let deadline = Instant::now() + limit;
+
+        while Instant::now() < deadline {
+            self.step(deadline, &mut on_change)?;
+        }
+
+        Ok(())
+    }
+
+    fn poll(&mut self, index: usize, on_change: &mut impl FnMut(Change)) {
+        let device = self.devices[index].device;
+        let result = self.client.request::<responses::LightState>(&device, &DeviceRequest::GetColor);
+
+        let watched = &mut self.devices[index];
+        let now = Instant::now();
+        watched.next_poll = now + self.poll_interval;
+
+        let state = match result {
+            Ok(state) => Some(LightState::from(state)),
+            // A device without a light still answers, or the product check that turned the poll down asked it
+            // for its version
+            Err(ClientError::Unhandled(_) | ClientError::Unsupported(_)) => None,
+            Err(err) => {
+                log::debug!("Monitor poll of {:?} failed: {}", device.target, err);
+                watched.missed_polls += 1;
+                if watched.online && watched.missed_polls >= self.offline_after {
+                    watched.online = false;
+                    on_change(Change::Offline { device });
+                }
+                return;
+            }
+        };
+
+        watched.missed_polls = 0;
+        watched.last_seen = Some(now);
+        let previous = std::mem::replace(&mut watched.state, state.clone());
+
+        if !watched.online {
+            watched.online = true;
+            on_change(Change::Online { device });
+            return;
+        }
+
+        if let (Some(from), Some(to)) = (previous, state) {
+            if from.power != to.power {
+                on_change(Change::Power { device, from: from.power, to: to.power });
+            }
+            if from.color != to.color {
+                on_change(Change::Color { device, from: from.color, to: to.color });
+            }
+            if from.label != to.label {
+                on_change(Change::Label { device, from: from.label, to: to.label });
+            }
+        }
+    }
+
+    // The next device heard on the listening socket before `deadline`, or `None` if there is no socket or nothing
+    // is heard. The device is addressed where its packet came from.
+    fn receive_until(&self, deadline: Instant) -> Result<Option<Device>, ClientError> {
+        let Some(listener) = &self.listener else {
+            std::thread::sleep(deadline.saturating_duration_since(Instant::now()));
+            return Ok(None);
+        };
+
+        let mut buffer = [0u8; MAX_PACKET_SIZE];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+
+            listener.set_read_timeout(Some(deadline - now))?;
+            let (size, from) = match listener.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
+
+            if self.device_port.is_some() && self.device_port != Some(from.port()) {
+                continue;
+            }
+
+            // Requests from other apps arrive here too, and are skipped. Packets of a type this crate doesn't know
+            // could be either, so they are skipped as well.
+            match deserialize_lifx_packet_as::<DeviceResponse>(&buffer[..size]) {
+                Ok((_, DeviceResponse::Unknown { .. })) => {}
+                Ok((header, _)) if header.target != [0; 8] => return Ok(Some(Device { target: header.target, address: from })),
+                Ok(_) => {}
+                Err(err) => log::debug!("Monitor dropping undecodable packet from {}: {}", from, err),
+            }
+        }
+    }
+}
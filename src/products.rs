@@ -0,0 +1,301 @@
+// The LIFX product catalogue, for working out what a device can do from its StateVersion reply. `catalogue.rs` is
+// generated from the official products.json with `cargo run --example generate_products -- products.json`.
+
+use core::fmt;
+
+use crate::{firmware::FirmwareVersion, DeviceRequest};
+
+mod catalogue;
+
+/// The kelvin range a device's whites cover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TemperatureRange {
+    pub min: u16,
+    pub max: u16,
+}
+
+impl TemperatureRange {
+    pub fn contains(&self, kelvin: u16) -> bool {
+        (self.min..=self.max).contains(&kelvin)
+    }
+}
+
+/// What a product supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Features {
+    /// Has HEV (germicidal) light
+    pub hev: bool,
+    /// Can show colours rather than only whites
+    pub color: bool,
+    /// Devices can be chained together, as tiles are
+    pub chain: bool,
+    /// Has a 2D grid of zones
+    pub matrix: bool,
+    /// Switches loads with relays
+    pub relays: bool,
+    /// Has physical buttons
+    pub buttons: bool,
+    /// Has infrared LEDs for night vision cameras
+    pub infrared: bool,
+    /// Has a strip of individually addressable zones
+    pub multizone: bool,
+    /// Supports the extended multizone messages that set every zone at once
+    pub extended_multizone: bool,
+    /// `None` for devices without a light
+    pub temperature_range: Option<TemperatureRange>,
+}
+
+impl Features {
+    pub const NONE: Features = Features {
+        hev: false,
+        color: false,
+        chain: false,
+        matrix: false,
+        relays: false,
+        buttons: false,
+        infrared: false,
+        multizone: false,
+        extended_multizone: false,
+        temperature_range: None,
+    };
+
+    fn upgrade(&mut self, upgrade: &FeatureUpgrade) {
+        let flags = [
+            (&mut self.hev, upgrade.hev),
+            (&mut self.color, upgrade.color),
+            (&mut self.chain, upgrade.chain),
+            (&mut self.matrix, upgrade.matrix),
+            (&mut self.relays, upgrade.relays),
+            (&mut self.buttons, upgrade.buttons),
+            (&mut self.infrared, upgrade.infrared),
+            (&mut self.multizone, upgrade.multizone),
+            (&mut self.extended_multizone, upgrade.extended_multizone),
+        ];
+
+        for (flag, upgraded) in flags {
+            if let Some(upgraded) = upgraded {
+                *flag = upgraded;
+            }
+        }
+
+        if upgrade.temperature_range.is_some() {
+            self.temperature_range = upgrade.temperature_range;
+        }
+    }
+}
+
+/// A feature some messages need.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Any light at all, which switches lack
+    Light,
+    Color,
+    Infrared,
+    Hev,
+    Multizone,
+    ExtendedMultizone,
+    Matrix,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Capability::Light => "a light",
+            Capability::Color => "colour",
+            Capability::Infrared => "infrared",
+            Capability::Hev => "HEV",
+            Capability::Multizone => "zones",
+            Capability::ExtendedMultizone => "extended multizone support",
+            Capability::Matrix => "a matrix of zones",
+        };
+
+        f.write_str(name)
+    }
+}
+
+/// Why a device can't carry out a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Unsupported {
+    Missing(Capability),
+    Kelvin { kelvin: u16, range: TemperatureRange },
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unsupported::Missing(capability) => write!(f, "Device does not have {}", capability),
+            Unsupported::Kelvin { kelvin, range } => {
+                write!(f, "{}K is outside the device's range of {}K to {}K", kelvin, range.min, range.max)
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+impl std::error::Error for Unsupported {}
+
+impl Features {
+    pub fn has(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Light => self.temperature_range.is_some(),
+            Capability::Color => self.color,
+            Capability::Infrared => self.infrared,
+            Capability::Hev => self.hev,
+            Capability::Multizone => self.multizone,
+            Capability::ExtendedMultizone => self.extended_multizone,
+            Capability::Matrix => self.matrix,
+        }
+    }
+
+    /// Checks that a device with these features can act on `message`. Devices silently ignore most messages
+    /// they can't carry out, so this is the only way to find out before sending.
+    pub fn check(&self, message: &DeviceRequest) -> Result<(), Unsupported> {
+        let (capability, saturation, kelvin) = match *message {
+            DeviceRequest::SetColor { saturation, kelvin, .. } | DeviceRequest::SetWaveform { saturation, kelvin, .. } => {
+                (Capability::Light, Some(saturation), Some(kelvin))
+            }
+            DeviceRequest::SetWaveformOptional { saturation, kelvin, set_saturation, set_kelvin, .. } => {
+                (Capability::Light, Some(saturation).filter(|_| set_saturation != 0), Some(kelvin).filter(|_| set_kelvin != 0))
+            }
+            DeviceRequest::GetColor | DeviceRequest::GetLightPower | DeviceRequest::SetLightPower { .. } => (Capability::Light, None, None),
+            DeviceRequest::GetInfrared | DeviceRequest::SetInfrared { .. } => (Capability::Infrared, None, None),
+            DeviceRequest::GetHevCycle
+            | DeviceRequest::SetHevCycle { .. }
+            | DeviceRequest::GetHevCycleConfiguration
+            | DeviceRequest::SetHevCycleConfiguration { .. }
+            | DeviceRequest::GetLastHevCycleResult => (Capability::Hev, None, None),
+            DeviceRequest::SetColorZones { saturation, kelvin, .. } => (Capability::Multizone, Some(saturation), Some(kelvin)),
+            DeviceRequest::GetColorZones { .. } => (Capability::Multizone, None, None),
+            DeviceRequest::GetExtendedColorZones | DeviceRequest::SetExtendedColorZones { .. } => {
+                (Capability::ExtendedMultizone, None, None)
+            }
+            DeviceRequest::GetMultiZoneEffect | DeviceRequest::SetMultiZoneEffect { .. } => (Capability::Multizone, None, None),
+            DeviceRequest::GetDeviceChain
+            | DeviceRequest::Get64 { .. }
+            | DeviceRequest::Set64 { .. }
+            | DeviceRequest::CopyFrameBuffer { .. }
+            | DeviceRequest::GetTileEffect { .. }
+            | DeviceRequest::SetTileEffect { .. } => (Capability::Matrix, None, None),
+            _ => return Ok(()),
+        };
+
+        if !self.has(capability) {
+            return Err(Unsupported::Missing(capability));
+        }
+
+        if saturation.is_some_and(|saturation| saturation > 0) && !self.color {
+            return Err(Unsupported::Missing(Capability::Color));
+        }
+
+        // A fully saturated colour has no white in it, so its kelvin is never used and is often left at 0
+        let kelvin = kelvin.filter(|_| saturation != Some(u16::MAX));
+        match (kelvin, self.temperature_range) {
+            (Some(kelvin), Some(range)) if !range.contains(kelvin) => Err(Unsupported::Kelvin { kelvin, range }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether [`Features::check`] could reject `message`, so it's worth finding out what the device is first.
+pub fn depends_on_features(message: &DeviceRequest) -> bool {
+    // Every message that needs something fails against a device with nothing
+    Features::NONE.check(message).is_err()
+}
+
+/// Features that change once a device runs a given firmware version; `None` leaves a feature as it was.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FeatureUpgrade {
+    pub hev: Option<bool>,
+    pub color: Option<bool>,
+    pub chain: Option<bool>,
+    pub matrix: Option<bool>,
+    pub relays: Option<bool>,
+    pub buttons: Option<bool>,
+    pub infrared: Option<bool>,
+    pub multizone: Option<bool>,
+    pub extended_multizone: Option<bool>,
+    pub temperature_range: Option<TemperatureRange>,
+}
+
+impl FeatureUpgrade {
+    pub const NONE: FeatureUpgrade = FeatureUpgrade {
+        hev: None,
+        color: None,
+        chain: None,
+        matrix: None,
+        relays: None,
+        buttons: None,
+        infrared: None,
+        multizone: None,
+        extended_multizone: None,
+        temperature_range: None,
+    };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Upgrade {
+    pub major: u16,
+    pub minor: u16,
+    pub features: FeatureUpgrade,
+}
+
+impl Upgrade {
+    /// The first firmware version with this upgrade.
+    pub fn version(&self) -> FirmwareVersion {
+        FirmwareVersion::new(self.major, self.minor)
+    }
+}
+
+/// A catalogue entry, with its features as of the earliest firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Product {
+    pub vendor: u32,
+    pub product: u32,
+    pub name: &'static str,
+    pub features: Features,
+    /// Sorted oldest firmware first
+    pub upgrades: &'static [Upgrade],
+}
+
+impl Product {
+    /// The product's features on a device running `firmware`.
+    pub fn features_at(&self, firmware: impl Into<FirmwareVersion>) -> Features {
+        let firmware = firmware.into();
+        let mut features = self.features;
+
+        for upgrade in self.upgrades {
+            if firmware.is_at_least(upgrade.major, upgrade.minor) {
+                features.upgrade(&upgrade.features);
+            }
+        }
+
+        features
+    }
+}
+
+/// A product as it behaves on a particular firmware version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ProductInfo {
+    pub vendor: u32,
+    pub product: u32,
+    pub name: &'static str,
+    pub features: Features,
+}
+
+/// Every product in the catalogue.
+pub fn products() -> &'static [Product] {
+    catalogue::PRODUCTS
+}
+
+/// Looks up a product by the vendor and product ids from `StateVersion` and the firmware version from
+/// `StateHostFirmware`, or `None` if the catalogue doesn't know it. A `(major, minor)` pair will do for the version.
+pub fn product_info(vendor: u32, product: u32, firmware: impl Into<FirmwareVersion>) -> Option<ProductInfo> {
+    let entry = products().iter().find(|entry| entry.vendor == vendor && entry.product == product)?;
+
+    Some(ProductInfo {
+        vendor,
+        product,
+        name: entry.name,
+        features: entry.features_at(firmware),
+    })
+}
@@ -0,0 +1,532 @@
+// Generated by `cargo run --example generate_products`. Do not edit by hand.
+use super::{FeatureUpgrade, Features, Product, TemperatureRange, Upgrade};
+
+pub(super) static PRODUCTS: &[Product] = &[
+    Product {
+        vendor: 1,
+        product: 1,
+        name: "LIFX Original 1000",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 3,
+        name: "LIFX Color 650",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 10,
+        name: "LIFX White 800 (Low Voltage)",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2700, max: 6500 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 11,
+        name: "LIFX White 800 (High Voltage)",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2700, max: 6500 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 15,
+        name: "LIFX Color 1000",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 18,
+        name: "LIFX White 900 BR30 (Low Voltage)",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 19,
+        name: "LIFX White 900 BR30 (High Voltage)",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 20,
+        name: "LIFX Color 1000 BR30",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 22,
+        name: "LIFX Color 1000",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 27,
+        name: "LIFX A19",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 28,
+        name: "LIFX BR30",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 29,
+        name: "LIFX A19 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 30,
+        name: "LIFX BR30 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 31,
+        name: "LIFX Z",
+        features: Features { color: true, multizone: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 77, features: FeatureUpgrade { extended_multizone: Some(true), ..FeatureUpgrade::NONE } },
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 32,
+        name: "LIFX Z",
+        features: Features { color: true, multizone: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 77, features: FeatureUpgrade { extended_multizone: Some(true), ..FeatureUpgrade::NONE } },
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 36,
+        name: "LIFX Downlight",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 37,
+        name: "LIFX Downlight",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 38,
+        name: "LIFX Beam",
+        features: Features { color: true, multizone: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 77, features: FeatureUpgrade { extended_multizone: Some(true), ..FeatureUpgrade::NONE } },
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 39,
+        name: "LIFX Downlight White to Warm",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 40,
+        name: "LIFX Downlight",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 43,
+        name: "LIFX A19",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 44,
+        name: "LIFX BR30",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 45,
+        name: "LIFX A19 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 46,
+        name: "LIFX BR30 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 2, minor: 80, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 49,
+        name: "LIFX Mini Color",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 50,
+        name: "LIFX Mini White to Warm",
+        features: Features { temperature_range: Some(TemperatureRange { min: 1500, max: 6500 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 3, minor: 70, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 4000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 51,
+        name: "LIFX Mini White",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2700, max: 2700 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 52,
+        name: "LIFX GU10",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 53,
+        name: "LIFX GU10",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 55,
+        name: "LIFX Tile",
+        features: Features { color: true, chain: true, matrix: true, temperature_range: Some(TemperatureRange { min: 2500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 57,
+        name: "LIFX Candle",
+        features: Features { color: true, matrix: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 59,
+        name: "LIFX Mini Color",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 60,
+        name: "LIFX Mini White to Warm",
+        features: Features { temperature_range: Some(TemperatureRange { min: 1500, max: 6500 }), ..Features::NONE },
+        upgrades: &[
+            Upgrade { major: 3, minor: 70, features: FeatureUpgrade { temperature_range: Some(TemperatureRange { min: 1500, max: 4000 }), ..FeatureUpgrade::NONE } },
+        ],
+    },
+    Product {
+        vendor: 1,
+        product: 61,
+        name: "LIFX Mini White",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2700, max: 2700 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 62,
+        name: "LIFX A19",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 63,
+        name: "LIFX BR30",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 64,
+        name: "LIFX A19 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 65,
+        name: "LIFX BR30 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 66,
+        name: "LIFX Mini White",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2700, max: 2700 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 68,
+        name: "LIFX Candle",
+        features: Features { color: true, matrix: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 70,
+        name: "LIFX Switch",
+        features: Features { relays: true, buttons: true, ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 71,
+        name: "LIFX Switch",
+        features: Features { relays: true, buttons: true, ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 81,
+        name: "LIFX Candle White to Warm",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2200, max: 6500 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 82,
+        name: "LIFX Filament Clear",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2100, max: 2100 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 85,
+        name: "LIFX Filament Amber",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2000, max: 2000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 87,
+        name: "LIFX Mini White",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2700, max: 2700 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 88,
+        name: "LIFX Mini White",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2700, max: 2700 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 89,
+        name: "LIFX Switch",
+        features: Features { relays: true, buttons: true, ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 90,
+        name: "LIFX Clean",
+        features: Features { hev: true, color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 91,
+        name: "LIFX Color",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 92,
+        name: "LIFX Color",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 94,
+        name: "LIFX BR30",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 96,
+        name: "LIFX Candle White to Warm",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2200, max: 6500 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 97,
+        name: "LIFX A19",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 98,
+        name: "LIFX BR30",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 99,
+        name: "LIFX Clean",
+        features: Features { hev: true, color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 100,
+        name: "LIFX Filament Clear",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2100, max: 2100 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 101,
+        name: "LIFX Filament Amber",
+        features: Features { temperature_range: Some(TemperatureRange { min: 2000, max: 2000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 109,
+        name: "LIFX A19 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 110,
+        name: "LIFX BR30 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 111,
+        name: "LIFX A19 Night Vision",
+        features: Features { color: true, infrared: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 117,
+        name: "LIFX Z",
+        features: Features { color: true, multizone: true, extended_multizone: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 118,
+        name: "LIFX Z",
+        features: Features { color: true, multizone: true, extended_multizone: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 119,
+        name: "LIFX Beam",
+        features: Features { color: true, multizone: true, extended_multizone: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 120,
+        name: "LIFX Beam",
+        features: Features { color: true, multizone: true, extended_multizone: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 123,
+        name: "LIFX Color",
+        features: Features { color: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 141,
+        name: "LIFX Neon",
+        features: Features { color: true, multizone: true, extended_multizone: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 143,
+        name: "LIFX String",
+        features: Features { color: true, multizone: true, extended_multizone: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 176,
+        name: "LIFX Ceiling",
+        features: Features { color: true, matrix: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+    Product {
+        vendor: 1,
+        product: 177,
+        name: "LIFX Ceiling",
+        features: Features { color: true, matrix: true, temperature_range: Some(TemperatureRange { min: 1500, max: 9000 }), ..Features::NONE },
+        upgrades: &[],
+    },
+];
@@ -1,5 +1,6 @@
 // See https://lan.developer.lifx.com/docs/packet-contents for more details
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LifxRequestOptions {
     /*
         The tagged field is a boolean flag that indicates whether the Frame Address target field is being used to address an
@@ -28,6 +29,7 @@ pub struct LifxRequestOptions {
         example, if you are discovering devices, the StateService (3) message will tell you the serial number for each LIFX 
         device on your network.
     */
+    #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
     pub target: [u8; 8],
 
 
@@ -58,8 +60,8 @@ pub struct LifxRequestOptions {
     pub sequence: u8,
 }
 
-impl LifxRequestOptions {
-    pub fn default() -> LifxRequestOptions {
+impl Default for LifxRequestOptions {
+    fn default() -> LifxRequestOptions {
         LifxRequestOptions {
             tagged: false,
             source: 1234567890,
@@ -69,7 +71,9 @@ impl LifxRequestOptions {
             sequence: 0,
         }
     }
+}
 
+impl LifxRequestOptions {
     pub fn increment_sequence(&mut self) {
         self.sequence = self.sequence.wrapping_add(1);
     }
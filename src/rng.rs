@@ -0,0 +1,37 @@
+use core::time::Duration;
+
+/// SplitMix64: small, fast and seedable, so a seed always produces the same sequence. Used for simulated network
+/// faults and for effects that flicker.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A number from 0.0 up to but not including 1.0.
+    pub(crate) fn unit(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub(crate) fn chance(&mut self, probability: f32) -> bool {
+        probability > 0.0 && self.unit() < probability
+    }
+
+    pub(crate) fn up_to(&mut self, max: Duration) -> Duration {
+        if max.is_zero() {
+            return Duration::ZERO;
+        }
+
+        Duration::from_nanos(self.next_u64() % max.as_nanos() as u64)
+    }
+}
@@ -0,0 +1,291 @@
+use std::time::Duration;
+
+use crate::{
+    client::{ClientError, Device, LifxClient},
+    light::milliseconds,
+    messages::{responses, EXTENDED_MULTIZONE_COLORS, MULTIZONE_APPLY, MULTIZONE_NO_APPLY},
+    products::{Capability, Features},
+    DeviceRequest, DeviceResponse, Hsbk,
+};
+
+/// The state of a set of devices at one moment, to be put back later with [`Scene::restore`].
+///
+/// With the `serde` feature a scene can be saved in any format serde supports, such as JSON or TOML.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    pub devices: Vec<DeviceScene>,
+}
+
+/// One device's part of a scene.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceScene {
+    #[cfg_attr(feature = "serde", serde(with = "crate::hex"))]
+    pub target: [u8; 8],
+    pub power: u16,
+    pub color: Hsbk,
+    /// Colour of every zone, for strips and beams
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub zones: Vec<Hsbk>,
+    /// The first 64 pixels of every tile in the chain, row by row, for matrix devices
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Vec::is_empty"))]
+    pub tiles: Vec<Vec<Hsbk>>,
+}
+
+/// What [`Scene::capture`] read.
+#[derive(Debug, Default)]
+pub struct Captured {
+    pub scene: Scene,
+    /// Devices that couldn't be read, which are left out of the scene. The rest were still captured.
+    pub failed: Vec<(Device, ClientError)>,
+}
+
+/// What [`Scene::restore`] did.
+#[derive(Debug, Default)]
+pub struct Restored {
+    /// Messages sent; devices already in their scene state need none
+    pub sent: usize,
+    /// Devices in the scene that weren't in the list to restore to
+    pub missing: Vec<[u8; 8]>,
+    /// Devices that couldn't be read or didn't acknowledge a change. The rest of the scene was still restored.
+    pub failed: Vec<(Device, ClientError)>,
+}
+
+impl Scene {
+    /// Reads the state of every device. Devices without a light, such as switches, are left out.
+    pub fn capture(client: &mut LifxClient, devices: &[Device]) -> Captured {
+        let mut captured = Captured::default();
+
+        for device in devices {
+            match DeviceScene::capture(client, device) {
+                Ok(Some(state)) => captured.scene.devices.push(state),
+                Ok(None) => {}
+                Err(err) => captured.failed.push((*device, err)),
+            }
+        }
+
+        captured
+    }
+
+    /// Brings each device back to its state in the scene over `duration`, matching them up with `devices` by
+    /// target. Each device is read first and only sent the changes it needs.
+    pub fn restore(&self, client: &mut LifxClient, devices: &[Device], duration: Duration) -> Restored {
+        let mut restored = Restored::default();
+
+        for scene in &self.devices {
+            let Some(device) = devices.iter().find(|device| device.target == scene.target) else {
+                restored.missing.push(scene.target);
+                continue;
+            };
+
+            match scene.restore(client, device, duration) {
+                Ok(sent) => restored.sent += sent,
+                Err(err) => restored.failed.push((*device, err)),
+            }
+        }
+
+        restored
+    }
+}
+
+impl DeviceScene {
+    /// Reads the state of one device, or `None` if it has no light.
+    pub fn capture(client: &mut LifxClient, device: &Device) -> Result<Option<Self>, ClientError> {
+        Ok(read(client, device)?.map(|(scene, _)| scene))
+    }
+
+    // Sends whatever differs between the device and this scene, returning how many messages that took
+    fn restore(&self, client: &mut LifxClient, device: &Device, duration: Duration) -> Result<usize, ClientError> {
+        let Some((current, features)) = read(client, device)? else {
+            return Ok(0);
+        };
+
+        let duration_ms = milliseconds(duration);
+        let mut messages = Vec::new();
+
+        if !self.zones.is_empty() && !current.zones.is_empty() {
+            messages.extend(zone_messages(&current.zones, &self.zones, features.extended_multizone, duration_ms));
+        } else if !self.tiles.is_empty() && !current.tiles.is_empty() {
+            messages.extend(tile_messages(&current.tiles, &self.tiles, duration_ms));
+        } else if self.color != current.color {
+            let Hsbk { hue, saturation, brightness, kelvin } = self.color;
+            messages.push(DeviceRequest::SetColor { reserved_6: 0, hue, saturation, brightness, kelvin, duration_ms });
+        }
+
+        if self.power != current.power {
+            messages.push(DeviceRequest::SetLightPower { level: self.power, duration_ms });
+        }
+
+        for message in &messages {
+            client.send_acked(device, message)?;
+        }
+
+        Ok(messages.len())
+    }
+}
+
+pub(crate) fn read(client: &mut LifxClient, device: &Device) -> Result<Option<(DeviceScene, Features)>, ClientError> {
+    // A device the catalogue doesn't know is treated as a plain light
+    let features = match client.product(device)? {
+        Some(info) if !info.features.has(Capability::Light) => return Ok(None),
+        Some(info) => info.features,
+        None => Features::NONE,
+    };
+
+    let state: responses::LightState = client.request(device, &DeviceRequest::GetColor)?;
+    let mut scene = DeviceScene {
+        target: device.target,
+        power: state.power,
+        color: Hsbk::new(state.hue, state.saturation, state.brightness, state.kelvin),
+        zones: Vec::new(),
+        tiles: Vec::new(),
+    };
+
+    if features.extended_multizone {
+        let replies = client.request_replies(device, &DeviceRequest::GetExtendedColorZones, |replies| assemble_zones(replies).is_some())?;
+        scene.zones = assemble_zones(&replies).unwrap_or_default();
+    } else if features.multizone {
+        let message = DeviceRequest::GetColorZones { start_index: 0, end_index: u8::MAX };
+        let replies = client.request_replies(device, &message, |replies| assemble_zones(replies).is_some())?;
+        scene.zones = assemble_zones(&replies).unwrap_or_default();
+    }
+
+    if features.matrix {
+        let chain: responses::StateDeviceChain = client.request(device, &DeviceRequest::GetDeviceChain)?;
+        let count = chain.tile_devices_count as usize;
+        let message = DeviceRequest::Get64 { tile_index: 0, length: count as u8, reserved_6: 0, x: 0, y: 0, width: 8 };
+        let replies = client.request_replies(device, &message, |replies| assemble_tiles(replies, count).is_some())?;
+        scene.tiles = assemble_tiles(&replies, count).unwrap_or_default();
+    }
+
+    Ok(Some((scene, features)))
+}
+
+// Puts zone replies together in order, once every zone is accounted for. Resends can deliver a chunk twice.
+fn assemble_zones(replies: &[DeviceResponse]) -> Option<Vec<Hsbk>> {
+    let mut zones: Vec<Option<Hsbk>> = Vec::new();
+
+    for reply in replies {
+        let (count, first, colors) = match reply {
+            DeviceResponse::StateZone { zones_count, zone_index, hue, saturation, brightness, kelvin } => {
+                (*zones_count as usize, *zone_index as usize, vec![Hsbk::new(*hue, *saturation, *brightness, *kelvin)])
+            }
+            DeviceResponse::StateMultiZone { zones_count, zone_index, colors } => {
+                (*zones_count as usize, *zone_index as usize, colors.to_vec())
+            }
+            DeviceResponse::StateExtendedColorZones { zones_count, zone_index, colors_count, colors } => {
+                (*zones_count as usize, *zone_index as usize, colors[..(*colors_count as usize).min(EXTENDED_MULTIZONE_COLORS)].to_vec())
+            }
+            _ => continue,
+        };
+
+        zones.resize(count, None);
+        for (zone, color) in zones.iter_mut().skip(first).zip(colors) {
+            *zone = Some(color);
+        }
+    }
+
+    zones.into_iter().collect::<Option<Vec<_>>>().filter(|zones| !zones.is_empty())
+}
+
+fn assemble_tiles(replies: &[DeviceResponse], count: usize) -> Option<Vec<Vec<Hsbk>>> {
+    let mut tiles = vec![None; count];
+
+    for reply in replies {
+        if let DeviceResponse::State64 { tile_index, colors, .. } = reply {
+            if let Some(tile) = tiles.get_mut(*tile_index as usize) {
+                *tile = Some(colors.to_vec());
+            }
+        }
+    }
+
+    tiles.into_iter().collect()
+}
+
+// One SetExtendedColorZones per block of 82 zones with a change in it, or on older firmware one SetColorZones per
+// run of same-coloured zones with a change in it. Either way only the last message applies, so they all change
+// together.
+pub(crate) fn zone_messages(current: &[Hsbk], target: &[Hsbk], extended: bool, duration_ms: u32) -> Vec<DeviceRequest> {
+    let changed = |index: usize| target.get(index).is_some_and(|color| current.get(index) != Some(color));
+    let count = current.len().min(target.len());
+    let mut messages = Vec::new();
+
+    if extended {
+        for first in (0..count).step_by(EXTENDED_MULTIZONE_COLORS) {
+            let block = first..(first + EXTENDED_MULTIZONE_COLORS).min(count);
+            if !block.clone().any(changed) {
+                continue;
+            }
+
+            let colors = &target[block];
+            messages.push(DeviceRequest::SetExtendedColorZones {
+                duration_ms,
+                apply: MULTIZONE_NO_APPLY,
+                zone_index: first as u16,
+                colors_count: colors.len() as u8,
+                colors: core::array::from_fn(|index| colors.get(index).copied().unwrap_or_default()),
+            });
+        }
+    } else {
+        let mut index = 0;
+        while index < count {
+            if !changed(index) {
+                index += 1;
+                continue;
+            }
+
+            // A run carries on over zones that already show its colour, as long as a changed one follows
+            let color = target[index];
+            let start = index;
+            let mut end = index;
+            while index + 1 < count && target[index + 1] == color {
+                index += 1;
+                if changed(index) {
+                    end = index;
+                }
+            }
+            index = end;
+
+            messages.push(DeviceRequest::SetColorZones {
+                start_index: start as u8,
+                end_index: index as u8,
+                hue: color.hue,
+                saturation: color.saturation,
+                brightness: color.brightness,
+                kelvin: color.kelvin,
+                duration_ms,
+                apply: MULTIZONE_NO_APPLY,
+            });
+            index += 1;
+        }
+    }
+
+    if let Some(
+        DeviceRequest::SetExtendedColorZones { apply, .. } | DeviceRequest::SetColorZones { apply, .. },
+    ) = messages.last_mut()
+    {
+        *apply = MULTIZONE_APPLY;
+    }
+
+    messages
+}
+
+pub(crate) fn tile_messages(current: &[Vec<Hsbk>], target: &[Vec<Hsbk>], duration_ms: u32) -> Vec<DeviceRequest> {
+    current
+        .iter()
+        .zip(target)
+        .enumerate()
+        .filter(|(_, (current, target))| current != target)
+        .map(|(index, (_, target))| DeviceRequest::Set64 {
+            tile_index: index as u8,
+            length: 1,
+            fb_index: 0,
+            x: 0,
+            y: 0,
+            width: 8,
+            duration_ms,
+            colors: core::array::from_fn(|pixel| target.get(pixel).copied().unwrap_or_default()),
+        })
+        .collect()
+}
@@ -0,0 +1,938 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    circadian::{Location, SunTimes},
+    client::{ClientError, Device, LifxClient},
+    firmware_effects::{Direction, MultiZoneEffect, Palette, SkyType, TileEffect},
+    light::milliseconds,
+    scene::Scene,
+    DeviceRequest, Hsbk,
+};
+
+#[derive(Debug)]
+pub enum ScheduleError {
+    Io(io::Error),
+    /// A line of a schedule file that couldn't be read, counting from 1
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleError::Io(err) => write!(f, "I/O error: {}", err),
+            ScheduleError::Parse { line, message } => write!(f, "Line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ScheduleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ScheduleError::Io(err) => Some(err),
+            ScheduleError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ScheduleError {
+    fn from(err: io::Error) -> Self {
+        ScheduleError::Io(err)
+    }
+}
+
+const MINUTES_PER_DAY: i64 = 1440;
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, and back
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month as i64 + 9) % 12) + 2) / 5 + day as i64 - 1;
+
+    era * 146097 + year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+
+    (year_of_era + era * 400 + (month <= 2) as i64, month, day)
+}
+
+fn unix_seconds(at: SystemTime) -> i64 {
+    match at.duration_since(UNIX_EPOCH) {
+        Ok(since) => since.as_secs() as i64,
+        Err(err) => -(err.duration().as_secs_f64().ceil() as i64),
+    }
+}
+
+fn from_unix_seconds(seconds: i64) -> SystemTime {
+    if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    }
+}
+
+/// A five field cron expression: minute, hour, day of the month, month and day of the week, with Sunday as 0 or 7.
+///
+/// Each field takes `*`, a number, a range like `1-5`, a list like `1,15` and a step like `*/15` or `0-30/10`.
+/// As in cron, when both the day of the month and the day of the week are restricted, either one matching is
+/// enough.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+    expression: String,
+}
+
+impl Cron {
+    fn matches_day(&self, days: i64, day: u32) -> bool {
+        let weekday = (days + 4).rem_euclid(7) as u32;
+        let day_matches = self.days & (1 << day) != 0;
+        let weekday_matches = self.weekdays & (1 << weekday) != 0;
+
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (false, true) => day_matches,
+            (true, false) => weekday_matches,
+            (false, false) => day_matches || weekday_matches,
+        }
+    }
+
+    /// The first whole minute after `at` that matches, with the expression read in local time `utc_offset`
+    /// minutes ahead of UTC. `None` if nothing matches within the next few years, as for `0 0 30 2 *`.
+    pub fn next_after(&self, at: SystemTime, utc_offset: i32) -> Option<SystemTime> {
+        let offset_seconds = utc_offset as i64 * 60;
+        let mut minute = (unix_seconds(at) + offset_seconds).div_euclid(60) + 1;
+        let give_up = minute + 5 * 366 * MINUTES_PER_DAY;
+
+        while minute < give_up {
+            let days = minute.div_euclid(MINUTES_PER_DAY);
+            let minute_of_day = minute.rem_euclid(MINUTES_PER_DAY);
+            let (year, month, day) = civil_from_days(days);
+
+            if self.months & (1 << month) == 0 {
+                let (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+                minute = days_from_civil(year, month, 1) * MINUTES_PER_DAY;
+            } else if !self.matches_day(days, day) {
+                minute = (days + 1) * MINUTES_PER_DAY;
+            } else if self.hours & (1 << (minute_of_day / 60)) == 0 {
+                minute = days * MINUTES_PER_DAY + (minute_of_day / 60 + 1) * 60;
+            } else if self.minutes & (1 << (minute_of_day % 60)) == 0 {
+                minute += 1;
+            } else {
+                return Some(from_unix_seconds(minute * 60 - offset_seconds));
+            }
+        }
+
+        None
+    }
+}
+
+// Parses one field into a bit mask of the values it allows, and whether it was `*`
+fn parse_field(field: &str, min: u32, max: u32) -> Result<(u64, bool), String> {
+    let mut mask = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("Bad step in {:?}", field))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(format!("Step of 0 in {:?}", field));
+        }
+
+        let value = |text: &str| match text.parse::<u32>() {
+            Ok(value) if (min..=max).contains(&value) => Ok(value),
+            _ => Err(format!("{:?} is not a number from {} to {}", text, min, max)),
+        };
+        let (first, last) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((first, last)) => (value(first)?, value(last)?),
+                // A single value with a step runs to the end, as in `5/15`
+                None if step > 1 => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+
+        for value in (first..=last).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok((mask, field == "*"))
+}
+
+impl FromStr for Cron {
+    type Err = String;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("A cron expression has 5 fields, not {}", fields.len()));
+        };
+
+        let (weekday_mask, any_weekday) = parse_field(weekdays, 0, 7)?;
+        let (day_mask, any_day) = parse_field(days, 1, 31)?;
+
+        Ok(Cron {
+            minutes: parse_field(minutes, 0, 59)?.0,
+            hours: parse_field(hours, 0, 23)?.0 as u32,
+            days: day_mask as u32,
+            months: parse_field(months, 1, 12)?.0 as u16,
+            // Sunday can be written as 7
+            weekdays: (weekday_mask | weekday_mask >> 7) as u8 & 0x7F,
+            any_day,
+            any_weekday,
+            expression: fields.join(" "),
+        })
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+/// When a job runs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum When {
+    Cron(Cron),
+    /// Sunrise at the schedule's location, moved by `offset` seconds, so -1800 is half an hour before
+    Sunrise { offset: i64 },
+    Sunset { offset: i64 },
+}
+
+impl When {
+    fn next_after(&self, at: SystemTime, utc_offset: i32, location: Option<Location>) -> Option<SystemTime> {
+        let (offset, sunset) = match self {
+            When::Cron(cron) => return cron.next_after(at, utc_offset),
+            When::Sunrise { offset } => (*offset, false),
+            When::Sunset { offset } => (*offset, true),
+        };
+
+        // Look from the day before, in case a large offset brings yesterday's sunset past `at`; near the poles
+        // the sun may not rise for months
+        let location = location?;
+        let start = unix_seconds(at);
+        (-1..=366).find_map(|day| {
+            let sun = SunTimes::on(location, from_unix_seconds(start + day * 86400));
+            let event = if sunset { sun.sunset } else { sun.sunrise }?;
+            let firing = unix_seconds(event) + offset;
+            (firing > start).then(|| from_unix_seconds(firing))
+        })
+    }
+}
+
+impl fmt::Display for When {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (event, offset) = match self {
+            When::Cron(cron) => return cron.fmt(f),
+            When::Sunrise { offset } => ("@sunrise", *offset),
+            When::Sunset { offset } => ("@sunset", *offset),
+        };
+
+        match offset {
+            0 => f.write_str(event),
+            offset if offset < 0 => write!(f, "{}-{}", event, DurationText(Duration::from_secs(offset.unsigned_abs()))),
+            offset => write!(f, "{}+{}", event, DurationText(Duration::from_secs(offset as u64))),
+        }
+    }
+}
+
+/// Which devices a job acts on.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Targets {
+    /// Every device the scheduler is given
+    All,
+    Devices(Vec<[u8; 8]>),
+}
+
+impl Targets {
+    fn select(&self, devices: &[Device]) -> Vec<Device> {
+        match self {
+            Targets::All => devices.to_vec(),
+            Targets::Devices(targets) => devices.iter().filter(|device| targets.contains(&device.target)).copied().collect(),
+        }
+    }
+}
+
+/// What a job does.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Power { on: bool, duration: Duration },
+    Color { color: Hsbk, duration: Duration },
+    /// Restores the scene added to the [`Scheduler`] under this name
+    Scene { name: String, duration: Duration },
+    MultiZoneEffect(MultiZoneEffect),
+    TileEffect(TileEffect),
+}
+
+/// One line of a schedule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Job {
+    /// Names the job in reports; unique within a schedule
+    pub name: String,
+    pub when: When,
+    pub targets: Targets,
+    /// Whether to still run the job, once, when the scheduler comes back after missing it
+    pub catch_up: bool,
+    pub action: Action,
+}
+
+/// A set of jobs, kept in a text file like a crontab.
+///
+/// ```text
+/// # Times are local, an hour ahead of UTC
+/// location 51.5074 -0.1278
+/// utc-offset +01:00
+///
+/// # name   when               targets                          action
+/// wake     30 6 * * 1-5       d073d5000001                     power on 10m
+/// evening  @sunset-30m        *                        catch-up scene evening 5m
+/// party    0 22 * * 6         d073d5000002,d073d5000003        strip-effect move 2s left
+/// ```
+///
+/// Each job line has a name, a cron expression or `@sunrise`/`@sunset` with an optional offset, `*` or a comma
+/// separated list of serial numbers, an optional `catch-up`, and an action:
+///
+/// - `power on|off [duration]`
+/// - `color hue:saturation:brightness:kelvin [duration]`, each a number from 0 to 65535
+/// - `scene name [duration]`
+/// - `strip-effect off`, `strip-effect move speed [left|right] [for duration]`
+/// - `tile-effect off`, `tile-effect flame speed [for duration]`, `tile-effect morph speed [color...] [for duration]`,
+///   `tile-effect sky speed sunrise|sunset|clouds:min:max [for duration]`
+///
+/// Durations are written like `500ms`, `90s` or `1h30m`. Times follow the fixed UTC offset all year, so a schedule
+/// needs editing when the clocks change.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schedule {
+    /// Needed for jobs that run at sunrise or sunset
+    pub location: Option<Location>,
+    /// Minutes local time is ahead of UTC, which cron expressions are read in
+    pub utc_offset: i32,
+    pub jobs: Vec<Job>,
+}
+
+/// One time a job runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Firing {
+    pub at: SystemTime,
+    pub job: Job,
+    /// Minutes ahead of UTC to show `at` in
+    pub utc_offset: i32,
+}
+
+impl fmt::Display for Firing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let local = unix_seconds(self.at) + self.utc_offset as i64 * 60;
+        let (year, month, day) = civil_from_days(local.div_euclid(86400));
+        let seconds = local.rem_euclid(86400);
+
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {} {} {}",
+            year,
+            month,
+            day,
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            OffsetText(self.utc_offset),
+            self.job.name,
+            ActionText(&self.job.action)
+        )
+    }
+}
+
+impl Schedule {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ScheduleError> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    /// Writes the schedule in its text form. Comments and layout of a file it was loaded from are not kept.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ScheduleError> {
+        Ok(fs::write(path, self.to_string())?)
+    }
+
+    pub fn job(&self, name: &str) -> Option<&Job> {
+        self.jobs.iter().find(|job| job.name == name)
+    }
+
+    /// When `job` next runs after `at`, or `None` if it never does, as for a sunrise job without a location.
+    pub fn next_firing(&self, job: &Job, at: SystemTime) -> Option<SystemTime> {
+        job.when.next_after(at, self.utc_offset, self.location)
+    }
+
+    /// The next `count` firings of every job after `at`, in order. This is what a dry run prints.
+    pub fn upcoming(&self, at: SystemTime, count: usize) -> Vec<Firing> {
+        let mut next: Vec<(Option<SystemTime>, &Job)> = self.jobs.iter().map(|job| (self.next_firing(job, at), job)).collect();
+        let mut firings = Vec::with_capacity(count);
+
+        while firings.len() < count {
+            let Some((slot, _)) = next.iter().enumerate().filter_map(|(index, (time, _))| time.map(|time| (index, time))).min_by_key(|(_, time)| *time)
+            else {
+                break;
+            };
+
+            let (time, job) = next[slot];
+            let time = time.unwrap();
+            firings.push(Firing { at: time, job: job.clone(), utc_offset: self.utc_offset });
+            next[slot].0 = self.next_firing(job, time);
+        }
+
+        firings
+    }
+
+    // The last time `job` ran in (`after`, `until`]
+    fn last_firing(&self, job: &Job, after: SystemTime, until: SystemTime) -> Option<SystemTime> {
+        let mut last = None;
+        let mut at = after;
+
+        while let Some(next) = self.next_firing(job, at).filter(|next| *next <= until) {
+            last = Some(next);
+            at = next;
+        }
+
+        last
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ScheduleError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut schedule = Schedule::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| ScheduleError::Parse { line: index + 1, message };
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let words: Vec<&str> = line.split_whitespace().collect();
+
+            match words[..] {
+                [] => {}
+                ["location", latitude, longitude] => {
+                    let latitude = latitude.parse().map_err(|_| error(format!("Bad latitude {:?}", latitude)))?;
+                    let longitude = longitude.parse().map_err(|_| error(format!("Bad longitude {:?}", longitude)))?;
+                    schedule.location = Some(Location::new(latitude, longitude));
+                }
+                ["utc-offset", offset] => schedule.utc_offset = parse_utc_offset(offset).map_err(error)?,
+                _ => {
+                    let job = parse_job(&words).map_err(error)?;
+                    if schedule.job(&job.name).is_some() {
+                        return Err(error(format!("There is already a job called {:?}", job.name)));
+                    }
+                    if schedule.location.is_none() && !matches!(job.when, When::Cron(_)) {
+                        return Err(error(String::from("Sunrise and sunset need a location line before them")));
+                    }
+                    schedule.jobs.push(job);
+                }
+            }
+        }
+
+        Ok(schedule)
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = self.location {
+            writeln!(f, "location {} {}", location.latitude, location.longitude)?;
+        }
+        if self.utc_offset != 0 {
+            writeln!(f, "utc-offset {}", OffsetText(self.utc_offset))?;
+        }
+
+        for job in &self.jobs {
+            let targets = match &job.targets {
+                Targets::All => String::from("*"),
+                Targets::Devices(targets) => targets.iter().map(serial).collect::<Vec<_>>().join(","),
+            };
+            let catch_up = if job.catch_up { " catch-up" } else { "" };
+
+            writeln!(f, "{} {} {}{} {}", job.name, job.when, targets, catch_up, ActionText(&job.action))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_job(words: &[&str]) -> Result<Job, String> {
+    let (name, rest) = words.split_first().ok_or("Empty job")?;
+
+    let (when, rest) = match rest.first() {
+        Some(event) if event.starts_with('@') => (parse_event(event)?, &rest[1..]),
+        _ if rest.len() >= 5 => (When::Cron(rest[..5].join(" ").parse()?), &rest[5..]),
+        _ => return Err(format!("Job {:?} needs a time", name)),
+    };
+
+    let (targets, rest) = rest.split_first().ok_or_else(|| format!("Job {:?} needs targets", name))?;
+    let targets = match *targets {
+        "*" => Targets::All,
+        list => Targets::Devices(list.split(',').map(parse_serial).collect::<Result<_, _>>()?),
+    };
+
+    let (catch_up, rest) = match rest.split_first() {
+        Some((&"catch-up", rest)) => (true, rest),
+        _ => (false, rest),
+    };
+
+    Ok(Job { name: name.to_string(), when, targets, catch_up, action: parse_action(rest)? })
+}
+
+fn parse_event(event: &str) -> Result<When, String> {
+    let (name, offset) = match event.find(['+', '-']) {
+        Some(sign) => {
+            let offset = parse_duration(&event[sign + 1..])?.as_secs() as i64;
+            (&event[..sign], if event[sign..].starts_with('-') { -offset } else { offset })
+        }
+        None => (event, 0),
+    };
+
+    match name {
+        "@sunrise" => Ok(When::Sunrise { offset }),
+        "@sunset" => Ok(When::Sunset { offset }),
+        _ => Err(format!("Unknown event {:?}, expected @sunrise or @sunset", name)),
+    }
+}
+
+fn parse_action(words: &[&str]) -> Result<Action, String> {
+    // An optional transition time after the action's arguments
+    let duration = |words: &[&str]| match words {
+        [] => Ok(Duration::ZERO),
+        [duration] => parse_duration(duration),
+        _ => Err(format!("Unexpected {:?}", words.join(" "))),
+    };
+
+    match words {
+        ["power", "on", rest @ ..] => Ok(Action::Power { on: true, duration: duration(rest)? }),
+        ["power", "off", rest @ ..] => Ok(Action::Power { on: false, duration: duration(rest)? }),
+        ["color", color, rest @ ..] => Ok(Action::Color { color: parse_color(color)?, duration: duration(rest)? }),
+        ["scene", name, rest @ ..] => Ok(Action::Scene { name: name.to_string(), duration: duration(rest)? }),
+        ["strip-effect", rest @ ..] => parse_strip_effect(rest).map(Action::MultiZoneEffect),
+        ["tile-effect", rest @ ..] => parse_tile_effect(rest).map(Action::TileEffect),
+        [] => Err(String::from("Missing action")),
+        _ => Err(format!("Unknown action {:?}", words[0])),
+    }
+}
+
+// Splits off a trailing `for duration`, how long an effect plays
+fn effect_duration<'a>(words: &'a [&'a str]) -> Result<(&'a [&'a str], Option<Duration>), String> {
+    match words {
+        [rest @ .., "for", duration] => Ok((rest, Some(parse_duration(duration)?))),
+        _ => Ok((words, None)),
+    }
+}
+
+fn parse_strip_effect(words: &[&str]) -> Result<MultiZoneEffect, String> {
+    let (words, duration) = effect_duration(words)?;
+
+    match words {
+        ["off"] => Ok(MultiZoneEffect::Off),
+        ["move", speed, rest @ ..] => {
+            let direction = match rest {
+                [] | ["right"] => Direction::Right,
+                ["left"] => Direction::Left,
+                _ => return Err(format!("Unexpected {:?}", rest.join(" "))),
+            };
+            Ok(MultiZoneEffect::Move { speed: parse_duration(speed)?, direction, duration })
+        }
+        ["unknown", effect_type] => Ok(MultiZoneEffect::Unknown(effect_type.parse().map_err(|_| format!("Bad effect type {:?}", effect_type))?)),
+        _ => Err(format!("Unknown strip effect {:?}", words.join(" "))),
+    }
+}
+
+fn parse_tile_effect(words: &[&str]) -> Result<TileEffect, String> {
+    let (words, duration) = effect_duration(words)?;
+
+    match words {
+        ["off"] => Ok(TileEffect::Off),
+        ["flame", speed] => Ok(TileEffect::Flame { speed: parse_duration(speed)?, duration }),
+        ["morph", speed, colors @ ..] => {
+            let colors = colors.iter().map(|color| parse_color(color)).collect::<Result<Vec<_>, _>>()?;
+            Ok(TileEffect::Morph { speed: parse_duration(speed)?, duration, palette: Palette::new(&colors) })
+        }
+        ["sky", speed, sky] => {
+            let sky = match sky.split(':').collect::<Vec<_>>()[..] {
+                ["sunrise"] => SkyType::Sunrise,
+                ["sunset"] => SkyType::Sunset,
+                ["clouds", min, max] => SkyType::Clouds {
+                    cloud_saturation_min: min.parse().map_err(|_| format!("Bad saturation {:?}", min))?,
+                    cloud_saturation_max: max.parse().map_err(|_| format!("Bad saturation {:?}", max))?,
+                },
+                _ => return Err(format!("Unknown sky {:?}", sky)),
+            };
+            Ok(TileEffect::Sky { speed: parse_duration(speed)?, duration, sky })
+        }
+        ["unknown", effect_type] => Ok(TileEffect::Unknown(effect_type.parse().map_err(|_| format!("Bad effect type {:?}", effect_type))?)),
+        _ => Err(format!("Unknown tile effect {:?}", words.join(" "))),
+    }
+}
+
+fn parse_color(text: &str) -> Result<Hsbk, String> {
+    let parts = text.split(':').map(|part| part.parse::<u16>()).collect::<Result<Vec<_>, _>>();
+
+    match parts.as_deref() {
+        Ok(&[hue, saturation, brightness, kelvin]) => Ok(Hsbk::new(hue, saturation, brightness, kelvin)),
+        _ => Err(format!("{:?} is not hue:saturation:brightness:kelvin", text)),
+    }
+}
+
+fn parse_serial(text: &str) -> Result<[u8; 8], String> {
+    let error = || format!("{:?} is not a serial number like d073d5000001", text);
+    if text.len() != 12 || !text.is_ascii() {
+        return Err(error());
+    }
+
+    let mut target = [0; 8];
+    for (byte, index) in target.iter_mut().zip((0..12).step_by(2)) {
+        *byte = u8::from_str_radix(&text[index..index + 2], 16).map_err(|_| error())?;
+    }
+
+    Ok(target)
+}
+
+fn serial(target: &[u8; 8]) -> String {
+    target[..6].iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn parse_utc_offset(text: &str) -> Result<i32, String> {
+    let error = || format!("{:?} is not an offset like +01:00", text);
+    let (sign, rest) = match text.as_bytes().first() {
+        Some(b'+') => (1, &text[1..]),
+        Some(b'-') => (-1, &text[1..]),
+        _ => return Err(error()),
+    };
+
+    let (hours, minutes) = rest.split_once(':').ok_or_else(error)?;
+    let hours: i32 = hours.parse().map_err(|_| error())?;
+    let minutes: i32 = minutes.parse().map_err(|_| error())?;
+
+    Ok(sign * (hours * 60 + minutes))
+}
+
+/// Parses a duration written like `500ms`, `90s`, `5m` or `1h30m`.
+pub fn parse_duration(text: &str) -> Result<Duration, String> {
+    let error = || format!("{:?} is not a duration like 500ms, 90s or 1h30m", text);
+    let mut total = Duration::ZERO;
+    let mut rest = text;
+
+    if rest.is_empty() {
+        return Err(error());
+    }
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+        let value: u64 = rest[..digits].parse().map_err(|_| error())?;
+        rest = &rest[digits..];
+
+        let unit_length = rest.find(|c: char| c.is_ascii_digit()).unwrap_or(rest.len());
+        total += match &rest[..unit_length] {
+            "ms" => Duration::from_millis(value),
+            "s" => Duration::from_secs(value),
+            "m" => Duration::from_secs(value * 60),
+            "h" => Duration::from_secs(value * 3600),
+            _ => return Err(error()),
+        };
+        rest = &rest[unit_length..];
+    }
+
+    Ok(total)
+}
+
+struct DurationText(Duration);
+
+impl fmt::Display for DurationText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.0.as_millis() as u64;
+        if millis == 0 {
+            return f.write_str("0s");
+        }
+
+        let parts = [(millis / 3_600_000, "h"), (millis / 60_000 % 60, "m"), (millis / 1000 % 60, "s"), (millis % 1000, "ms")];
+        for (value, unit) in parts.into_iter().filter(|(value, _)| *value > 0) {
+            write!(f, "{}{}", value, unit)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct OffsetText(i32);
+
+impl fmt::Display for OffsetText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.0 < 0 { '-' } else { '+' };
+        write!(f, "{}{:02}:{:02}", sign, self.0.unsigned_abs() / 60, self.0.unsigned_abs() % 60)
+    }
+}
+
+struct ActionText<'a>(&'a Action);
+
+impl fmt::Display for ActionText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let transition = |f: &mut fmt::Formatter<'_>, duration: Duration| match duration.is_zero() {
+            true => Ok(()),
+            false => write!(f, " {}", DurationText(duration)),
+        };
+        let play_for = |f: &mut fmt::Formatter<'_>, duration: Option<Duration>| match duration {
+            Some(duration) => write!(f, " for {}", DurationText(duration)),
+            None => Ok(()),
+        };
+        let color = |color: &Hsbk| format!("{}:{}:{}:{}", color.hue, color.saturation, color.brightness, color.kelvin);
+
+        match self.0 {
+            Action::Power { on, duration } => {
+                write!(f, "power {}", if *on { "on" } else { "off" })?;
+                transition(f, *duration)
+            }
+            Action::Color { color: value, duration } => {
+                write!(f, "color {}", color(value))?;
+                transition(f, *duration)
+            }
+            Action::Scene { name, duration } => {
+                write!(f, "scene {}", name)?;
+                transition(f, *duration)
+            }
+            Action::MultiZoneEffect(effect) => match effect {
+                MultiZoneEffect::Off => f.write_str("strip-effect off"),
+                MultiZoneEffect::Move { speed, direction, duration } => {
+                    let direction = if *direction == Direction::Left { "left" } else { "right" };
+                    write!(f, "strip-effect move {} {}", DurationText(*speed), direction)?;
+                    play_for(f, *duration)
+                }
+                MultiZoneEffect::Unknown(effect_type) => write!(f, "strip-effect unknown {}", effect_type),
+            },
+            Action::TileEffect(effect) => match effect {
+                TileEffect::Off => f.write_str("tile-effect off"),
+                TileEffect::Flame { speed, duration } => {
+                    write!(f, "tile-effect flame {}", DurationText(*speed))?;
+                    play_for(f, *duration)
+                }
+                TileEffect::Morph { speed, duration, palette } => {
+                    write!(f, "tile-effect morph {}", DurationText(*speed))?;
+                    for value in palette.colors() {
+                        write!(f, " {}", color(value))?;
+                    }
+                    play_for(f, *duration)
+                }
+                TileEffect::Sky { speed, duration, sky } => {
+                    write!(f, "tile-effect sky {} ", DurationText(*speed))?;
+                    match sky {
+                        SkyType::Sunrise => f.write_str("sunrise")?,
+                        SkyType::Sunset => f.write_str("sunset")?,
+                        SkyType::Clouds { cloud_saturation_min, cloud_saturation_max } => {
+                            write!(f, "clouds:{}:{}", cloud_saturation_min, cloud_saturation_max)?
+                        }
+                    }
+                    play_for(f, *duration)
+                }
+                TileEffect::Unknown(effect_type) => write!(f, "tile-effect unknown {}", effect_type),
+            },
+        }
+    }
+}
+
+/// A job the scheduler ran, or in a dry run would have.
+#[derive(Debug)]
+pub struct Run {
+    pub firing: Firing,
+    /// Whether the firing was missed while the scheduler wasn't running and is being caught up on
+    pub late: bool,
+    /// Devices that didn't take the action; the others did
+    pub failed: Vec<(Device, ClientError)>,
+}
+
+/// What one [`Scheduler::tick`] did.
+#[derive(Debug, Default)]
+pub struct Tick {
+    pub ran: Vec<Run>,
+    /// Firings missed while the scheduler wasn't running, for jobs without `catch-up`
+    pub missed: Vec<Firing>,
+    /// Scenes named by jobs that ran but never added with [`Scheduler::add_scene`]
+    pub unknown_scenes: Vec<String>,
+}
+
+/// Runs the jobs of a [`Schedule`] when they come due.
+///
+/// The scheduler remembers the time it last checked the schedule, in a state file if it has one, so after
+/// downtime it can tell which jobs it missed. A missed job with `catch-up` runs once, however many times it was
+/// missed; the rest are reported and skipped.
+#[derive(Debug)]
+pub struct Scheduler {
+    schedule: Schedule,
+    scenes: HashMap<String, Scene>,
+    checked_until: Option<SystemTime>,
+    state_file: Option<PathBuf>,
+    dry_run: bool,
+    grace: Duration,
+}
+
+impl Scheduler {
+    pub fn new(schedule: Schedule) -> Self {
+        Scheduler {
+            schedule,
+            scenes: HashMap::new(),
+            checked_until: None,
+            state_file: None,
+            dry_run: false,
+            grace: Duration::from_secs(120),
+        }
+    }
+
+    pub fn schedule(&self) -> &Schedule {
+        &self.schedule
+    }
+
+    /// Makes a scene available to jobs with `scene name`.
+    pub fn add_scene(&mut self, name: &str, scene: Scene) -> &mut Self {
+        self.scenes.insert(name.to_string(), scene);
+        self
+    }
+
+    /// Keeps the time the schedule was last checked in `path`, reading it now if the file exists.
+    pub fn set_state_file(&mut self, path: impl Into<PathBuf>) -> Result<&mut Self, ScheduleError> {
+        let path = path.into();
+
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let seconds = text.trim().parse().map_err(|_| ScheduleError::Parse { line: 1, message: format!("Bad state file {:?}", text.trim()) })?;
+                self.checked_until = Some(from_unix_seconds(seconds));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        self.state_file = Some(path);
+        Ok(self)
+    }
+
+    /// In a dry run jobs are reported by [`Scheduler::tick`] as they come due, but nothing is sent and the state
+    /// file isn't written.
+    pub fn set_dry_run(&mut self, dry_run: bool) -> &mut Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// How late a job can run and still count as on time rather than missed. Defaults to two minutes.
+    pub fn set_grace(&mut self, grace: Duration) -> &mut Self {
+        self.grace = grace;
+        self
+    }
+
+    /// When the schedule was last checked, or `None` before the first tick of a scheduler without a state file.
+    pub fn checked_until(&self) -> Option<SystemTime> {
+        self.checked_until
+    }
+
+    /// Runs the jobs due since the last tick, up to `now`, on the given devices. The first tick without a
+    /// previous check time runs nothing and just starts the clock.
+    pub fn tick(&mut self, client: &mut LifxClient, devices: &[Device], now: SystemTime) -> Result<Tick, ScheduleError> {
+        let mut tick = Tick::default();
+        let since = self.checked_until.unwrap_or(now);
+
+        for job in &self.schedule.jobs {
+            let Some(at) = self.schedule.last_firing(job, since, now) else {
+                continue;
+            };
+
+            let firing = Firing { at, job: job.clone(), utc_offset: self.schedule.utc_offset };
+            let late = now.duration_since(at).unwrap_or_default() > self.grace;
+            if late && !job.catch_up {
+                tick.missed.push(firing);
+                continue;
+            }
+
+            let mut failed = Vec::new();
+            if !self.dry_run {
+                let targets = job.targets.select(devices);
+                match &job.action {
+                    Action::Scene { name, duration } => match self.scenes.get(name) {
+                        Some(scene) => failed = scene.restore(client, &targets, *duration).failed,
+                        None => tick.unknown_scenes.push(name.clone()),
+                    },
+                    action => {
+                        for device in targets {
+                            if let Err(err) = perform(client, &device, action) {
+                                failed.push((device, err));
+                            }
+                        }
+                    }
+                }
+            }
+
+            tick.ran.push(Run { firing, late, failed });
+        }
+
+        tick.ran.sort_by_key(|run| run.firing.at);
+        self.checked_until = Some(now);
+        if let (false, Some(path)) = (self.dry_run, &self.state_file) {
+            fs::write(path, format!("{}\n", unix_seconds(now)))?;
+        }
+
+        Ok(tick)
+    }
+
+    /// Ticks until `limit` has passed, sleeping until the next job is due, and hands each tick to `report`.
+    pub fn run(&mut self, client: &mut LifxClient, devices: &[Device], limit: Duration, mut report: impl FnMut(&Tick)) -> Result<(), ScheduleError> {
+        let end = SystemTime::now() + limit;
+
+        loop {
+            let now = SystemTime::now();
+            report(&self.tick(client, devices, now)?);
+            if now >= end {
+                return Ok(());
+            }
+
+            // Wake for the next firing, and at least once a minute in case the clock is changed
+            let next = self.schedule.jobs.iter().filter_map(|job| self.schedule.next_firing(job, now)).min().unwrap_or(end);
+            let wake = next.min(end).min(now + Duration::from_secs(60));
+            thread::sleep(wake.duration_since(SystemTime::now()).unwrap_or_default());
+        }
+    }
+}
+
+fn perform(client: &mut LifxClient, device: &Device, action: &Action) -> Result<(), ClientError> {
+    match action {
+        Action::Power { on, duration } => {
+            let level = if *on { u16::MAX } else { 0 };
+            client.send_acked(device, &DeviceRequest::SetLightPower { level, duration_ms: milliseconds(*duration) })
+        }
+        Action::Color { color, duration } => {
+            let message = DeviceRequest::SetColor {
+                reserved_6: 0,
+                hue: color.hue,
+                saturation: color.saturation,
+                brightness: color.brightness,
+                kelvin: color.kelvin,
+                duration_ms: milliseconds(*duration),
+            };
+            client.send_acked(device, &message)
+        }
+        Action::MultiZoneEffect(effect) => client.set_multizone_effect(device, effect),
+        Action::TileEffect(effect) => client.set_tile_effect(device, effect),
+        // Scenes restore every device together and are handled by the scheduler
+        Action::Scene { .. } => Ok(()),
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    io,
+    net::{SocketAddr, UdpSocket},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    client::{Device, MAX_PACKET_SIZE},
+    deserialize_lifx_packet_as,
+    emulator::{reply_packet, EmulatorConfig, EmulatorState, VirtualDevice},
+    rng::Rng,
+    DeviceRequest,
+};
+
+/// How much later than its neighbours a reordered reply is sent.
+const REORDER_DELAY: Duration = Duration::from_millis(30);
+
+/// The kinds of device a simulated network is furnished with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProductKind {
+    Bulb,
+    Strip,
+    Tile,
+    Switch,
+}
+
+impl ProductKind {
+    /// Vendor and product ids of a representative LIFX product of this kind.
+    pub fn ids(self) -> (u32, u32) {
+        match self {
+            // LIFX A19
+            ProductKind::Bulb => (1, 27),
+            // LIFX Z
+            ProductKind::Strip => (1, 32),
+            // LIFX Tile
+            ProductKind::Tile => (1, 55),
+            // LIFX Switch
+            ProductKind::Switch => (1, 70),
+        }
+    }
+
+    /// Configuration for the `index`th device of a network, with a serial number and label derived from the index.
+    pub fn config(self, index: usize) -> EmulatorConfig {
+        let (vendor, product) = self.ids();
+
+        EmulatorConfig {
+            target: [0xD0, 0x73, 0xD5, 0x10, (index >> 8) as u8, index as u8, 0, 0],
+            label: format!("{:?} {}", self, index + 1),
+            vendor,
+            product,
+            light: self != ProductKind::Switch,
+            zones: if self == ProductKind::Strip { 16 } else { 0 },
+            tiles: if self == ProductKind::Tile { 5 } else { 0 },
+            ..Default::default()
+        }
+    }
+}
+
+/// Network faults applied to simulated traffic. The default is a perfect network.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Faults {
+    /// Chance from 0.0 to 1.0 that a packet is lost, applied to requests and replies separately
+    pub loss: f32,
+    /// Delay before every reply
+    pub latency: Duration,
+    /// Extra delay of up to this much, chosen at random for each reply
+    pub jitter: Duration,
+    /// Chance that a reply is sent twice
+    pub duplicate: f32,
+    /// Chance that a reply is held back until after the ones following it
+    pub reorder: f32,
+}
+
+struct Node {
+    device: VirtualDevice,
+    socket: UdpSocket,
+    port: u16,
+    online: bool,
+    arrivals: Vec<Instant>,
+}
+
+/// A reply waiting for its delay to pass, ordered by when it is due and then by when it was queued.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct Pending {
+    due: Instant,
+    order: u64,
+    node: usize,
+    to: SocketAddr,
+    packet: Vec<u8>,
+}
+
+struct Network {
+    nodes: Vec<Node>,
+    faults: Faults,
+    rng: Rng,
+    outbox: BinaryHeap<Reverse<Pending>>,
+    queued: u64,
+    listeners: Vec<SocketAddr>,
+}
+
+impl Network {
+    /// Hands a packet to one node, or to every node if it arrived on the broadcast socket.
+    fn deliver(&mut self, node: Option<usize>, packet: &[u8], from: SocketAddr, now: Instant) {
+        let (header, request) = match deserialize_lifx_packet_as::<DeviceRequest>(packet) {
+            Ok(packet) => packet,
+            Err(err) => {
+                log::debug!("Simulator dropping packet from {}: {}", from, err);
+                return;
+            }
+        };
+
+        let recipients = match node {
+            Some(index) => index..index + 1,
+            None => 0..self.nodes.len(),
+        };
+
+        for index in recipients {
+            if !self.nodes[index].online || self.rng.chance(self.faults.loss) || !self.nodes[index].device.is_addressed_by(&header) {
+                continue;
+            }
+
+            let node = &mut self.nodes[index];
+            node.arrivals.push(now);
+
+            let target = node.device.config.target;
+            let replies = node.device.handle(&header, request.clone(), node.port, now);
+
+            for reply in replies {
+                if self.rng.chance(self.faults.loss) {
+                    continue;
+                }
+
+                let mut delay = self.faults.latency + self.rng.up_to(self.faults.jitter);
+                if self.rng.chance(self.faults.reorder) {
+                    delay += REORDER_DELAY;
+                }
+
+                let packet = reply_packet(target, &header, &reply);
+                let copies = if self.rng.chance(self.faults.duplicate) { 2 } else { 1 };
+                for _ in 0..copies {
+                    self.queue(Pending { due: now + delay, order: 0, node: index, to: from, packet: packet.clone() });
+                }
+
+                // Replies to source 0 are broadcast on the LAN, so everyone listening hears them
+                if header.source == 0 {
+                    for to in self.listeners.clone() {
+                        self.queue(Pending { due: now + delay, order: 0, node: index, to, packet: packet.clone() });
+                    }
+                }
+            }
+        }
+    }
+
+    fn queue(&mut self, mut pending: Pending) {
+        self.queued += 1;
+        pending.order = self.queued;
+        self.outbox.push(Reverse(pending));
+    }
+
+    fn flush(&mut self, now: Instant) {
+        while self.outbox.peek().is_some_and(|Reverse(pending)| pending.due <= now) {
+            let Reverse(pending) = self.outbox.pop().unwrap();
+
+            if let Err(err) = self.nodes[pending.node].socket.send_to(&pending.packet, pending.to) {
+                log::warn!("Simulator failed to reply to {}: {}", pending.to, err);
+            }
+        }
+    }
+}
+
+/// A network of emulated devices on localhost with injectable faults, for testing discovery, retries and rate
+/// limiting without real hardware.
+///
+/// Every device listens on its own port, and packets sent to [`Simulator::broadcast_address`] reach all of them
+/// the way a LAN broadcast would. Faults are drawn from a generator seeded at start, so a given seed and sequence
+/// of requests always loses, delays and duplicates the same packets.
+pub struct Simulator {
+    broadcast: SocketAddr,
+    devices: Vec<Device>,
+    network: Arc<Mutex<Network>>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Simulator {
+    pub fn start(configs: Vec<EmulatorConfig>, faults: Faults, seed: u64) -> io::Result<Self> {
+        let hub = UdpSocket::bind("127.0.0.1:0")?;
+        hub.set_nonblocking(true)?;
+        let broadcast = hub.local_addr()?;
+
+        let mut nodes = Vec::with_capacity(configs.len());
+        let mut devices = Vec::with_capacity(configs.len());
+        for config in configs {
+            let socket = UdpSocket::bind("127.0.0.1:0")?;
+            socket.set_nonblocking(true)?;
+            let address = socket.local_addr()?;
+
+            devices.push(Device { target: config.target, address });
+            nodes.push(Node { device: VirtualDevice::new(config), socket, port: address.port(), online: true, arrivals: Vec::new() });
+        }
+
+        let network = Arc::new(Mutex::new(Network { nodes, faults, rng: Rng::new(seed), outbox: BinaryHeap::new(), queued: 0, listeners: Vec::new() }));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread = {
+            let network = network.clone();
+            let running = running.clone();
+
+            thread::spawn(move || serve(hub, network, running))
+        };
+
+        Ok(Simulator { broadcast, devices, network, running, thread: Some(thread) })
+    }
+
+    /// A house of `count` devices: mostly bulbs, with strips, tiles and switches mixed in.
+    pub fn house(count: usize, faults: Faults, seed: u64) -> io::Result<Self> {
+        let configs = (0..count)
+            .map(|index| {
+                let kind = match index % 10 {
+                    0..=4 => ProductKind::Bulb,
+                    5 | 6 => ProductKind::Strip,
+                    7 => ProductKind::Tile,
+                    _ => ProductKind::Switch,
+                };
+
+                kind.config(index)
+            })
+            .collect();
+
+        Self::start(configs, faults, seed)
+    }
+
+    /// Where to send packets meant for every device.
+    pub fn broadcast_address(&self) -> SocketAddr {
+        self.broadcast
+    }
+
+    pub fn devices(&self) -> &[Device] {
+        &self.devices
+    }
+
+    pub fn set_faults(&self, faults: Faults) {
+        self.network.lock().unwrap().faults = faults;
+    }
+
+    /// Sends `address` a copy of every reply to a request with source 0, as real devices broadcast those replies
+    /// to port 56700 for every app on the network to hear.
+    pub fn add_listener(&self, address: SocketAddr) {
+        self.network.lock().unwrap().listeners.push(address);
+    }
+
+    /// Takes a device off the network, or brings it back. Offline devices ignore every packet.
+    pub fn set_online(&self, index: usize, online: bool) {
+        self.network.lock().unwrap().nodes[index].online = online;
+    }
+
+    pub fn state(&self, index: usize) -> EmulatorState {
+        self.network.lock().unwrap().nodes[index].device.state(Instant::now())
+    }
+
+    /// When each packet the device accepted arrived, oldest first.
+    pub fn arrivals(&self, index: usize) -> Vec<Instant> {
+        self.network.lock().unwrap().nodes[index].arrivals.clone()
+    }
+
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn receive(socket: &UdpSocket, buffer: &mut [u8]) -> Option<(usize, SocketAddr)> {
+    match socket.recv_from(buffer) {
+        Ok(received) => Some(received),
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => None,
+        Err(err) => {
+            log::warn!("Simulator receive failed: {}", err);
+            None
+        }
+    }
+}
+
+fn serve(hub: UdpSocket, network: Arc<Mutex<Network>>, running: Arc<AtomicBool>) {
+    let mut buffer = [0u8; MAX_PACKET_SIZE];
+
+    while running.load(Ordering::Relaxed) {
+        {
+            let mut network = network.lock().unwrap();
+            let now = Instant::now();
+
+            while let Some((size, from)) = receive(&hub, &mut buffer) {
+                network.deliver(None, &buffer[..size], from, now);
+            }
+
+            for index in 0..network.nodes.len() {
+                while let Some((size, from)) = receive(&network.nodes[index].socket, &mut buffer) {
+                    network.deliver(Some(index), &buffer[..size], from, now);
+                }
+            }
+
+            network.flush(Instant::now());
+        }
+
+        thread::sleep(Duration::from_millis(1));
+    }
+}
@@ -0,0 +1,362 @@
+// serde for `Message`: the derived, externally tagged form with the packet number added to each variant's fields,
+// as in `{"SetPower": {"packet_number": 21, "level": 65535}}`. The packet number is checked against the generated
+// schema when read back, and may be left out of hand-written JSON. The Unknown variant carries its own.
+//
+// Compact formats such as bincode lay fields out by position and name variants by index, so they get the derived
+// form unchanged; the variant index already says which packet it is.
+use core::fmt;
+
+use lifx_serialization::{LifxPayload, PacketSchema};
+use serde::{
+    de::{self, value::BorrowedStrDeserializer, DeserializeSeed, EnumAccess, IgnoredAny, MapAccess, SeqAccess, VariantAccess, Visitor},
+    forward_to_deserialize_any,
+    ser::SerializeStructVariant,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::messages::Message;
+
+const PACKET_NUMBER: &str = "packet_number";
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return Message::serialize(self, serializer);
+        }
+
+        let packet_number = match self {
+            Message::Unknown { .. } => None,
+            message => Some(message.packet_number()),
+        };
+
+        Message::serialize(self, Tagged { inner: serializer, packet_number })
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            Message::deserialize(Untagged(deserializer))
+        } else {
+            Message::deserialize(deserializer)
+        }
+    }
+}
+
+// Passes everything on to the inner serializer, adding the packet number as the first field of a variant
+struct Tagged<S> {
+    inner: S,
+    packet_number: Option<u16>,
+}
+
+macro_rules! forward_serialize {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ok:ty;)*) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<$ok, S::Error> {
+                self.inner.$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<S: Serializer> Serializer for Tagged<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = S::SerializeSeq;
+    type SerializeTuple = S::SerializeTuple;
+    type SerializeTupleStruct = S::SerializeTupleStruct;
+    type SerializeTupleVariant = S::SerializeTupleVariant;
+    type SerializeMap = S::SerializeMap;
+    type SerializeStruct = S::SerializeStruct;
+    type SerializeStructVariant = S::SerializeStructVariant;
+
+    forward_serialize! {
+        serialize_bool(v: bool) -> S::Ok;
+        serialize_i8(v: i8) -> S::Ok;
+        serialize_i16(v: i16) -> S::Ok;
+        serialize_i32(v: i32) -> S::Ok;
+        serialize_i64(v: i64) -> S::Ok;
+        serialize_u8(v: u8) -> S::Ok;
+        serialize_u16(v: u16) -> S::Ok;
+        serialize_u32(v: u32) -> S::Ok;
+        serialize_u64(v: u64) -> S::Ok;
+        serialize_f32(v: f32) -> S::Ok;
+        serialize_f64(v: f64) -> S::Ok;
+        serialize_char(v: char) -> S::Ok;
+        serialize_str(v: &str) -> S::Ok;
+        serialize_bytes(v: &[u8]) -> S::Ok;
+        serialize_none() -> S::Ok;
+        serialize_unit() -> S::Ok;
+        serialize_unit_struct(name: &'static str) -> S::Ok;
+        serialize_seq(len: Option<usize>) -> S::SerializeSeq;
+        serialize_tuple(len: usize) -> S::SerializeTuple;
+        serialize_tuple_struct(name: &'static str, len: usize) -> S::SerializeTupleStruct;
+        serialize_tuple_variant(name: &'static str, index: u32, variant: &'static str, len: usize) -> S::SerializeTupleVariant;
+        serialize_map(len: Option<usize>) -> S::SerializeMap;
+        serialize_struct(name: &'static str, len: usize) -> S::SerializeStruct;
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(value)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_struct(name, value)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, name: &'static str, index: u32, variant: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_newtype_variant(name, index, variant, value)
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, index: u32, variant: &'static str) -> Result<S::Ok, S::Error> {
+        match self.packet_number {
+            Some(packet_number) => {
+                let mut fields = self.inner.serialize_struct_variant(name, index, variant, 1)?;
+                fields.serialize_field(PACKET_NUMBER, &packet_number)?;
+                fields.end()
+            }
+            None => self.inner.serialize_unit_variant(name, index, variant),
+        }
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, index: u32, variant: &'static str, len: usize) -> Result<S::SerializeStructVariant, S::Error> {
+        match self.packet_number {
+            Some(packet_number) => {
+                let mut fields = self.inner.serialize_struct_variant(name, index, variant, len + 1)?;
+                fields.serialize_field(PACKET_NUMBER, &packet_number)?;
+                Ok(fields)
+            }
+            None => self.inner.serialize_struct_variant(name, index, variant, len),
+        }
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+// Passes everything on to the inner deserializer, taking the packet number out of a variant's fields
+struct Untagged<D>(D);
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for Untagged<D> {
+    type Error = D::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        self.0.deserialize_any(visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, D::Error> {
+        self.0.deserialize_enum(name, variants, UntaggedVisitor(visitor))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.0.is_human_readable()
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        newtype_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct UntaggedVisitor<V>(V);
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for UntaggedVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.0.expecting(formatter)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        self.0.visit_enum(UntaggedEnum(data))
+    }
+}
+
+// The variant named in the data; `None` for Unknown, which has no schema
+struct VariantSeed;
+
+impl<'de> DeserializeSeed<'de> for VariantSeed {
+    type Value = Option<&'static PacketSchema>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for VariantSeed {
+    type Value = Option<&'static PacketSchema>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a message name")
+    }
+
+    // The schema lists the variants in order, leaving out Unknown, which comes last
+    fn visit_u64<E: de::Error>(self, index: u64) -> Result<Self::Value, E> {
+        let schema = Message::schema();
+        match usize::try_from(index) {
+            Ok(index) if index < schema.len() => Ok(Some(&schema[index])),
+            Ok(index) if index == schema.len() => Ok(None),
+            _ => Err(E::custom(format_args!("unknown message index {}", index))),
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+        match Message::schema().iter().find(|schema| schema.name == name) {
+            Some(schema) => Ok(Some(schema)),
+            None if name == "Unknown" => Ok(None),
+            None => Err(E::custom(format_args!("unknown message `{}`", name))),
+        }
+    }
+}
+
+struct UntaggedEnum<A>(A);
+
+impl<'de, A: EnumAccess<'de>> EnumAccess<'de> for UntaggedEnum<A> {
+    type Error = A::Error;
+    type Variant = UntaggedVariant<A::Variant>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<(T::Value, Self::Variant), A::Error> {
+        let (schema, variant) = self.0.variant_seed(VariantSeed)?;
+        let name = schema.map_or("Unknown", |schema| schema.name);
+        let value = seed.deserialize(BorrowedStrDeserializer::new(name))?;
+
+        Ok((value, UntaggedVariant { inner: variant, schema }))
+    }
+}
+
+struct UntaggedVariant<A> {
+    inner: A,
+    schema: Option<&'static PacketSchema>,
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for UntaggedVariant<A> {
+    type Error = A::Error;
+
+    // A message without fields is written with just its packet number, but plain `null` is read too
+    fn unit_variant(self) -> Result<(), A::Error> {
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Fields {
+            packet_number: Option<u16>,
+        }
+
+        let Some(schema) = self.schema else {
+            return self.inner.unit_variant();
+        };
+
+        match self.inner.newtype_variant::<Option<Fields>>()? {
+            Some(Fields { packet_number: Some(packet_number) }) => check(schema, packet_number),
+            _ => Ok(()),
+        }
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, A::Error> {
+        match self.schema {
+            Some(schema) => self.inner.struct_variant(fields, FieldsVisitor { inner: visitor, schema, fields }),
+            None => self.inner.struct_variant(fields, visitor),
+        }
+    }
+}
+
+fn check<E: de::Error>(schema: &PacketSchema, packet_number: u16) -> Result<(), E> {
+    if packet_number == schema.packet_number {
+        Ok(())
+    } else {
+        Err(E::custom(format_args!("{} is packet number {}, not {}", schema.name, schema.packet_number, packet_number)))
+    }
+}
+
+struct FieldsVisitor<V> {
+    inner: V,
+    schema: &'static PacketSchema,
+    fields: &'static [&'static str],
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for FieldsVisitor<V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_map(Fields { inner: map, schema: self.schema, fields: self.fields })
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        self.inner.visit_seq(seq)
+    }
+}
+
+// A field of a message: one the derived code knows, the packet number, or one it would skip anyway
+enum Field {
+    Known(&'static str),
+    PacketNumber,
+    Other,
+}
+
+struct FieldSeed(&'static [&'static str]);
+
+impl<'de> DeserializeSeed<'de> for FieldSeed {
+    type Value = Field;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Field, D::Error> {
+        deserializer.deserialize_identifier(self)
+    }
+}
+
+impl<'de> Visitor<'de> for FieldSeed {
+    type Value = Field;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a field name")
+    }
+
+    fn visit_str<E: de::Error>(self, name: &str) -> Result<Field, E> {
+        Ok(match self.0.iter().find(|field| **field == name) {
+            Some(field) => Field::Known(field),
+            None if name == PACKET_NUMBER => Field::PacketNumber,
+            None => Field::Other,
+        })
+    }
+}
+
+struct Fields<A> {
+    inner: A,
+    schema: &'static PacketSchema,
+    fields: &'static [&'static str],
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for Fields<A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error> {
+        loop {
+            match self.inner.next_key_seed(FieldSeed(self.fields))? {
+                Some(Field::Known(name)) => return seed.deserialize(BorrowedStrDeserializer::new(name)).map(Some),
+                Some(Field::PacketNumber) => check(self.schema, self.inner.next_value()?)?,
+                Some(Field::Other) => {
+                    self.inner.next_value::<IgnoredAny>()?;
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, A::Error> {
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,98 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::{thread, time::Duration};
+
+use lifx_lan::{
+    client::LifxClient,
+    simulator::{Faults, ProductKind, Simulator},
+    DeviceRequest, Hsbk,
+};
+
+fn room(count: usize) -> Simulator {
+    Simulator::start((0..count).map(|index| ProductKind::Bulb.config(index)).collect(), Faults::default(), 1).unwrap()
+}
+
+fn set_color(color: Hsbk) -> DeviceRequest {
+    DeviceRequest::SetColor {
+        reserved_6: 0,
+        hue: color.hue,
+        saturation: color.saturation,
+        brightness: color.brightness,
+        kelvin: color.kelvin,
+        duration_ms: 0,
+    }
+}
+
+fn client(simulator: &Simulator) -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(200)).set_retries(1).set_rate_limit(None);
+
+    // Look every bulb up ahead of time, as a long running controller would have
+    for device in simulator.devices() {
+        client.product(device).unwrap();
+    }
+
+    client
+}
+
+// How far apart the first and last packet of the burst reached their bulbs
+fn arrival_spread(simulator: &Simulator) -> Duration {
+    let last_arrivals: Vec<_> = (0..simulator.devices().len()).map(|index| *simulator.arrivals(index).last().unwrap()).collect();
+    *last_arrivals.iter().max().unwrap() - *last_arrivals.iter().min().unwrap()
+}
+
+#[test]
+fn a_scene_lands_on_every_bulb_at_once() {
+    let simulator = room(30);
+    let mut client = client(&simulator);
+
+    let scene: Vec<_> = simulator
+        .devices()
+        .iter()
+        .enumerate()
+        .map(|(index, device)| (*device, set_color(Hsbk::new(index as u16 * 2000, 65535, 65535, 3500))))
+        .collect();
+
+    let burst = client.send_burst(&scene, None).unwrap();
+    assert_eq!(burst.packets, 30);
+    assert!(burst.skew < Duration::from_millis(20), "{:?}", burst.skew);
+
+    thread::sleep(Duration::from_millis(50));
+    for index in 0..30 {
+        assert_eq!(simulator.state(index).color, Hsbk::new(index as u16 * 2000, 65535, 65535, 3500));
+    }
+    assert!(arrival_spread(&simulator) < Duration::from_millis(20), "{:?}", arrival_spread(&simulator));
+}
+
+#[test]
+fn identical_messages_can_go_as_one_broadcast() {
+    let simulator = room(30);
+    let mut client = client(&simulator);
+    let red = set_color(Hsbk::new(0, 65535, 65535, 3500));
+
+    let messages: Vec<_> = simulator.devices().iter().map(|device| (*device, red.clone())).collect();
+    let burst = client.send_burst(&messages, Some(simulator.broadcast_address())).unwrap();
+    assert_eq!(burst.packets, 1);
+
+    thread::sleep(Duration::from_millis(50));
+    for index in 0..30 {
+        assert_eq!(simulator.state(index).color, Hsbk::new(0, 65535, 65535, 3500));
+    }
+}
+
+#[test]
+fn different_messages_are_never_broadcast() {
+    let simulator = room(2);
+    let mut client = client(&simulator);
+    let devices = simulator.devices();
+
+    let messages = [
+        (devices[0], set_color(Hsbk::new(0, 65535, 65535, 3500))),
+        (devices[1], set_color(Hsbk::new(21845, 65535, 65535, 3500))),
+    ];
+    let burst = client.send_burst(&messages, Some(simulator.broadcast_address())).unwrap();
+    assert_eq!(burst.packets, 2);
+
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(simulator.state(1).color.hue, 21845);
+}
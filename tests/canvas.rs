@@ -0,0 +1,223 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::time::Duration;
+
+use lifx_lan::{
+    canvas::{Canvas, Orientation, TileChain, TileLayout, ZoneStrip},
+    client::{Device, LifxClient},
+    messages::{responses, TileDevice, MULTIZONE_APPLY, MULTIZONE_NO_APPLY},
+    simulator::{Faults, ProductKind, Simulator},
+    DeviceRequest, Hsbk,
+};
+
+fn tile(user_x: f32, user_y: f32, accel: (i16, i16, i16)) -> TileDevice {
+    TileDevice {
+        accel_meas_x: accel.0,
+        accel_meas_y: accel.1,
+        accel_meas_z: accel.2,
+        user_x,
+        user_y,
+        width: 8,
+        height: 8,
+        ..TileDevice::default()
+    }
+}
+
+// A distinct colour for every canvas pixel
+fn gradient(canvas: &mut Canvas) {
+    for y in 0..canvas.height() {
+        for x in 0..canvas.width() {
+            canvas.set(x, y, Hsbk::new((x * 1000) as u16, 65535, (y * 8000 + 1) as u16, 3500));
+        }
+    }
+}
+
+#[test]
+fn orientation_follows_gravity() {
+    assert_eq!(Orientation::from_accelerometer(0, -100, 2), Orientation::Upright);
+    assert_eq!(Orientation::from_accelerometer(0, 100, 2), Orientation::UpsideDown);
+    assert_eq!(Orientation::from_accelerometer(100, 3, 2), Orientation::RotatedRight);
+    assert_eq!(Orientation::from_accelerometer(-100, 3, 2), Orientation::RotatedLeft);
+    assert_eq!(Orientation::from_accelerometer(1, 3, -100), Orientation::FaceUp);
+    assert_eq!(Orientation::from_accelerometer(-1, -1, -1), Orientation::Upright);
+}
+
+#[test]
+fn lays_out_tiles_from_user_coordinates() {
+    // Two tiles side by side with a third above the right one, half a tile along
+    let layout = TileLayout::from_chain(&[tile(0.0, 0.0, (0, -100, 0)), tile(1.0, 0.0, (0, -100, 0)), tile(1.5, 1.0, (0, -100, 0))]);
+
+    assert_eq!((layout.width(), layout.height()), (20, 16));
+    let corners: Vec<_> = layout.tiles().iter().map(|tile| (tile.left, tile.top)).collect();
+    assert_eq!(corners, [(0, 8), (8, 8), (12, 0)]);
+
+    let mut canvas = layout.canvas();
+    gradient(&mut canvas);
+    let pixels = layout.tile_pixels(&canvas, 1);
+    assert_eq!(pixels.len(), 64);
+    assert_eq!(pixels[0], canvas.get(8, 8).unwrap());
+    assert_eq!(pixels[63], canvas.get(15, 15).unwrap());
+}
+
+#[test]
+fn turned_tiles_take_their_pixels_turned() {
+    let layout = TileLayout::from_chain(&[
+        tile(0.0, 0.0, (100, 0, 0)),
+        tile(1.0, 0.0, (-100, 0, 0)),
+        tile(2.0, 0.0, (0, 100, 0)),
+    ]);
+    let mut canvas = layout.canvas();
+    gradient(&mut canvas);
+
+    // A tile turned clockwise has its first pixel at the top right of its footprint
+    let right = layout.tile_pixels(&canvas, 0);
+    assert_eq!(right[0], canvas.get(7, 0).unwrap());
+    assert_eq!(right[7], canvas.get(7, 7).unwrap());
+
+    let left = layout.tile_pixels(&canvas, 1);
+    assert_eq!(left[0], canvas.get(8, 7).unwrap());
+    assert_eq!(left[7], canvas.get(8, 0).unwrap());
+
+    let upside_down = layout.tile_pixels(&canvas, 2);
+    assert_eq!(upside_down[0], canvas.get(23, 7).unwrap());
+    assert_eq!(upside_down[63], canvas.get(16, 0).unwrap());
+}
+
+#[test]
+fn skips_chain_entries_without_pixels() {
+    // The middle entry reports no width, far off to the left, and must neither stretch the canvas nor be drawn
+    let empty = TileDevice { width: 0, ..tile(-5.0, 0.0, (0, -100, 0)) };
+    let layout = TileLayout::from_chain(&[tile(0.0, 0.0, (0, -100, 0)), empty, tile(1.0, 0.0, (0, -100, 0))]);
+    assert_eq!(layout.tiles().len(), 3);
+    assert_eq!((layout.width(), layout.height()), (16, 8));
+    assert_eq!((layout.tiles()[2].left, layout.tiles()[2].top), (8, 0));
+
+    let device = Device { target: [0xD0, 0x73, 0xD5, 0x10, 0, 0, 0, 0], address: "127.0.0.1:56700".parse().unwrap() };
+    let tiles = TileChain::new(device, layout);
+    let messages = tiles.messages(&tiles.canvas(), Duration::ZERO);
+    let drawn: Vec<u8> = messages
+        .iter()
+        .filter_map(|message| match message {
+            DeviceRequest::Set64 { tile_index, .. } => Some(*tile_index),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(drawn, [0, 2]);
+    assert!(matches!(messages.last(), Some(DeviceRequest::CopyFrameBuffer { length: 3, .. })));
+}
+
+#[test]
+fn draws_off_screen_then_flips_every_tile_at_once() {
+    let chain = Simulator::start(vec![ProductKind::Tile.config(0)], Faults::default(), 1).unwrap();
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+
+    let tiles = TileChain::query(&mut client, &chain.devices()[0]).unwrap();
+    assert_eq!(tiles.layout().tiles().len(), 5);
+    assert_eq!((tiles.layout().width(), tiles.layout().height()), (40, 8));
+
+    let mut canvas = tiles.canvas();
+    gradient(&mut canvas);
+
+    let messages = tiles.messages(&canvas, Duration::from_millis(100));
+    assert_eq!(messages.len(), 6);
+    assert!(messages[..5].iter().all(|message| matches!(message, DeviceRequest::Set64 { fb_index: 1, .. })));
+    assert!(matches!(
+        messages[5],
+        DeviceRequest::CopyFrameBuffer { tile_index: 0, length: 5, src_fb_index: 1, dst_fb_index: 0, width: 8, height: 8, duration_ms: 100, .. }
+    ));
+
+    // Nothing shows until the flip
+    let before = chain.state(0).tiles;
+    for message in &messages[..5] {
+        client.send_acked(tiles.device(), message).unwrap();
+    }
+    assert_eq!(chain.state(0).tiles, before);
+
+    tiles.draw(&mut client, &canvas, Duration::ZERO).unwrap();
+    // The frame goes out unacknowledged, so wait for a reply to something sent after it
+    client.request::<responses::StateDeviceChain>(tiles.device(), &DeviceRequest::GetDeviceChain).unwrap();
+    for (index, shown) in chain.state(0).tiles.iter().enumerate() {
+        assert_eq!(shown.as_slice(), tiles.layout().tile_pixels(&canvas, index));
+    }
+}
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+    client
+}
+
+#[test]
+fn strip_sends_only_changed_zones_in_one_extended_message() {
+    let strip = Simulator::start(vec![ProductKind::Strip.config(0)], Faults::default(), 1).unwrap();
+    let mut client = client();
+
+    let mut zones = ZoneStrip::query(&mut client, &strip.devices()[0]).unwrap();
+    assert_eq!(zones.len(), 16);
+    assert!(zones.is_extended());
+    assert!(zones.messages(Duration::ZERO).is_empty());
+
+    let red = Hsbk::new(0, 65535, 65535, 3500);
+    zones.set(3, red);
+    zones.set(12, red);
+    zones.set(99, red);
+
+    let messages = zones.messages(Duration::from_millis(250));
+    assert_eq!(messages.len(), 1);
+    assert!(matches!(
+        messages[0],
+        DeviceRequest::SetExtendedColorZones { apply: MULTIZONE_APPLY, zone_index: 0, colors_count: 16, duration_ms: 250, .. }
+    ));
+
+    assert_eq!(zones.commit(&mut client, Duration::ZERO).unwrap(), 1);
+    assert_eq!(zones.shown(), zones.zones());
+    assert_eq!(strip.state(0).zones, zones.zones());
+    assert_eq!(zones.commit(&mut client, Duration::ZERO).unwrap(), 0);
+
+    zones.fill(red);
+    zones.revert();
+    assert!(zones.messages(Duration::ZERO).is_empty());
+}
+
+#[test]
+fn strip_on_old_firmware_sends_ranges_applied_together() {
+    let mut config = ProductKind::Strip.config(0);
+    config.firmware_major = 2;
+    config.firmware_minor = 70;
+    let strip = Simulator::start(vec![config], Faults::default(), 1).unwrap();
+    let mut client = client();
+
+    let mut zones = ZoneStrip::query(&mut client, &strip.devices()[0]).unwrap();
+    assert!(!zones.is_extended());
+
+    let (red, blue) = (Hsbk::new(0, 65535, 65535, 3500), Hsbk::new(43690, 65535, 65535, 3500));
+    zones.fill(red);
+    zones.commit(&mut client, Duration::ZERO).unwrap();
+
+    // Zone 5 is already red, so one range covers 4 to 6
+    zones.set(4, blue);
+    zones.set(6, blue);
+    zones.set(5, blue);
+    zones.set(10, blue);
+    let messages = zones.messages(Duration::ZERO);
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(messages[0], DeviceRequest::SetColorZones { start_index: 4, end_index: 6, apply: MULTIZONE_NO_APPLY, .. }));
+    assert!(matches!(messages[1], DeviceRequest::SetColorZones { start_index: 10, end_index: 10, apply: MULTIZONE_APPLY, .. }));
+
+    zones.commit(&mut client, Duration::ZERO).unwrap();
+    assert_eq!(strip.state(0).zones, zones.zones());
+
+    // A run of one colour reaches over zones that already show it
+    zones.fill(blue);
+    let messages = zones.messages(Duration::ZERO);
+    assert_eq!(messages.len(), 1);
+    assert!(matches!(messages[0], DeviceRequest::SetColorZones { start_index: 0, end_index: 15, apply: MULTIZONE_APPLY, .. }));
+}
+
+#[test]
+fn strip_query_rejects_devices_without_zones() {
+    let bulb = Simulator::start(vec![ProductKind::Bulb.config(0)], Faults::default(), 1).unwrap();
+    let result = ZoneStrip::query(&mut client(), &bulb.devices()[0]);
+    assert!(matches!(result, Err(lifx_lan::client::ClientError::Unsupported(_))));
+}
@@ -0,0 +1,159 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lifx_lan::{
+    circadian::{day_position, CircadianScheduler, Curve, Location, SunTimes},
+    client::LifxClient,
+    emulator::EmulatorConfig,
+    simulator::{Faults, ProductKind, Simulator},
+    DeviceRequest, Hsbk,
+};
+
+const LONDON: Location = Location { latitude: 51.5074, longitude: -0.1278 };
+const TROMSO: Location = Location { latitude: 69.6492, longitude: 18.9553 };
+
+// A UTC date and time, from the days-from-civil algorithm
+fn utc(year: i64, month: i64, day: i64, hour: u64, minute: u64) -> SystemTime {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146097 + day_of_era - 719468) as u64;
+
+    UNIX_EPOCH + Duration::from_secs(days * 86400 + hour * 3600 + minute * 60)
+}
+
+fn assert_near(actual: Option<SystemTime>, expected: SystemTime) {
+    let actual = actual.expect("no sun time");
+    let difference = actual.duration_since(expected).or_else(|_| expected.duration_since(actual)).unwrap();
+    assert!(difference < Duration::from_secs(180), "off by {:?}", difference);
+}
+
+#[test]
+fn works_out_sunrise_and_sunset() {
+    let midsummer = SunTimes::on(LONDON, utc(2024, 6, 21, 9, 0));
+    assert_near(midsummer.sunrise, utc(2024, 6, 21, 3, 43));
+    assert_near(midsummer.sunset, utc(2024, 6, 21, 20, 21));
+    assert_near(Some(midsummer.solar_noon), utc(2024, 6, 21, 12, 2));
+
+    let equinox = SunTimes::on(Location::new(0.0, 0.0), utc(2024, 3, 20, 23, 0));
+    assert_near(equinox.sunrise, utc(2024, 3, 20, 6, 4));
+    assert_near(equinox.sunset, utc(2024, 3, 20, 18, 11));
+
+    let midnight_sun = SunTimes::on(TROMSO, utc(2024, 6, 21, 12, 0));
+    assert!(midnight_sun.sunrise.is_none() && midnight_sun.is_polar_day());
+    let polar_night = SunTimes::on(TROMSO, utc(2024, 12, 21, 12, 0));
+    assert!(polar_night.sunset.is_none() && polar_night.is_polar_night());
+}
+
+#[test]
+fn follows_the_sun_through_the_day() {
+    let noon = day_position(LONDON, utc(2024, 6, 21, 12, 2));
+    assert!(noon > 0.99, "{}", noon);
+    assert!(day_position(LONDON, utc(2024, 6, 21, 3, 43)).abs() < 0.01);
+    assert!(day_position(LONDON, utc(2024, 6, 21, 23, 0)) < 0.0);
+    assert!(day_position(LONDON, utc(2024, 6, 22, 0, 2)) < -0.99);
+    assert!(day_position(TROMSO, utc(2024, 6, 21, 0, 0)) >= 0.0);
+    assert!(day_position(TROMSO, utc(2024, 12, 21, 10, 0)) < 0.0);
+
+    let curve = Curve::default();
+    assert_eq!(curve.color_at(LONDON, utc(2024, 6, 21, 12, 2)).kelvin, 5500);
+    assert_eq!(curve.color_at(LONDON, utc(2024, 6, 21, 12, 2)).brightness, 65535);
+    let morning = curve.color_at(LONDON, utc(2024, 6, 21, 7, 0));
+    assert!(morning.kelvin > 2200 && morning.kelvin < 5500);
+
+    let midnight = curve.color_at(LONDON, utc(2024, 6, 22, 0, 2));
+    assert_eq!(midnight.kelvin, 2200);
+    assert!(midnight.brightness.abs_diff(19661) < 100);
+}
+
+#[test]
+fn applies_the_curve_and_steps_aside_for_manual_changes() {
+    let simulator = Simulator::start(vec![ProductKind::Bulb.config(0), ProductKind::Bulb.config(1)], Faults::default(), 1).unwrap();
+    let devices = simulator.devices();
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+
+    let curve = Curve::default();
+    let mut scheduler = CircadianScheduler::new(LONDON, curve);
+    scheduler.set_transition(Duration::ZERO);
+    devices.iter().for_each(|device| scheduler.add(*device));
+
+    let morning = utc(2024, 6, 21, 7, 0);
+    let applied = scheduler.apply(&mut client, morning);
+    assert_eq!(applied.updated.len(), 2);
+    assert!(applied.failed.is_empty());
+    assert_eq!(simulator.state(0).color, curve.color_at(LONDON, morning));
+
+    // Nothing to send while the lights are already on the curve
+    assert!(scheduler.apply(&mut client, morning).updated.is_empty());
+
+    // Someone picks a colour in the app for the first light
+    let red = Hsbk::new(0, 65535, 65535, 3500);
+    let message = DeviceRequest::SetColor { reserved_6: 0, hue: red.hue, saturation: red.saturation, brightness: red.brightness, kelvin: red.kelvin, duration_ms: 0 };
+    client.send_acked(&devices[0], &message).unwrap();
+
+    let evening = utc(2024, 6, 21, 19, 0);
+    let applied = scheduler.apply(&mut client, evening);
+    assert_eq!(applied.overridden, [devices[0]]);
+    assert_eq!(applied.updated, [devices[1]]);
+    assert!(scheduler.is_overridden(&devices[0]));
+    assert_eq!(simulator.state(0).color, red);
+
+    // Still held on the next update
+    assert_eq!(scheduler.apply(&mut client, evening).overridden, [devices[0]]);
+
+    scheduler.resume(&devices[0]);
+    assert_eq!(scheduler.apply(&mut client, evening).updated, [devices[0]]);
+    assert_eq!(simulator.state(0).color, curve.color_at(LONDON, evening));
+}
+
+#[test]
+fn overrides_expire_after_the_hold() {
+    let simulator = Simulator::start(vec![ProductKind::Bulb.config(0)], Faults::default(), 1).unwrap();
+    let device = simulator.devices()[0];
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+
+    let mut scheduler = CircadianScheduler::new(LONDON, Curve::default());
+    scheduler.set_transition(Duration::ZERO).set_override_hold(Duration::ZERO);
+    scheduler.add(device);
+
+    let noon = utc(2024, 6, 21, 12, 0);
+    scheduler.apply(&mut client, noon);
+    client.send_acked(&device, &DeviceRequest::SetLightPower { level: 0, duration_ms: 0 }).unwrap();
+    let message = DeviceRequest::SetColor { reserved_6: 0, hue: 0, saturation: 0, brightness: 1000, kelvin: 9000, duration_ms: 0 };
+    client.send_acked(&device, &message).unwrap();
+
+    assert_eq!(scheduler.apply(&mut client, noon).overridden, [device]);
+    assert_eq!(scheduler.apply(&mut client, noon).updated, [device]);
+    // Power is left as it is
+    assert_eq!(simulator.state(0).power, 0);
+}
+
+#[test]
+fn keeps_kelvin_within_what_the_light_can_show() {
+    // A LIFX Color 1000, whose whites only go down to 2500K
+    let config = EmulatorConfig { product: 15, ..ProductKind::Bulb.config(0) };
+    let simulator = Simulator::start(vec![config], Faults::default(), 1).unwrap();
+    let device = simulator.devices()[0];
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+
+    let curve = Curve::default();
+    let mut scheduler = CircadianScheduler::new(LONDON, curve);
+    scheduler.set_transition(Duration::ZERO);
+    scheduler.add(device);
+
+    // The curve asks for 2200K at night
+    let midnight = utc(2024, 6, 22, 0, 2);
+    let applied = scheduler.apply(&mut client, midnight);
+    assert!(applied.failed.is_empty(), "{:?}", applied.failed);
+    assert_eq!(applied.updated, [device]);
+    assert_eq!(simulator.state(0).color, Hsbk { kelvin: 2500, ..curve.color_at(LONDON, midnight) });
+
+    // Already as close to the curve as it can get, so nothing more is sent
+    assert!(scheduler.apply(&mut client, midnight).updated.is_empty());
+}
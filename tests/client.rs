@@ -0,0 +1,103 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::{net::UdpSocket, thread, time::Duration};
+
+use lifx_lan::{
+    client::{ClientError, Device, LifxClient},
+    deserialize_lifx_packet_as,
+    messages::responses,
+    serialize_lifx_packet, DeviceRequest, DeviceResponse, LifxRequestOptions, Message,
+};
+use lifx_serialization::LifxPayload;
+
+const TARGET: [u8; 8] = [0xD0, 0x73, 0xD5, 0, 0, 1, 0, 0];
+
+// Answers GetColor with a LightState and anything else with StateUnhandled
+fn spawn_fake_bulb() -> Device {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let address = socket.local_addr().unwrap();
+
+    thread::spawn(move || {
+        let mut buffer = [0u8; 1024];
+        while let Ok((size, from)) = socket.recv_from(&mut buffer) {
+            let (header, message) = deserialize_lifx_packet_as::<DeviceRequest>(&buffer[..size]).unwrap();
+
+            let reply = match message {
+                DeviceRequest::GetColor => DeviceResponse::LightState {
+                    hue: 100,
+                    saturation: 200,
+                    brightness: 300,
+                    kelvin: 3500,
+                    reserved_6: [0; 2],
+                    power: 65535,
+                    label: "Desk".into(),
+                    reserved_7: [0; 8],
+                },
+                other => DeviceResponse::Unhandled { unhandled_type: other.packet_number() },
+            };
+
+            let options = LifxRequestOptions { source: header.source, target: TARGET, sequence: header.sequence, ..Default::default() };
+            let mut packet = [0u8; 1024];
+            serialize_lifx_packet(&options, &reply, &mut packet);
+            socket.send_to(&packet[..36 + reply.size()], from).unwrap();
+        }
+    });
+
+    Device { target: TARGET, address }
+}
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(200)).set_retries(0);
+    client
+}
+
+#[test]
+fn responses_are_paired_with_requests() {
+    assert_eq!(Message::GetColor.response_packet_numbers(), &[107]);
+    assert_eq!(Message::GetPower.response_packet_numbers(), &[22]);
+    assert_eq!(Message::responses_for(117), &[118]);
+    assert!(Message::SetReboot.response_packet_numbers().is_empty());
+}
+
+#[test]
+fn messages_split_by_direction() {
+    assert_eq!(DeviceRequest::try_from(Message::GetColor), Ok(DeviceRequest::GetColor));
+    assert_eq!(DeviceRequest::try_from(Message::Acknowledgement), Err(Message::Acknowledgement));
+    assert_eq!(DeviceResponse::try_from(Message::SetPower { level: 0 }), Err(Message::SetPower { level: 0 }));
+    assert_eq!(Message::from(DeviceResponse::Power { level: 1 }), Message::Power { level: 1 });
+
+    // A reply can't be decoded as something to send, and vice versa
+    let mut packet = [0u8; 64];
+    serialize_lifx_packet(&LifxRequestOptions::default(), &DeviceResponse::Power { level: 1 }, &mut packet);
+    assert!(deserialize_lifx_packet_as::<DeviceRequest>(&packet).is_err());
+    assert!(deserialize_lifx_packet_as::<DeviceResponse>(&packet).is_ok());
+}
+
+#[test]
+fn typed_request_returns_the_response_variant() {
+    let device = spawn_fake_bulb();
+
+    let state: responses::LightState = client().request(&device, &DeviceRequest::GetColor).unwrap();
+    assert_eq!((state.hue, state.kelvin, state.label.as_str()), (100, 3500, "Desk"));
+}
+
+#[test]
+fn requesting_the_wrong_response_type_is_rejected() {
+    let device = spawn_fake_bulb();
+
+    match client().request::<responses::Power>(&device, &DeviceRequest::GetColor) {
+        Err(ClientError::ResponseMismatch { request: 101, response: 22 }) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn unhandled_packets_are_reported() {
+    let device = spawn_fake_bulb();
+
+    match client().request::<responses::Label>(&device, &DeviceRequest::GetLabel) {
+        Err(ClientError::Unhandled(23)) => {}
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
@@ -0,0 +1,128 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::time::Duration;
+
+use lifx_lan::{
+    client::LifxClient,
+    effects::{Blend, Breathe, Candle, ColorCycle, Effect, EffectEngine, Keyframes, Layers, Strobe, Waveform},
+    simulator::{Faults, ProductKind, Simulator},
+    DeviceRequest, Hsbk,
+};
+
+const BLACK: Hsbk = Hsbk { hue: 0, saturation: 0, brightness: 0, kelvin: 3500 };
+const WHITE: Hsbk = Hsbk { hue: 0, saturation: 0, brightness: 65535, kelvin: 3500 };
+const RED: Hsbk = Hsbk { hue: 0, saturation: 65535, brightness: 65535, kelvin: 3500 };
+
+struct Fill(Hsbk);
+
+impl Effect for Fill {
+    fn render(&mut self, _elapsed: Duration, pixels: &mut [Hsbk]) {
+        pixels.iter_mut().for_each(|pixel| *pixel = self.0);
+    }
+}
+
+fn render(effect: &mut impl Effect, elapsed: Duration, pixels: usize) -> Vec<Hsbk> {
+    let mut pixels = vec![BLACK; pixels];
+    effect.render(elapsed, &mut pixels);
+    pixels
+}
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+    client
+}
+
+#[test]
+fn breathe_swings_between_its_colours() {
+    let mut breathe = Breathe { from: BLACK, to: WHITE, period: Duration::from_secs(2), cycles: Some(3.0) };
+
+    assert_eq!(render(&mut breathe, Duration::ZERO, 1), [BLACK]);
+    assert_eq!(render(&mut breathe, Duration::from_secs(1), 1), [WHITE]);
+    assert_eq!(render(&mut breathe, Duration::from_secs(2), 1), [BLACK]);
+    assert_eq!(breathe.duration(), Some(Duration::from_secs(6)));
+
+    // Nonsense counts from user input don't panic
+    breathe.cycles = Some(-1.0);
+    assert_eq!(breathe.duration(), Some(Duration::ZERO));
+    breathe.cycles = Some(f32::NAN);
+    assert_eq!(breathe.duration(), Some(Duration::ZERO));
+    let strobe = Strobe { color: WHITE, period: Duration::from_secs(3600), duty: 0.5, cycles: Some(f32::MAX) };
+    assert_eq!(strobe.duration(), None);
+}
+
+#[test]
+fn layers_blend_over_each_other() {
+    let mut half = Layers::new().with(Fill(RED), Blend::Replace, 1.0).with(Fill(WHITE), Blend::Replace, 0.5);
+    assert_eq!(render(&mut half, Duration::ZERO, 2), [Hsbk { saturation: 32768, ..RED }; 2]);
+
+    // A brightness layer dims the colour underneath without changing it
+    let dim = Hsbk { brightness: 16384, ..WHITE };
+    let mut masked = Layers::new().with(Fill(RED), Blend::Replace, 1.0).with(Fill(dim), Blend::Brightness, 1.0);
+    assert_eq!(render(&mut masked, Duration::ZERO, 1), [Hsbk { brightness: 16384, ..RED }]);
+
+    assert_eq!(masked.duration(), None);
+    assert!(masked.native().is_none());
+
+    let strobe = Strobe { color: RED, period: Duration::from_millis(500), duty: 0.25, cycles: Some(4.0) };
+    let single = Layers::new().with(strobe, Blend::Replace, 1.0);
+    assert_eq!(single.duration(), Some(Duration::from_secs(2)));
+    assert_eq!(single.native(), strobe.native());
+}
+
+#[test]
+fn built_in_effects() {
+    let mut cycle =
+        ColorCycle { period: Duration::from_secs(4), spread: 1.0, saturation: 65535, brightness: 65535, kelvin: 3500 };
+    let hues: Vec<u16> = render(&mut cycle, Duration::from_secs(1), 4).iter().map(|pixel| pixel.hue).collect();
+    assert_eq!(hues, [16384, 32768, 49152, 0]);
+
+    // The same seed always flickers the same way, never brighter than the flame itself
+    let frames = |seed| {
+        let mut candle = Candle::new(RED, 0.5, seed);
+        (0..20).map(|frame| render(&mut candle, Duration::from_millis(frame * 50), 3)).collect::<Vec<_>>()
+    };
+    let flame = frames(7);
+    assert_eq!(flame, frames(7));
+    assert!(flame.iter().flatten().all(|pixel| pixel.brightness >= 32767 && pixel.hue == RED.hue));
+    assert!(flame.iter().flatten().any(|pixel| pixel.brightness != flame[0][0].brightness));
+
+    let mut sunrise = Keyframes::sunrise(Duration::from_secs(60), 4000);
+    assert_eq!(render(&mut sunrise, Duration::ZERO, 1)[0].brightness, 0);
+    let early = render(&mut sunrise, Duration::from_secs(10), 1)[0];
+    assert!(early.brightness > 0 && early.brightness < 13107 && early.saturation == 65535);
+    assert_eq!(render(&mut sunrise, Duration::from_secs(90), 1), [Hsbk::new(7282, 0, 65535, 4000)]);
+}
+
+#[test]
+fn engine_streams_frames_and_hands_waveforms_to_the_firmware() {
+    let configs = vec![ProductKind::Bulb.config(0), ProductKind::Strip.config(1), ProductKind::Tile.config(2)];
+    let room = Simulator::start(configs, Faults::default(), 1).unwrap();
+    let devices = room.devices().to_vec();
+    let mut client = client();
+
+    let mut engine = EffectEngine::new(50);
+    engine.add(&mut client, &devices[0], Strobe { color: RED, period: Duration::from_millis(200), duty: 0.25, cycles: None }).unwrap();
+    let cycle = ColorCycle { period: Duration::from_secs(1), spread: 1.0, saturation: 65535, brightness: 65535, kelvin: 3500 };
+    engine.add(&mut client, &devices[1], cycle).unwrap();
+    engine.add(&mut client, &devices[2], Keyframes { frames: vec![(Duration::ZERO, WHITE), (Duration::from_millis(100), RED)] }).unwrap();
+
+    let played = engine.run(&mut client, Duration::from_millis(300)).unwrap();
+    assert!(played.frames >= 10);
+
+    // The bulb got one waveform and nothing after it
+    let bulb = room.state(0).requests;
+    let waveform = bulb.iter().position(|request| matches!(request, DeviceRequest::SetWaveform { waveform, .. } if *waveform == Waveform::Pulse as u8));
+    assert_eq!(waveform, Some(bulb.len() - 1));
+
+    // The strip shows a rainbow, sent a frame at a time
+    let strip = room.state(1);
+    let frames = strip.requests.iter().filter(|request| matches!(request, DeviceRequest::SetExtendedColorZones { .. })).count();
+    assert!(frames >= 5, "{} frames", frames);
+    assert_ne!(strip.zones[0].hue, strip.zones[8].hue);
+
+    // The keyframes ended on red, and the engine stopped sending once they did
+    assert!(room.state(2).tiles.iter().flatten().all(|pixel| *pixel == RED));
+    let tile_frames = room.state(2).requests.iter().filter(|request| matches!(request, DeviceRequest::Set64 { .. })).count();
+    assert!(tile_frames <= 5 * 8, "{} tile frames", tile_frames);
+}
@@ -0,0 +1,74 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::{thread, time::Duration};
+
+use lifx_lan::{
+    client::{ClientError, Device, LifxClient},
+    emulator::{Emulator, EmulatorConfig},
+    messages::responses,
+    DeviceRequest, DeviceResponse, Hsbk,
+};
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(200)).set_retries(1);
+    client
+}
+
+#[test]
+fn answers_get_messages() {
+    let emulator = Emulator::start(EmulatorConfig { label: String::from("Porch"), ..Default::default() }).unwrap();
+    let mut client = client();
+
+    let label: responses::Label = client.request(&emulator.device(), &DeviceRequest::GetLabel).unwrap();
+    assert_eq!(label.label, "Porch");
+
+    let version: responses::Version = client.request(&emulator.device(), &DeviceRequest::GetVersion).unwrap();
+    assert_eq!((version.vendor, version.product), (1, 27));
+
+    let service: responses::StateService = client.request(&emulator.device(), &DeviceRequest::GetService).unwrap();
+    assert_eq!(service.port, emulator.address().port() as u32);
+
+    let power: responses::Power = client.request(&emulator.device(), &DeviceRequest::GetPower).unwrap();
+    assert_eq!(power.level, 0);
+}
+
+#[test]
+fn set_color_transitions_over_its_duration() {
+    let emulator = Emulator::start(EmulatorConfig { color: Hsbk::new(0, 0, 0, 3500), ..Default::default() }).unwrap();
+    let mut client = client();
+
+    let set_color = DeviceRequest::SetColor { reserved_6: 0, hue: 0, saturation: 0, brightness: 65535, kelvin: 3500, duration_ms: 600 };
+    client.send_acked(&emulator.device(), &set_color).unwrap();
+
+    thread::sleep(Duration::from_millis(200));
+    let midway = emulator.state().color.brightness;
+    assert!(midway > 0 && midway < 65535, "brightness {} should be part way", midway);
+
+    thread::sleep(Duration::from_millis(500));
+    assert_eq!(emulator.state().color, Hsbk::new(0, 0, 65535, 3500));
+
+    let state: responses::LightState = client.request(&emulator.device(), &DeviceRequest::GetColor).unwrap();
+    assert_eq!(state.brightness, 65535);
+}
+
+#[test]
+fn set_messages_reply_with_the_previous_state_when_asked() {
+    let emulator = Emulator::start(EmulatorConfig::default()).unwrap();
+    let mut client = client();
+
+    let reply = client.request_message(&emulator.device(), &DeviceRequest::SetLightPower { level: 65535, duration_ms: 0 }).unwrap();
+    assert_eq!(reply, DeviceResponse::LightPower { level: 0 });
+    assert_eq!(emulator.state().power, 65535);
+}
+
+#[test]
+fn ignores_packets_for_other_devices() {
+    let emulator = Emulator::start(EmulatorConfig::default()).unwrap();
+    let mut client = client();
+
+    let stranger = Device { target: [0xD0, 0x73, 0xD5, 9, 9, 9, 0, 0], address: emulator.address() };
+    assert!(matches!(client.send_acked(&stranger, &DeviceRequest::SetPower { level: 65535 }), Err(ClientError::Timeout)));
+    assert_eq!(emulator.state().power, 0);
+    assert!(emulator.state().requests.is_empty());
+}
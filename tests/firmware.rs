@@ -0,0 +1,57 @@
+use core::time::Duration;
+
+use lifx_lan::{messages::responses, FirmwareVersion};
+
+#[test]
+fn displays_as_major_dot_minor() {
+    assert_eq!(FirmwareVersion::new(3, 70).to_string(), "3.70");
+    assert_eq!(FirmwareVersion::new(2, 80).to_string(), "2.80");
+}
+
+#[test]
+fn orders_by_version_then_build() {
+    let mut versions = [
+        FirmwareVersion { major: 3, minor: 70, build: 2 },
+        FirmwareVersion::new(2, 80),
+        FirmwareVersion { major: 3, minor: 70, build: 1 },
+        FirmwareVersion::new(3, 9),
+    ];
+    versions.sort();
+
+    assert_eq!(
+        versions,
+        [
+            FirmwareVersion::new(2, 80),
+            FirmwareVersion::new(3, 9),
+            FirmwareVersion { major: 3, minor: 70, build: 1 },
+            FirmwareVersion { major: 3, minor: 70, build: 2 },
+        ]
+    );
+    assert!(FirmwareVersion { major: 3, minor: 70, build: 1 }.is_at_least(3, 70));
+    assert!(!FirmwareVersion::new(2, 76).is_at_least(2, 77));
+}
+
+#[test]
+fn reads_the_build_time() {
+    let firmware = responses::HostFirmware {
+        build: 1_600_000_000_000_000_000,
+        reserved_6: [0; 8],
+        version_minor: 70,
+        version_major: 3,
+    };
+    let version = FirmwareVersion::from(&firmware);
+
+    assert_eq!(version, FirmwareVersion { major: 3, minor: 70, build: 1_600_000_000_000_000_000 });
+    assert_eq!(version.build_time(), Duration::from_secs(1_600_000_000));
+}
+
+#[test]
+fn knows_when_extended_multizone_arrived() {
+    // LIFX Z gained extended multizone in 2.77
+    assert!(!FirmwareVersion::new(2, 76).supports_extended_multizone(1, 32));
+    assert!(FirmwareVersion::new(2, 77).supports_extended_multizone(1, 32));
+
+    // Bulbs never have it
+    assert!(!FirmwareVersion::new(3, 70).supports_extended_multizone(1, 27));
+    assert_eq!(FirmwareVersion::new(3, 70).features(1, 9999), None);
+}
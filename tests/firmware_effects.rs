@@ -0,0 +1,166 @@
+use core::time::Duration;
+
+use lifx_lan::{
+    firmware_effects::{Direction, MultiZoneEffect, Palette, SkyType, TileEffect},
+    messages::responses,
+    DeviceRequest, Hsbk,
+};
+
+fn palette(count: usize) -> Vec<Hsbk> {
+    (0..count).map(|index| Hsbk::new(index as u16 * 3000, 65535, 65535, 3500)).collect()
+}
+
+// Reads a Set message back the way a device would report it
+fn multizone_state(message: DeviceRequest) -> responses::StateMultiZoneEffect {
+    let DeviceRequest::SetMultiZoneEffect { instance_id, effect_type, speed_ms, duration_ns, direction, .. } = message else {
+        panic!("not a SetMultiZoneEffect: {:?}", message);
+    };
+
+    responses::StateMultiZoneEffect {
+        instance_id,
+        effect_type,
+        reserved_6: [0; 2],
+        speed_ms,
+        duration_ns,
+        reserved_7: [0; 4],
+        reserved_8: [0; 4],
+        reserved_9: [0; 4],
+        direction,
+        reserved_10: [0; 24],
+    }
+}
+
+fn tile_state(message: DeviceRequest) -> responses::StateTileEffect {
+    let DeviceRequest::SetTileEffect {
+        instance_id,
+        effect_type,
+        speed_ms,
+        duration_ns,
+        sky_type,
+        cloud_saturation_min,
+        cloud_saturation_max,
+        palette_count,
+        palette,
+        ..
+    } = message
+    else {
+        panic!("not a SetTileEffect: {:?}", message);
+    };
+
+    responses::StateTileEffect {
+        reserved_6: 0,
+        instance_id,
+        effect_type,
+        speed_ms,
+        duration_ns,
+        reserved_7: [0; 4],
+        reserved_8: [0; 4],
+        sky_type,
+        reserved_9: [0; 3],
+        cloud_saturation_min,
+        reserved_10: [0; 3],
+        cloud_saturation_max,
+        reserved_11: [0; 23],
+        palette_count,
+        palette,
+    }
+}
+
+#[test]
+fn move_effect_encodes_speed_direction_and_duration() {
+    let effect = MultiZoneEffect::Move { speed: Duration::from_secs(3), direction: Direction::Left, duration: Some(Duration::from_secs(60)) };
+
+    let message = effect.to_message(42);
+    assert!(matches!(
+        message,
+        DeviceRequest::SetMultiZoneEffect { instance_id: 42, effect_type: 1, speed_ms: 3000, duration_ns: 60_000_000_000, direction: 1, .. }
+    ));
+    assert_eq!(MultiZoneEffect::from(&multizone_state(message)), effect);
+
+    let forever = MultiZoneEffect::Move { speed: Duration::from_secs(1), direction: Direction::Right, duration: None };
+    assert!(matches!(forever.to_message(1), DeviceRequest::SetMultiZoneEffect { duration_ns: 0, direction: 0, .. }));
+    assert_eq!(MultiZoneEffect::from(&multizone_state(forever.to_message(1))), forever);
+
+    assert_eq!(MultiZoneEffect::from(&multizone_state(MultiZoneEffect::Off.to_message(1))), MultiZoneEffect::Off);
+    assert_eq!(MultiZoneEffect::from(&multizone_state(MultiZoneEffect::Unknown(9).to_message(1))), MultiZoneEffect::Unknown(9));
+}
+
+#[test]
+fn tile_effects_encode_their_parameters() {
+    let morph = TileEffect::Morph { speed: Duration::from_secs(5), duration: None, palette: Palette::new(&palette(20)) };
+    let message = morph.to_message(7);
+    assert!(matches!(message, DeviceRequest::SetTileEffect { effect_type: 2, speed_ms: 5000, palette_count: 16, .. }));
+    assert_eq!(TileEffect::from(&tile_state(message)), morph);
+
+    let short = Palette::new(&palette(3));
+    assert_eq!(short.colors(), palette(3).as_slice());
+
+    let clouds = TileEffect::Sky {
+        speed: Duration::from_secs(50),
+        duration: Some(Duration::from_secs(600)),
+        sky: SkyType::Clouds { cloud_saturation_min: 50, cloud_saturation_max: 180 },
+    };
+    let message = clouds.to_message(8);
+    assert!(matches!(
+        message,
+        DeviceRequest::SetTileEffect { effect_type: 5, sky_type: 2, cloud_saturation_min: 50, cloud_saturation_max: 180, palette_count: 0, .. }
+    ));
+    assert_eq!(TileEffect::from(&tile_state(message)), clouds);
+
+    for effect in [
+        TileEffect::Off,
+        TileEffect::Flame { speed: Duration::from_secs(4), duration: None },
+        TileEffect::Sky { speed: Duration::from_secs(50), duration: None, sky: SkyType::Sunset },
+    ] {
+        assert_eq!(TileEffect::from(&tile_state(effect.to_message(1))), effect);
+    }
+}
+
+#[cfg(not(feature = "no-std"))]
+#[test]
+fn client_starts_and_reads_back_effects() {
+    use lifx_lan::{
+        client::{ClientError, LifxClient},
+        products::{Capability, Unsupported},
+        simulator::{Faults, ProductKind, Simulator},
+    };
+
+    let configs = vec![ProductKind::Bulb.config(0), ProductKind::Strip.config(1), ProductKind::Tile.config(2)];
+    let room = Simulator::start(configs, Faults::default(), 1).unwrap();
+    let [bulb, strip, tile] = [room.devices()[0], room.devices()[1], room.devices()[2]];
+
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+
+    assert_eq!(client.multizone_effect(&strip).unwrap(), MultiZoneEffect::Off);
+    let effect = MultiZoneEffect::Move { speed: Duration::from_secs(2), direction: Direction::Right, duration: None };
+    client.set_multizone_effect(&strip, &effect).unwrap();
+    assert_eq!(client.multizone_effect(&strip).unwrap(), effect);
+
+    let flame = TileEffect::Flame { speed: Duration::from_secs(4), duration: None };
+    client.set_tile_effect(&tile, &flame).unwrap();
+    assert_eq!(client.tile_effect(&tile).unwrap(), flame);
+
+    // Starting the same effect again is still a new instance, so the device restarts it
+    client.set_tile_effect(&tile, &flame).unwrap();
+    let instances: Vec<u32> = room
+        .state(2)
+        .requests
+        .iter()
+        .filter_map(|request| match request {
+            DeviceRequest::SetTileEffect { instance_id, .. } => Some(*instance_id),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(instances.len(), 2);
+    assert_ne!(instances[0], instances[1]);
+
+    assert!(matches!(
+        client.set_tile_effect(&bulb, &flame),
+        Err(ClientError::Unsupported(Unsupported::Missing(Capability::Matrix)))
+    ));
+    assert!(matches!(
+        client.set_multizone_effect(&tile, &effect),
+        Err(ClientError::Unsupported(Unsupported::Missing(Capability::Multizone)))
+    ));
+}
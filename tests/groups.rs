@@ -0,0 +1,173 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::time::Duration;
+
+use lifx_lan::{
+    client::LifxClient,
+    groups::{Directory, GroupError, Kind, Membership},
+    simulator::{Faults, ProductKind, Simulator},
+    DeviceRequest,
+};
+
+fn membership(id: u8, label: &str, updated_at: u64) -> Membership {
+    Membership { id: [id; 16], label: String::from(label), updated_at }
+}
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+    client
+}
+
+// Two rooms in one home, where the kitchen was renamed while one of its bulbs was unplugged
+fn home() -> Simulator {
+    let home = membership(9, "Home", 10);
+    let rooms = [
+        membership(1, "Kitchen", 200),
+        membership(1, "Cooking area", 100),
+        membership(1, "Kitchen", 200),
+        membership(2, "Lounge", 100),
+        membership(2, "Lounge", 100),
+    ];
+
+    let configs = rooms
+        .into_iter()
+        .enumerate()
+        .map(|(index, group)| {
+            let mut config = ProductKind::Bulb.config(index);
+            config.group = group;
+            config.location = home.clone();
+            config
+        })
+        .collect();
+
+    Simulator::start(configs, Faults::default(), 1).unwrap()
+}
+
+#[test]
+fn resolves_groups_by_newest_label() {
+    let simulator = home();
+    let devices = simulator.devices();
+    let directory = Directory::query(&mut client(), devices).unwrap();
+
+    let labels: Vec<_> = directory.groups().iter().map(|group| group.label.as_str()).collect();
+    assert_eq!(labels, ["Kitchen", "Lounge"]);
+
+    let kitchen = directory.group("Kitchen").unwrap();
+    assert_eq!(kitchen.members, &devices[..3]);
+    assert_eq!(kitchen.updated_at, 200);
+    assert!(directory.group("Cooking area").is_none());
+
+    let home = directory.location("Home").unwrap();
+    assert_eq!(home.members, devices);
+    assert_eq!(directory.groups_in(home).count(), 2);
+    assert!(directory.unreachable().is_empty());
+}
+
+#[test]
+fn offline_devices_are_listed_as_unreachable() {
+    let simulator = home();
+    simulator.set_online(4, false);
+
+    let directory = Directory::query(&mut client(), simulator.devices()).unwrap();
+
+    assert_eq!(directory.unreachable(), &simulator.devices()[4..]);
+    assert_eq!(directory.group("Lounge").unwrap().members, &simulator.devices()[3..4]);
+}
+
+#[test]
+fn commands_fan_out_to_every_member() {
+    let simulator = home();
+    let mut client = client();
+    let directory = Directory::query(&mut client, simulator.devices()).unwrap();
+    let kitchen = directory.group("Kitchen").unwrap();
+
+    simulator.set_online(2, false);
+    let failures = kitchen.send_acked(&mut client, &DeviceRequest::SetLightPower { level: 65535, duration_ms: 0 });
+
+    assert_eq!(failures.len(), 1);
+    assert_eq!(failures[0].0, simulator.devices()[2]);
+    assert_eq!(simulator.state(0).power, 65535);
+    assert_eq!(simulator.state(1).power, 65535);
+    assert_eq!(simulator.state(3).power, 0);
+}
+
+#[test]
+fn creates_groups_with_fresh_ids() {
+    let simulator = home();
+    let mut client = client();
+    let mut directory = Directory::query(&mut client, simulator.devices()).unwrap();
+    let devices = simulator.devices();
+
+    let id = directory.create(&mut client, Kind::Group, "Pantry", &devices[2..4]).unwrap();
+    assert_ne!(id, [1; 16]);
+    assert_ne!(id, [2; 16]);
+    // Version 4 UUID
+    assert_eq!(id[6] >> 4, 4);
+
+    let pantry = directory.group("Pantry").unwrap();
+    assert_eq!(pantry.members, &devices[2..4]);
+    assert_eq!(directory.group("Kitchen").unwrap().members, &devices[..2]);
+    assert_eq!(directory.group("Lounge").unwrap().members, &devices[4..]);
+
+    // The devices agree with the directory
+    assert_eq!(simulator.state(2).group.id, id);
+    assert_eq!(simulator.state(3).group.label, "Pantry");
+    assert_eq!(Directory::query(&mut client, devices).unwrap().groups(), directory.groups());
+
+    let other = directory.create(&mut client, Kind::Location, "Cabin", &devices[..1]).unwrap();
+    assert_ne!(other, id);
+}
+
+#[test]
+fn renames_every_member() {
+    let simulator = home();
+    let mut client = client();
+    let mut directory = Directory::query(&mut client, simulator.devices()).unwrap();
+
+    directory.rename(&mut client, Kind::Location, "Home", "Beach house").unwrap();
+
+    assert!(directory.location("Home").is_none());
+    assert_eq!(directory.location("Beach house").unwrap().members, simulator.devices());
+    for index in 0..simulator.devices().len() {
+        assert_eq!(simulator.state(index).location.label, "Beach house");
+        assert_eq!(simulator.state(index).location.id, [9; 16]);
+    }
+
+    assert!(matches!(directory.rename(&mut client, Kind::Group, "Attic", "Loft"), Err(GroupError::NotFound(_))));
+
+    // A label the devices can't hold is turned down before anything is sent
+    let long = "A beach house by the sea, with a view";
+    assert!(matches!(directory.rename(&mut client, Kind::Location, "Beach house", long), Err(GroupError::LabelTooLong(_))));
+    assert!(matches!(directory.create(&mut client, Kind::Group, long, simulator.devices()), Err(GroupError::LabelTooLong(_))));
+    assert_eq!(simulator.state(0).location.label, "Beach house");
+}
+
+#[test]
+fn moves_devices_between_groups() {
+    let simulator = home();
+    let mut client = client();
+    let mut directory = Directory::query(&mut client, simulator.devices()).unwrap();
+    let devices = simulator.devices();
+
+    directory.move_device(&mut client, Kind::Group, &devices[0], "Lounge").unwrap();
+    assert_eq!(directory.group("Lounge").unwrap().members, [devices[3], devices[4], devices[0]]);
+    assert_eq!(directory.group("Kitchen").unwrap().members, &devices[1..3]);
+    assert_eq!(simulator.state(0).group.id, [2; 16]);
+
+    // Emptying a group drops it
+    for device in &devices[1..3] {
+        directory.move_device(&mut client, Kind::Group, device, "Lounge").unwrap();
+    }
+    assert!(directory.group("Kitchen").is_none());
+
+    simulator.set_online(1, false);
+    match directory.create(&mut client, Kind::Group, "Hall", &devices[..2]) {
+        Err(GroupError::Members(failures)) => {
+            assert_eq!(failures.len(), 1);
+            assert_eq!(failures[0].0, devices[1]);
+        }
+        other => panic!("Expected the offline device to fail, got {:?}", other),
+    }
+    assert_eq!(directory.group("Hall").unwrap().members, &devices[..1]);
+}
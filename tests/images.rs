@@ -0,0 +1,141 @@
+#![cfg(all(feature = "images", not(feature = "no-std")))]
+
+use std::{path::PathBuf, time::Duration};
+
+use image::{codecs::gif::GifEncoder, Delay, Rgba, RgbaImage};
+use lifx_lan::{
+    images::{self, Fit, ImageOptions},
+    Hsbk,
+};
+
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+const GREEN: Rgba<u8> = Rgba([0, 255, 0, 255]);
+const BLUE: Rgba<u8> = Rgba([0, 0, 255, 255]);
+const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("lifx_lan_{}_{}", std::process::id(), name))
+}
+
+// Red, green, blue and white quarters
+fn quarters(width: u32, height: u32) -> RgbaImage {
+    RgbaImage::from_fn(width, height, |x, y| match (x < width / 2, y < height / 2) {
+        (true, true) => RED,
+        (false, true) => GREEN,
+        (true, false) => BLUE,
+        (false, false) => WHITE,
+    })
+}
+
+#[test]
+fn converts_rgb_to_hsbk() {
+    assert_eq!(Hsbk::from_rgb(255, 0, 0, 3500), Hsbk::new(0, 65535, 65535, 3500));
+    assert_eq!(Hsbk::from_rgb(0, 255, 0, 3500), Hsbk::new(21845, 65535, 65535, 3500));
+    assert_eq!(Hsbk::from_rgb(0, 0, 255, 3500), Hsbk::new(43691, 65535, 65535, 3500));
+    assert_eq!(Hsbk::from_rgb(255, 0, 255, 3500), Hsbk::new(54613, 65535, 65535, 3500));
+    assert_eq!(Hsbk::from_rgb(128, 128, 128, 2700), Hsbk::new(0, 0, 32896, 2700));
+    assert_eq!(Hsbk::from_rgb(0, 0, 0, 2700), Hsbk::new(0, 0, 0, 2700));
+}
+
+#[test]
+fn loads_a_png_scaled_to_the_canvas() {
+    let path = temp_path("quarters.png");
+    quarters(4, 4).save(&path).unwrap();
+
+    let canvas = images::load(&path, 8, 8, &ImageOptions::default()).unwrap();
+    assert_eq!(canvas.get(0, 0), Some(Hsbk::new(0, 65535, 65535, 6500)));
+    assert_eq!(canvas.get(7, 0).unwrap().hue, 21845);
+    assert_eq!(canvas.get(0, 7).unwrap().hue, 43691);
+    assert_eq!(canvas.get(7, 7), Some(Hsbk::new(0, 0, 65535, 6500)));
+
+    // A wide image fitted into a square leaves unlit bars above and below
+    let wide = temp_path("wide.png");
+    quarters(8, 4).save(&wide).unwrap();
+    let contained = images::load(&wide, 8, 8, &ImageOptions::default()).unwrap();
+    assert_eq!(contained.get(0, 0).unwrap().brightness, 0);
+    assert_eq!(contained.get(0, 2), Some(Hsbk::new(0, 65535, 65535, 6500)));
+    assert_eq!(contained.get(0, 7).unwrap().brightness, 0);
+
+    // Covering the square crops the sides instead
+    let covered = images::load(&wide, 8, 8, &ImageOptions { fit: Fit::Cover, ..Default::default() }).unwrap();
+    assert!(covered.pixels().iter().all(|pixel| pixel.brightness > 0));
+
+    let stretched = images::load(&wide, 8, 8, &ImageOptions { fit: Fit::Stretch, ..Default::default() }).unwrap();
+    assert_eq!(stretched.get(7, 7), Some(Hsbk::new(0, 0, 65535, 6500)));
+
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(wide).unwrap();
+}
+
+#[test]
+fn transparent_pixels_are_dimmed() {
+    let mut image = RgbaImage::from_pixel(2, 1, RED);
+    image.put_pixel(1, 0, Rgba([255, 0, 0, 0]));
+
+    let canvas = images::to_canvas(&image, 2, 1, &ImageOptions { fit: Fit::Stretch, ..Default::default() });
+    assert_eq!(canvas.get(0, 0).unwrap().brightness, 65535);
+    assert_eq!(canvas.get(1, 0).unwrap().brightness, 0);
+}
+
+#[test]
+fn loads_gif_frames_with_their_delays() {
+    let path = temp_path("animation.gif");
+    {
+        let file = std::fs::File::create(&path).unwrap();
+        let mut encoder = GifEncoder::new(file);
+        for (color, delay) in [(RED, 100), (BLUE, 250)] {
+            let frame = image::Frame::from_parts(RgbaImage::from_pixel(4, 4, color), 0, 0, Delay::from_numer_denom_ms(delay, 1));
+            encoder.encode_frame(frame).unwrap();
+        }
+    }
+
+    let frames = images::load_frames(&path, 8, 8, &ImageOptions::default()).unwrap();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].delay, Duration::from_millis(100));
+    assert_eq!(frames[1].delay, Duration::from_millis(250));
+    assert!(frames[0].canvas.pixels().iter().all(|pixel| pixel.hue == 0 && pixel.saturation == 65535));
+    assert!(frames[1].canvas.pixels().iter().all(|pixel| pixel.hue == 43691));
+
+    // A still image is a single frame
+    let still = temp_path("still.png");
+    quarters(2, 2).save(&still).unwrap();
+    let frames = images::load_frames(&still, 8, 8, &ImageOptions::default()).unwrap();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].delay, Duration::ZERO);
+
+    assert!(matches!(images::load(temp_path("missing.png"), 8, 8, &ImageOptions::default()), Err(images::ImageError::Io(_))));
+
+    std::fs::remove_file(path).unwrap();
+    std::fs::remove_file(still).unwrap();
+}
+
+#[test]
+fn plays_frames_on_a_tile_chain() {
+    use lifx_lan::{
+        canvas::TileChain,
+        client::LifxClient,
+        messages::responses,
+        simulator::{Faults, ProductKind, Simulator},
+        DeviceRequest,
+    };
+
+    let simulator = Simulator::start(vec![ProductKind::Tile.config(0)], Faults::default(), 1).unwrap();
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+    let chain = TileChain::query(&mut client, &simulator.devices()[0]).unwrap();
+
+    let frames: Vec<_> = [RED, GREEN]
+        .into_iter()
+        .map(|color| images::Frame {
+            canvas: images::to_canvas(&RgbaImage::from_pixel(1, 1, color), 40, 8, &ImageOptions { fit: Fit::Stretch, ..Default::default() }),
+            delay: Duration::from_millis(10),
+        })
+        .collect();
+
+    images::play(&mut client, &chain, &frames, 2).unwrap();
+    client.request::<responses::StateDeviceChain>(chain.device(), &DeviceRequest::GetDeviceChain).unwrap();
+
+    let flips = simulator.state(0).requests.iter().filter(|request| matches!(request, DeviceRequest::CopyFrameBuffer { .. })).count();
+    assert_eq!(flips, 4);
+    assert!(simulator.state(0).tiles.iter().flatten().all(|pixel| pixel.hue == 21845));
+}
@@ -0,0 +1,85 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::{thread, time::Duration};
+
+use lifx_lan::{
+    client::LifxClient,
+    emulator::{Emulator, EmulatorConfig},
+    light::Light,
+    DeviceRequest, Hsbk,
+};
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(200)).set_retries(1).set_rate_limit(None);
+    client
+}
+
+#[test]
+fn get_state_fills_the_cache() {
+    let emulator = Emulator::start(EmulatorConfig { label: String::from("Desk"), power: 65535, ..Default::default() }).unwrap();
+    let mut client = client();
+    let mut light = Light::new(emulator.device());
+
+    assert_eq!(light.state(), None);
+    assert!(light.is_stale(Duration::from_secs(60)));
+
+    let state = light.get_state(&mut client).unwrap();
+    assert_eq!(state.label, "Desk");
+    assert_eq!(state.color, Hsbk::new(0, 0, 65535, 3500));
+    assert!(state.is_on());
+
+    assert!(!light.is_stale(Duration::from_secs(60)));
+    thread::sleep(Duration::from_millis(30));
+    assert!(light.is_stale(Duration::from_millis(10)));
+}
+
+#[test]
+fn setters_update_the_cache() {
+    let emulator = Emulator::start(EmulatorConfig::default()).unwrap();
+    let mut client = client();
+    let mut light = Light::new(emulator.device());
+    light.get_state(&mut client).unwrap();
+
+    light.power_on(&mut client, Duration::ZERO).unwrap();
+    light.set_color(&mut client, Hsbk::new(21845, 65535, 40000, 3500), Duration::ZERO).unwrap();
+    light.set_brightness(&mut client, 10000, Duration::ZERO).unwrap();
+
+    let cached = light.state().unwrap().clone();
+    assert_eq!(cached.color, Hsbk::new(21845, 65535, 10000, 3500));
+    assert_eq!(cached.power, 65535);
+
+    // The cache agrees with the light
+    assert_eq!(light.get_state(&mut client).unwrap(), &cached);
+    assert_eq!(emulator.state().color, cached.color);
+}
+
+#[test]
+fn partial_changes_fetch_the_state_they_need_once() {
+    let emulator = Emulator::start(EmulatorConfig { color: Hsbk::new(100, 65535, 30000, 3500), ..Default::default() }).unwrap();
+    let mut client = client();
+    let mut light = Light::new(emulator.device());
+
+    light.set_kelvin(&mut client, 2700, Duration::ZERO).unwrap();
+    light.set_brightness(&mut client, 50000, Duration::ZERO).unwrap();
+
+    assert_eq!(emulator.state().color, Hsbk::new(100, 0, 50000, 2700));
+
+    let color_requests = emulator.state().requests.into_iter().filter(|request| *request == DeviceRequest::GetColor).count();
+    assert_eq!(color_requests, 1);
+}
+
+#[test]
+fn get_state_within_only_asks_when_stale() {
+    let emulator = Emulator::start(EmulatorConfig::default()).unwrap();
+    let mut client = client();
+    let mut light = Light::new(emulator.device());
+
+    light.get_state_within(&mut client, Duration::from_secs(60)).unwrap();
+    light.get_state_within(&mut client, Duration::from_secs(60)).unwrap();
+    thread::sleep(Duration::from_millis(30));
+    light.get_state_within(&mut client, Duration::from_millis(10)).unwrap();
+
+    let color_requests = emulator.state().requests.into_iter().filter(|request| *request == DeviceRequest::GetColor).count();
+    assert_eq!(color_requests, 2);
+}
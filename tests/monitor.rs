@@ -0,0 +1,140 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::{net::UdpSocket, sync::mpsc, time::Duration};
+
+use lifx_lan::{
+    client::LifxClient,
+    monitor::{Change, StateMonitor},
+    serialize_lifx_packet,
+    simulator::{Faults, ProductKind, Simulator},
+    DeviceRequest, Hsbk, LifxRequestOptions, Message,
+};
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+    client
+}
+
+fn set_color(color: Hsbk) -> DeviceRequest {
+    let Hsbk { hue, saturation, brightness, kelvin } = color;
+    DeviceRequest::SetColor { reserved_6: 0, hue, saturation, brightness, kelvin, duration_ms: 0 }
+}
+
+fn changes(monitor: &mut StateMonitor) -> Vec<Change> {
+    let mut changes = Vec::new();
+    monitor.refresh(|change| changes.push(change));
+    changes
+}
+
+#[test]
+fn polling_reports_what_changed() {
+    let simulator = Simulator::start(vec![ProductKind::Bulb.config(0), ProductKind::Switch.config(1)], Faults::default(), 1).unwrap();
+    let devices = simulator.devices();
+    let mut monitor = StateMonitor::new(client());
+    devices.iter().for_each(|device| monitor.watch(*device));
+
+    assert_eq!(changes(&mut monitor), [Change::Online { device: devices[0] }, Change::Online { device: devices[1] }]);
+    assert_eq!(monitor.get(&devices[0]).unwrap().state.as_ref().unwrap().label, "Bulb 1");
+    // The switch has no light to report
+    assert!(monitor.get(&devices[1]).unwrap().online && monitor.get(&devices[1]).unwrap().state.is_none());
+    assert!(changes(&mut monitor).is_empty());
+
+    // Another app changes the bulb
+    let mut app = client();
+    let red = Hsbk::new(0, 65535, 65535, 3500);
+    let before = monitor.get(&devices[0]).unwrap().state.clone().unwrap();
+    app.send_acked(&devices[0], &set_color(red)).unwrap();
+    app.send_acked(&devices[0], &DeviceRequest::SetLightPower { level: u16::MAX, duration_ms: 0 }).unwrap();
+    app.send_acked(&devices[0], &DeviceRequest::SetLabel { label: String::from("Desk") }).unwrap();
+
+    assert_eq!(
+        changes(&mut monitor),
+        [
+            Change::Power { device: devices[0], from: 0, to: u16::MAX },
+            Change::Color { device: devices[0], from: before.color, to: red },
+            Change::Label { device: devices[0], from: String::from("Bulb 1"), to: String::from("Desk") },
+        ]
+    );
+}
+
+#[test]
+fn devices_that_stop_answering_go_offline() {
+    let simulator = Simulator::start(vec![ProductKind::Bulb.config(0)], Faults::default(), 1).unwrap();
+    let device = simulator.devices()[0];
+    let mut monitor = StateMonitor::new(client());
+    monitor.set_offline_after(2);
+    monitor.watch(device);
+    changes(&mut monitor);
+
+    simulator.set_online(0, false);
+    assert!(changes(&mut monitor).is_empty());
+    assert_eq!(changes(&mut monitor), [Change::Offline { device }]);
+    assert!(changes(&mut monitor).is_empty());
+    assert!(!monitor.get(&device).unwrap().online);
+
+    simulator.set_online(0, true);
+    assert_eq!(changes(&mut monitor), [Change::Online { device }]);
+}
+
+#[test]
+fn hears_changes_other_apps_make() {
+    let simulator = Simulator::start(vec![ProductKind::Bulb.config(0)], Faults::default(), 1).unwrap();
+    let device = simulator.devices()[0];
+
+    // Nothing is watched and polls are far apart, so the monitor can only learn of the bulb by hearing it. Simulated
+    // devices each answer from a port of their own.
+    let mut monitor = StateMonitor::new(client());
+    monitor.listen("127.0.0.1:0").unwrap().set_poll_interval(Duration::from_secs(3600)).set_device_port(None);
+    simulator.add_listener(monitor.listen_addr().unwrap().unwrap());
+
+    let mut app = client();
+    app.set_source(0);
+    app.send_acked(&device, &DeviceRequest::SetLightPower { level: u16::MAX, duration_ms: 0 }).unwrap();
+
+    let (sender, receiver) = mpsc::channel();
+    monitor.run(Duration::from_millis(200), |change| sender.send(change).unwrap()).unwrap();
+    assert_eq!(receiver.try_iter().collect::<Vec<_>>(), [Change::Online { device }]);
+    assert_eq!(monitor.get(&device).unwrap().state.as_ref().unwrap().power, u16::MAX);
+
+    // Changes made with another source aren't broadcast, so they wait for the next poll
+    let mut quiet = client();
+    quiet.send_acked(&device, &DeviceRequest::SetLightPower { level: 0, duration_ms: 0 }).unwrap();
+    monitor.run(Duration::from_millis(100), |change| sender.send(change).unwrap()).unwrap();
+    assert!(receiver.try_iter().next().is_none());
+
+    app.send_acked(&device, &set_color(Hsbk::new(0, 0, 30000, 2700))).unwrap();
+    monitor.run(Duration::from_millis(200), |change| sender.send(change).unwrap()).unwrap();
+    let heard: Vec<Change> = receiver.try_iter().collect();
+    assert_eq!(heard.len(), 2);
+    assert!(matches!(heard[0], Change::Power { from: u16::MAX, to: 0, .. }));
+    assert!(matches!(heard[1], Change::Color { to: Hsbk { brightness: 30000, kelvin: 2700, .. }, .. }));
+}
+
+#[test]
+fn ignores_packets_that_arent_from_devices() {
+    let simulator = Simulator::start(vec![ProductKind::Bulb.config(0)], Faults::default(), 1).unwrap();
+    let device = simulator.devices()[0];
+
+    let mut monitor = StateMonitor::new(client());
+    monitor.listen("127.0.0.1:0").unwrap().set_device_port(None);
+    let listener = monitor.listen_addr().unwrap().unwrap();
+
+    // An app asking the bulb for a packet type this crate doesn't model
+    let app = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let options = LifxRequestOptions { target: device.target, ..Default::default() };
+    let mut buffer = [0u8; 64];
+    serialize_lifx_packet(&options, &Message::Unknown { packet_number: 999, payload: Default::default() }, &mut buffer);
+    app.send_to(&buffer[..36], listener).unwrap();
+
+    monitor.run(Duration::from_millis(100), |change| panic!("unexpected {:?}", change)).unwrap();
+    assert!(monitor.devices().is_empty());
+
+    // Replies only count when they come from the port devices use
+    monitor.set_device_port(Some(56700));
+    serialize_lifx_packet(&options, &Message::Power { level: 0 }, &mut buffer);
+    app.send_to(&buffer[..38], listener).unwrap();
+
+    monitor.run(Duration::from_millis(100), |change| panic!("unexpected {:?}", change)).unwrap();
+    assert!(monitor.devices().is_empty());
+}
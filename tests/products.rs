@@ -0,0 +1,70 @@
+use lifx_lan::products::{product_info, products, TemperatureRange};
+use serde_json::Value;
+
+#[test]
+fn looks_up_products_by_id() {
+    let tile = product_info(1, 55, (3, 70)).unwrap();
+    assert_eq!(tile.name, "LIFX Tile");
+    assert!(tile.features.color && tile.features.matrix && tile.features.chain);
+    assert!(!tile.features.multizone);
+
+    let switch = product_info(1, 70, (3, 70)).unwrap();
+    assert!(switch.features.relays && switch.features.buttons);
+    assert_eq!(switch.features.temperature_range, None);
+
+    assert_eq!(product_info(1, 9999, (3, 70)), None);
+    assert_eq!(product_info(2, 27, (3, 70)), None);
+}
+
+#[test]
+fn upgrades_apply_from_their_firmware_version() {
+    let old = product_info(1, 32, (2, 76)).unwrap().features;
+    assert!(!old.extended_multizone);
+    assert_eq!(old.temperature_range, Some(TemperatureRange { min: 2500, max: 9000 }));
+
+    let extended = product_info(1, 32, (2, 77)).unwrap().features;
+    assert!(extended.extended_multizone);
+    assert_eq!(extended.temperature_range, Some(TemperatureRange { min: 2500, max: 9000 }));
+
+    let current = product_info(1, 32, (3, 0)).unwrap().features;
+    assert!(current.extended_multizone);
+    assert_eq!(current.temperature_range, Some(TemperatureRange { min: 1500, max: 9000 }));
+}
+
+// The generated catalogue must say the same as the products.json it was generated from
+#[test]
+fn catalogue_matches_products_json() {
+    let json: Value = serde_json::from_str(include_str!("../products.json")).unwrap();
+    let mut count = 0;
+
+    for vendor in json.as_array().unwrap() {
+        for product in vendor["products"].as_array().unwrap() {
+            let id = (vendor["vid"].as_u64().unwrap() as u32, product["pid"].as_u64().unwrap() as u32);
+            let info = product_info(id.0, id.1, (0, 0)).unwrap_or_else(|| panic!("{:?} missing from the catalogue", id));
+            count += 1;
+
+            assert_eq!(info.name, product["name"].as_str().unwrap());
+
+            let expected = |name: &str| product["features"].get(name).unwrap_or(&vendor["defaults"][name]).clone();
+            let features = info.features;
+            for (name, actual) in [
+                ("hev", features.hev),
+                ("color", features.color),
+                ("chain", features.chain),
+                ("matrix", features.matrix),
+                ("relays", features.relays),
+                ("buttons", features.buttons),
+                ("infrared", features.infrared),
+                ("multizone", features.multizone),
+                ("extended_multizone", features.extended_multizone),
+            ] {
+                assert_eq!(Value::Bool(actual), expected(name), "{} of {:?}", name, id);
+            }
+
+            let range = features.temperature_range.map(|range| serde_json::json!([range.min, range.max])).unwrap_or(Value::Null);
+            assert_eq!(range, expected("temperature_range"), "temperature_range of {:?}", id);
+        }
+    }
+
+    assert_eq!(count, products().len());
+}
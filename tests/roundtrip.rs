@@ -0,0 +1,367 @@
+// Run with `cargo test --features no-std` to exercise the heapless representation.
+use lifx_lan::{
+    deserialize_lifx_packet, deserialize_lifx_packet_ref, messages::TileDevice, serialize_lifx_packet, Hsbk, LifxRequestOptions, Message,
+    MessageRef,
+};
+use lifx_serialization::LifxPayload;
+
+fn label<T: TryFrom<&'static str>>(value: &'static str) -> T
+where
+    T::Error: core::fmt::Debug,
+{
+    value.try_into().unwrap()
+}
+
+fn payload<T: TryFrom<&'static [u8]>>(value: &'static [u8]) -> T
+where
+    T::Error: core::fmt::Debug,
+{
+    value.try_into().unwrap()
+}
+
+// Distinct colours so a misplaced element shows up
+fn colors<const N: usize>() -> [Hsbk; N] {
+    core::array::from_fn(|index| Hsbk::new(index as u16 * 700, 65535 - index as u16, 1000 + index as u16, 2500 + index as u16))
+}
+
+fn all_messages() -> Vec<Message> {
+    vec![
+        Message::Service { service: 1, port: 56700 },
+        Message::StateService { service: 1, port: 56700 },
+        Message::GetMeshInfo {},
+        Message::MeshInfo { signal: 0.5, tx: 10, rx: 20, mcu_temperature: 40 },
+        Message::HostFirmware { build: 1_600_000_000_000_000_000, reserved_6: [0; 8], version_minor: 70, version_major: 3 },
+        Message::WifiInfo { signal: 0.25, tx: 1, rx: 2, mcu_temperature: 3 },
+        Message::WifiFirmware { build: 1_500_000_000_000_000_000, reserved_6: [0; 8], version_minor: 1, version_major: 2 },
+        Message::Power { level: 65535 },
+        Message::Acknowledgement,
+        Message::Label { label: label("Kitchen") },
+        Message::Version { vendor: 1, product: 27, reserved_6: 0 },
+        Message::Info { time: 1, uptime: 2, downtime: 3 },
+        Message::Location { location: [7; 16], label: label("Home"), updated_at: 42 },
+        Message::Group { group: [9; 16], label: label("Living Room"), updated_at: 43 },
+        Message::EchoResponse { echoing: [0xAB; 64] },
+        Message::Unhandled { unhandled_type: 999 },
+        Message::LightState {
+            hue: 1,
+            saturation: 2,
+            brightness: 3,
+            kelvin: 3500,
+            reserved_6: [0; 2],
+            power: 65535,
+            label: label("Desk"),
+            reserved_7: [0; 8],
+        },
+        Message::LightPower { level: 0 },
+        Message::Infrared { brightness: 100 },
+        Message::HevCycle { duration_s: 7200, remaining_s: 30, last_power: 1 },
+        Message::HevCycleConfig { indication: 1, duration_s: 7200 },
+        Message::LastHevCycleResult { result: 0 },
+        Message::StateZone { zones_count: 16, zone_index: 3, hue: 1, saturation: 2, brightness: 3, kelvin: 3500 },
+        Message::StateMultiZone { zones_count: 16, zone_index: 8, colors: colors() },
+        Message::StateExtendedColorZones { zones_count: 120, zone_index: 82, colors_count: 38, colors: colors() },
+        Message::StateDeviceChain {
+            start_index: 0,
+            tile_devices: core::array::from_fn(|index| TileDevice {
+                accel_meas_x: -(index as i16),
+                user_x: index as f32 * 1.5,
+                user_y: -0.5,
+                width: 8,
+                height: 8,
+                device_version_vendor: 1,
+                device_version_product: 55,
+                firmware_build: 1_600_000_000_000_000_000 + index as u64,
+                firmware_version_minor: 70,
+                firmware_version_major: 3,
+                ..Default::default()
+            }),
+            tile_devices_count: 5,
+        },
+        Message::State64 { tile_index: 2, reserved_6: 0, x: 0, y: 0, width: 8, colors: colors() },
+        Message::StateMultiZoneEffect {
+            instance_id: 77,
+            effect_type: 1,
+            reserved_6: [0; 2],
+            speed_ms: 3000,
+            duration_ns: 0,
+            reserved_7: [0; 4],
+            reserved_8: [0; 4],
+            reserved_9: [0; 4],
+            direction: 1,
+            reserved_10: [0; 24],
+        },
+        Message::StateTileEffect {
+            reserved_6: 0,
+            instance_id: 78,
+            effect_type: 5,
+            speed_ms: 50000,
+            duration_ns: 60_000_000_000,
+            reserved_7: [0; 4],
+            reserved_8: [0; 4],
+            sky_type: 2,
+            reserved_9: [0; 3],
+            cloud_saturation_min: 50,
+            reserved_10: [0; 3],
+            cloud_saturation_max: 180,
+            reserved_11: [0; 23],
+            palette_count: 3,
+            palette: colors(),
+        },
+        Message::GetService,
+        Message::GetHostFirmware,
+        Message::GetWifiInfo,
+        Message::GetWifiFirmware,
+        Message::GetPower,
+        Message::SetPower { level: 65535 },
+        Message::GetLabel,
+        Message::SetLabel { label: label("A label that is exactly 32 bytes") },
+        Message::GetVersion,
+        Message::GetInfo,
+        Message::SetReboot,
+        Message::GetLocation,
+        Message::SetLocation { location: [1; 16], label: label("Office"), updated_at: 5 },
+        Message::GetGroup,
+        Message::SetGroup { group: [2; 16], label: label("Bedroom"), updated_at: 6 },
+        Message::EchoRequest { echoing: [0x5A; 64] },
+        Message::GetColor,
+        Message::SetColor { reserved_6: 0, hue: 21845, saturation: 65535, brightness: 32768, kelvin: 3500, duration_ms: 1000 },
+        Message::SetWaveform {
+            reserved_6: 0,
+            transient: 1,
+            hue: 1,
+            saturation: 2,
+            brightness: 3,
+            kelvin: 4,
+            period_ms: 500,
+            cycles: 2.5,
+            skew_ratio: -16384,
+            waveform: 4,
+        },
+        Message::GetLightPower,
+        Message::SetLightPower { level: 65535, duration_ms: 250 },
+        Message::SetWaveformOptional {
+            reserved_6: 0,
+            transient: 0,
+            hue: 1,
+            saturation: 2,
+            brightness: 3,
+            kelvin: 4,
+            period_ms: 500,
+            cycles: 1.0,
+            skew_ratio: 0,
+            waveform: 1,
+            set_hue: 1,
+            set_saturation: 0,
+            set_brightness: 1,
+            set_kelvin: 0,
+        },
+        Message::GetInfrared,
+        Message::SetInfrared { brightness: 65535 },
+        Message::GetHevCycle,
+        Message::SetHevCycle { duration_s: 3600 },
+        Message::GetHevCycleConfiguration,
+        Message::SetHevCycleConfiguration { indication: 0, duration_s: 3600 },
+        Message::GetLastHevCycleResult,
+        Message::SetColorZones {
+            start_index: 0,
+            end_index: 7,
+            hue: 1,
+            saturation: 2,
+            brightness: 3,
+            kelvin: 3500,
+            duration_ms: 100,
+            apply: 1,
+        },
+        Message::GetColorZones { start_index: 0, end_index: 255 },
+        Message::SetExtendedColorZones { duration_ms: 100, apply: 1, zone_index: 0, colors_count: 82, colors: colors() },
+        Message::GetExtendedColorZones,
+        Message::GetDeviceChain,
+        Message::Get64 { tile_index: 0, length: 5, reserved_6: 0, x: 0, y: 0, width: 8 },
+        Message::Set64 { tile_index: 1, length: 1, fb_index: 0, x: 0, y: 0, width: 8, duration_ms: 0, colors: colors() },
+        Message::SetMultiZoneEffect {
+            instance_id: 77,
+            effect_type: 1,
+            reserved_6: [0; 2],
+            speed_ms: 3000,
+            duration_ns: 0,
+            reserved_7: [0; 4],
+            reserved_8: [0; 4],
+            reserved_9: [0; 4],
+            direction: 1,
+            reserved_10: [0; 24],
+        },
+        Message::GetMultiZoneEffect,
+        Message::SetTileEffect {
+            reserved_6: 0,
+            reserved_7: 0,
+            instance_id: 78,
+            effect_type: 2,
+            speed_ms: 3000,
+            duration_ns: 0,
+            reserved_8: [0; 4],
+            reserved_9: [0; 4],
+            sky_type: 0,
+            reserved_10: [0; 3],
+            cloud_saturation_min: 0,
+            reserved_11: [0; 3],
+            cloud_saturation_max: 0,
+            reserved_12: [0; 23],
+            palette_count: 16,
+            palette: colors(),
+        },
+        Message::GetTileEffect { reserved_6: 0, reserved_7: 0 },
+        Message::CopyFrameBuffer {
+            tile_index: 0,
+            length: 5,
+            src_fb_index: 1,
+            dst_fb_index: 0,
+            src_x: 0,
+            src_y: 0,
+            dst_x: 0,
+            dst_y: 0,
+            width: 8,
+            height: 8,
+            duration_ms: 250,
+        },
+        Message::SetAccessPoint {
+            interface: 2,
+            ssid: label("my network"),
+            password: label("a password that does not fit in thirty-two bytes"),
+            protocol: 5,
+        },
+        Message::Unknown { packet_number: 9999, payload: payload(&[1, 2, 3, 4, 5]) },
+    ]
+}
+
+#[test]
+fn every_message_round_trips() {
+    for message in all_messages() {
+        let mut buffer = [0u8; 1024];
+        serialize_lifx_packet(&LifxRequestOptions::default(), &message, &mut buffer);
+
+        let (header, decoded) = deserialize_lifx_packet(&buffer).unwrap();
+
+        assert_eq!(header.packet_number, message.packet_number());
+        assert_eq!(header.size as usize, 36 + message.size());
+        assert_eq!(decoded, message);
+    }
+}
+
+#[test]
+fn every_message_decodes_as_a_borrowed_view() {
+    for message in all_messages() {
+        let mut buffer = [0u8; 1024];
+        serialize_lifx_packet(&LifxRequestOptions::default(), &message, &mut buffer);
+
+        let (_header, view) = deserialize_lifx_packet_ref(&buffer).unwrap();
+        assert_eq!(view.packet_number(), message.packet_number());
+        assert_eq!(view.to_message().unwrap(), message);
+
+        let mut reencoded = [0u8; 1024];
+        assert_eq!(view.to_bytes(&mut reencoded), message.size());
+        assert_eq!(reencoded[..message.size()], buffer[36..36 + message.size()]);
+    }
+}
+
+#[test]
+fn borrowed_strings_point_into_the_buffer() {
+    let mut buffer = [0u8; 1024];
+    serialize_lifx_packet(&LifxRequestOptions::default(), &Message::Label { label: label("Porch") }, &mut buffer);
+
+    match deserialize_lifx_packet_ref(&buffer).unwrap() {
+        (_, MessageRef::Label { label }) => {
+            assert_eq!(label, "Porch");
+            assert_eq!(label.as_ptr(), buffer[36..].as_ptr());
+        }
+        (_, other) => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[test]
+fn strings_are_zero_padded_to_their_wire_size() {
+    let message = Message::SetLabel { label: label("Lamp") };
+
+    let mut buffer = [0xFFu8; 32];
+    assert_eq!(message.to_bytes(&mut buffer), 32);
+
+    assert_eq!(&buffer[..4], b"Lamp");
+    assert!(buffer[4..].iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn long_strings_are_cut_between_characters() {
+    // The "é" straddles the end of the 32 byte field, so it's left out whole
+    let mut buffer = [0xFFu8; 32];
+    lifx_serialization::serialize_string("Thirty-one bytes of plain text é", &mut buffer);
+
+    assert_eq!(&buffer[..31], b"Thirty-one bytes of plain text ");
+    assert_eq!(buffer[31], 0);
+    assert_eq!(lifx_serialization::deserialize_str(&buffer).unwrap(), "Thirty-one bytes of plain text ");
+}
+
+#[test]
+fn borrowed_byte_fields_of_the_wrong_length_are_fitted() {
+    let mut buffer = [0xFFu8; 56];
+
+    let short = MessageRef::Group { group: &[1, 2, 3], label: "Den", updated_at: 0 };
+    assert_eq!(short.to_bytes(&mut buffer), 56);
+    assert_eq!(&buffer[..3], [1, 2, 3]);
+    assert!(buffer[3..16].iter().all(|&byte| byte == 0));
+
+    let long = MessageRef::Group { group: &[7; 20], label: "Den", updated_at: 0 };
+    long.to_bytes(&mut buffer);
+    assert_eq!(buffer[..16], [7; 16]);
+    assert_eq!(&buffer[16..19], b"Den");
+}
+
+#[test]
+fn unknown_packets_pass_through_byte_for_byte() {
+    let mut packet = [0u8; 1024];
+    serialize_lifx_packet(&LifxRequestOptions::default(), &Message::SetPower { level: 65535 }, &mut packet);
+
+    // Relabel a known packet with a type this crate has never heard of, and append trailing junk
+    packet[0..2].copy_from_slice(&40u16.to_le_bytes());
+    packet[32..34].copy_from_slice(&4242u16.to_le_bytes());
+    packet[38..40].copy_from_slice(&[0xCA, 0xFE]);
+    packet[40] = 0xFF;
+
+    let (header, message) = deserialize_lifx_packet(&packet).unwrap();
+    assert_eq!(message, Message::Unknown { packet_number: 4242, payload: payload(&[0xFF, 0xFF, 0xCA, 0xFE]) });
+
+    let options = LifxRequestOptions { source: header.source, ..Default::default() };
+
+    let mut forwarded = [0u8; 1024];
+    serialize_lifx_packet(&options, &message, &mut forwarded);
+    assert_eq!(forwarded[..40], packet[..40]);
+}
+
+#[test]
+fn schema_describes_every_known_message() {
+    for message in all_messages() {
+        if let Message::Unknown { .. } = message {
+            assert_eq!(Message::name_for(9999), None);
+            continue;
+        }
+
+        let schema = Message::schema_for(message.packet_number()).unwrap();
+
+        assert_eq!(schema.name, message.name());
+        assert_eq!(schema.size, message.size());
+        assert_eq!(schema.fields.iter().map(|field| field.size).sum::<usize>(), schema.size);
+    }
+
+    assert_eq!(Message::schema().len(), all_messages().len() - 1);
+    assert_eq!(Message::name_for(101), Some("GetColor"));
+
+    let label = Message::schema_for(107).unwrap().fields.iter().find(|field| field.name == "label").unwrap();
+    assert_eq!((label.offset, label.size, label.ty), (12, 32, lifx_serialization::FieldType::String));
+
+    let colors = Message::schema_for(512).unwrap().fields.iter().find(|field| field.name == "colors").unwrap();
+    assert_eq!((colors.offset, colors.size), (5, 656));
+    assert_eq!(colors.ty.to_string(), "Hsbk[82]");
+    assert_eq!(Message::schema_for(702).unwrap().size, 882);
+    assert_eq!(Message::schema_for(508).unwrap().size, 59);
+    assert_eq!(Message::schema_for(509).unwrap().size, 59);
+    assert_eq!(Message::schema_for(719).unwrap().size, 188);
+    assert_eq!(Message::schema_for(720).unwrap().size, 187);
+}
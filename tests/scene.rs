@@ -0,0 +1,169 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::{net::UdpSocket, time::Duration};
+
+use lifx_lan::{
+    client::{Device, LifxClient},
+    messages::{MULTIZONE_APPLY, MULTIZONE_NO_APPLY},
+    scene::Scene,
+    simulator::{Faults, ProductKind, Simulator},
+    DeviceRequest, Hsbk,
+};
+
+const RED: Hsbk = Hsbk { hue: 0, saturation: 65535, brightness: 65535, kelvin: 3500 };
+const BLUE: Hsbk = Hsbk { hue: 43690, saturation: 65535, brightness: 32768, kelvin: 3500 };
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+    client
+}
+
+fn room() -> Simulator {
+    let kinds = [ProductKind::Bulb, ProductKind::Strip, ProductKind::Tile, ProductKind::Switch];
+    let configs = kinds.iter().enumerate().map(|(index, kind)| kind.config(index)).collect();
+    Simulator::start(configs, Faults::default(), 1).unwrap()
+}
+
+fn set_color(color: Hsbk) -> DeviceRequest {
+    let Hsbk { hue, saturation, brightness, kelvin } = color;
+    DeviceRequest::SetColor { reserved_6: 0, hue, saturation, brightness, kelvin, duration_ms: 0 }
+}
+
+fn set_zones(start_index: u8, end_index: u8, color: Hsbk) -> DeviceRequest {
+    let Hsbk { hue, saturation, brightness, kelvin } = color;
+    DeviceRequest::SetColorZones { start_index, end_index, hue, saturation, brightness, kelvin, duration_ms: 0, apply: MULTIZONE_APPLY }
+}
+
+fn set_tile(tile_index: u8, color: Hsbk) -> DeviceRequest {
+    DeviceRequest::Set64 { tile_index, length: 1, fb_index: 0, x: 0, y: 0, width: 8, duration_ms: 0, colors: [color; 64] }
+}
+
+#[test]
+fn captures_lights_zones_and_tiles() {
+    let room = room();
+    let mut client = client();
+
+    let captured = Scene::capture(&mut client, room.devices());
+    assert!(captured.failed.is_empty());
+    let scene = captured.scene;
+
+    // The switch has no light to capture
+    assert_eq!(scene.devices.len(), 3);
+    assert_eq!(scene.devices[0].target, room.devices()[0].target);
+    assert!(scene.devices[0].zones.is_empty() && scene.devices[0].tiles.is_empty());
+    assert_eq!(scene.devices[1].zones, room.state(1).zones);
+    assert_eq!(scene.devices[1].zones.len(), 16);
+    assert_eq!(scene.devices[2].tiles.len(), 5);
+    assert!(scene.devices[2].tiles.iter().zip(&room.state(2).tiles).all(|(captured, tile)| captured == tile));
+}
+
+#[test]
+fn capture_carries_on_past_devices_that_dont_answer() {
+    let room = room();
+    let mut client = client();
+
+    // A socket that never replies stands in for a light that has gone away
+    let silent = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let gone = Device { target: [0xD0, 0x73, 0xD5, 0x10, 0, 9, 0, 0], address: silent.local_addr().unwrap() };
+    let mut devices = room.devices().to_vec();
+    devices.insert(1, gone);
+
+    let captured = Scene::capture(&mut client, &devices);
+    assert_eq!(captured.scene.devices.len(), 3);
+    assert_eq!(captured.failed.len(), 1);
+    assert_eq!(captured.failed[0].0, gone);
+}
+
+#[test]
+fn restore_only_sends_what_changed() {
+    let room = room();
+    let devices = room.devices().to_vec();
+    let mut client = client();
+    let scene = Scene::capture(&mut client, &devices).scene;
+
+    client.send_acked(&devices[0], &set_color(RED)).unwrap();
+    client.send_acked(&devices[0], &DeviceRequest::SetLightPower { level: 65535, duration_ms: 0 }).unwrap();
+    client.send_acked(&devices[1], &set_zones(4, 7, BLUE)).unwrap();
+    client.send_acked(&devices[2], &set_tile(3, RED)).unwrap();
+
+    let restored = scene.restore(&mut client, &devices, Duration::ZERO);
+    assert!(restored.failed.is_empty() && restored.missing.is_empty());
+    // Colour and power on the bulb, one block of zones, one tile
+    assert_eq!(restored.sent, 4);
+    assert_eq!(Scene::capture(&mut client, &devices).scene, scene);
+
+    let strip = room.state(1);
+    let zones = strip.requests.iter().rev().find(|request| matches!(request, DeviceRequest::SetExtendedColorZones { .. }));
+    assert!(matches!(
+        zones,
+        Some(DeviceRequest::SetExtendedColorZones { zone_index: 0, colors_count: 16, apply: MULTIZONE_APPLY, .. })
+    ));
+
+    assert_eq!(scene.restore(&mut client, &devices, Duration::ZERO).sent, 0);
+}
+
+#[test]
+fn restores_zones_in_ranges_without_extended_multizone() {
+    // Strips only support the extended messages from firmware 2.77
+    let mut config = ProductKind::Strip.config(0);
+    config.firmware_major = 2;
+    config.firmware_minor = 70;
+    let strip = Simulator::start(vec![config], Faults::default(), 1).unwrap();
+    let device = strip.devices()[0];
+    let mut client = client();
+
+    // A zone of another colour between the changes keeps them in separate ranges
+    client.send_acked(&device, &set_zones(6, 6, BLUE)).unwrap();
+    let scene = Scene::capture(&mut client, &[device]).scene;
+    assert_eq!(scene.devices[0].zones.len(), 16);
+
+    client.send_acked(&device, &set_zones(2, 3, RED)).unwrap();
+    client.send_acked(&device, &set_zones(10, 10, BLUE)).unwrap();
+    let before = strip.state(0).requests.len();
+
+    assert_eq!(scene.restore(&mut client, &[device], Duration::ZERO).sent, 2);
+    assert_eq!(strip.state(0).zones, scene.devices[0].zones);
+
+    let sets: Vec<_> = strip.state(0).requests[before..]
+        .iter()
+        .filter_map(|request| match request {
+            DeviceRequest::SetColorZones { start_index, end_index, apply, .. } => Some((*start_index, *end_index, *apply)),
+            DeviceRequest::SetExtendedColorZones { .. } => panic!("extended message sent to old firmware"),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(sets, [(2, 3, MULTIZONE_NO_APPLY), (10, 10, MULTIZONE_APPLY)]);
+}
+
+#[test]
+fn reports_devices_missing_from_the_restore() {
+    let room = room();
+    let mut client = client();
+    let scene = Scene::capture(&mut client, room.devices()).scene;
+
+    let restored = scene.restore(&mut client, &room.devices()[1..], Duration::ZERO);
+    assert_eq!(restored.missing, [room.devices()[0].target]);
+    assert_eq!(restored.sent, 0);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn scenes_save_as_json_and_toml() {
+    use lifx_lan::scene::DeviceScene;
+
+    let scene = Scene {
+        devices: vec![
+            DeviceScene { target: [0xD0, 0x73, 0xD5, 0, 0, 1, 0, 0], power: 65535, color: RED, zones: vec![], tiles: vec![] },
+            DeviceScene { target: [0xD0, 0x73, 0xD5, 0, 0, 2, 0, 0], power: 0, color: BLUE, zones: vec![RED, BLUE], tiles: vec![] },
+            DeviceScene { target: [0xD0, 0x73, 0xD5, 0, 0, 3, 0, 0], power: 0, color: RED, zones: vec![], tiles: vec![vec![BLUE; 64]; 2] },
+        ],
+    };
+
+    let json = serde_json::to_string(&scene).unwrap();
+    assert!(json.contains(r#""target":"d073d50000010000""#));
+    assert_eq!(serde_json::from_str::<Scene>(&json).unwrap(), scene);
+
+    let toml = toml::to_string(&scene).unwrap();
+    assert_eq!(toml::from_str::<Scene>(&toml).unwrap(), scene);
+}
@@ -0,0 +1,224 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lifx_lan::{
+    client::LifxClient,
+    firmware_effects::{Direction, MultiZoneEffect, TileEffect},
+    scene::Scene,
+    schedule::{parse_duration, Action, Cron, Schedule, ScheduleError, Scheduler, Targets, When},
+    simulator::{Faults, ProductKind, Simulator},
+    Hsbk,
+};
+
+// A UTC date and time, from the days-from-civil algorithm
+fn utc(year: i64, month: i64, day: i64, hour: u64, minute: u64) -> SystemTime {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146097 + day_of_era - 719468) as u64;
+
+    UNIX_EPOCH + Duration::from_secs(days * 86400 + hour * 3600 + minute * 60)
+}
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(1).set_rate_limit(None);
+    client
+}
+
+const SCHEDULE: &str = "
+# Times are local, an hour ahead of UTC
+location 51.5074 -0.1278
+utc-offset +01:00
+
+wake     30 6 * * 1-5    d073d5100000               power on 10m
+evening  @sunset-30m     *               catch-up   scene evening 5m
+red      0 */2 * * *     d073d5100000,d073d5100001  color 0:65535:65535:3500 1s
+party    0 22 * * 6      *                          strip-effect move 2s left for 1h
+flames   0 23 * * 6      *                          tile-effect morph 1500ms 0:65535:65535:3500 21845:65535:65535:3500
+";
+
+#[test]
+fn finds_the_next_cron_match() {
+    let weekdays: Cron = "30 6 * * 1-5".parse().unwrap();
+    // Friday morning, after 06:30, so the next is Monday
+    assert_eq!(weekdays.next_after(utc(2024, 6, 21, 7, 0), 0), Some(utc(2024, 6, 24, 6, 30)));
+    assert_eq!(weekdays.next_after(utc(2024, 6, 21, 5, 0), 60), Some(utc(2024, 6, 21, 5, 30)));
+
+    let quarters: Cron = "*/15 * * * *".parse().unwrap();
+    assert_eq!(quarters.next_after(utc(2024, 6, 21, 10, 7), 0), Some(utc(2024, 6, 21, 10, 15)));
+    assert_eq!(quarters.next_after(utc(2024, 6, 21, 10, 15), 0), Some(utc(2024, 6, 21, 10, 30)));
+
+    let leap_day: Cron = "0 0 29 2 *".parse().unwrap();
+    assert_eq!(leap_day.next_after(utc(2024, 3, 1, 0, 0), 0), Some(utc(2028, 2, 29, 0, 0)));
+
+    // The first of the month or any Sunday
+    let either: Cron = "0 12 1 * 7".parse().unwrap();
+    assert_eq!(either.next_after(utc(2024, 6, 2, 13, 0), 0), Some(utc(2024, 6, 9, 12, 0)));
+    assert_eq!(either.next_after(utc(2024, 6, 30, 13, 0), 0), Some(utc(2024, 7, 1, 12, 0)));
+
+    assert_eq!("0 0 30 2 *".parse::<Cron>().unwrap().next_after(utc(2024, 1, 1, 0, 0), 0), None);
+    assert!("60 * * * *".parse::<Cron>().is_err());
+    assert!("* * *".parse::<Cron>().is_err());
+    assert!("*/0 * * * *".parse::<Cron>().is_err());
+
+    assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+    assert_eq!(parse_duration("1500ms"), Ok(Duration::from_millis(1500)));
+    assert!(parse_duration("soon").is_err());
+}
+
+#[test]
+fn reads_and_writes_schedule_files() {
+    let schedule: Schedule = SCHEDULE.parse().unwrap();
+    assert_eq!(schedule.utc_offset, 60);
+    assert_eq!(schedule.jobs.len(), 5);
+
+    let evening = schedule.job("evening").unwrap();
+    assert_eq!(evening.when, When::Sunset { offset: -1800 });
+    assert_eq!(evening.targets, Targets::All);
+    assert!(evening.catch_up);
+    assert_eq!(evening.action, Action::Scene { name: String::from("evening"), duration: Duration::from_secs(300) });
+
+    let red = schedule.job("red").unwrap();
+    assert_eq!(red.targets, Targets::Devices(vec![[0xD0, 0x73, 0xD5, 0x10, 0, 0, 0, 0], [0xD0, 0x73, 0xD5, 0x10, 0, 1, 0, 0]]));
+    assert_eq!(red.action, Action::Color { color: Hsbk::new(0, 65535, 65535, 3500), duration: Duration::from_secs(1) });
+
+    let party = schedule.job("party").unwrap();
+    let moving = MultiZoneEffect::Move { speed: Duration::from_secs(2), direction: Direction::Left, duration: Some(Duration::from_secs(3600)) };
+    assert_eq!(party.action, Action::MultiZoneEffect(moving));
+    assert!(matches!(&schedule.job("flames").unwrap().action, Action::TileEffect(TileEffect::Morph { palette, .. }) if palette.colors().len() == 2));
+
+    // Written out and read back, nothing is lost
+    let written = schedule.to_string();
+    assert!(written.contains("evening @sunset-30m * catch-up scene evening 5m"));
+    assert_eq!(written.parse::<Schedule>().unwrap(), schedule);
+
+    let path = std::env::temp_dir().join(format!("lifx_lan_{}_schedule.txt", std::process::id()));
+    schedule.save(&path).unwrap();
+    assert_eq!(Schedule::load(&path).unwrap(), schedule);
+    std::fs::remove_file(path).unwrap();
+
+    let error = |text: &str| match text.parse::<Schedule>() {
+        Err(ScheduleError::Parse { line, .. }) => line,
+        other => panic!("expected a parse error, got {:?}", other),
+    };
+    assert_eq!(error("\nlate @sunset * power off"), 2);
+    assert_eq!(error("a 0 * * * * * power on\na 1 * * * * * power off"), 2);
+    assert_eq!(error("a 0 * * * * * dance"), 1);
+    assert_eq!(error("a 0 * * * * d073 power on"), 1);
+}
+
+#[test]
+fn lists_upcoming_firings_for_a_dry_run() {
+    let schedule: Schedule = SCHEDULE.parse().unwrap();
+    // Friday 21 June 2024, 17:00 in local time
+    let upcoming = schedule.upcoming(utc(2024, 6, 21, 16, 0), 4);
+    let lines: Vec<String> = upcoming.iter().map(ToString::to_string).collect();
+
+    assert_eq!(lines[0], "2024-06-21 18:00:00 +01:00 red color 0:65535:65535:3500 1s");
+    assert_eq!(lines[1], "2024-06-21 20:00:00 +01:00 red color 0:65535:65535:3500 1s");
+    // London sunsets at about 21:21 local time at midsummer
+    assert!(lines[2].starts_with("2024-06-21 20:5") && lines[2].ends_with("evening scene evening 5m"), "{}", lines[2]);
+    assert_eq!(lines[3], "2024-06-21 22:00:00 +01:00 red color 0:65535:65535:3500 1s");
+    assert!(upcoming.windows(2).all(|pair| pair[0].at <= pair[1].at));
+}
+
+#[test]
+fn runs_jobs_when_due_and_handles_missed_ones() {
+    let simulator = Simulator::start(vec![ProductKind::Bulb.config(0), ProductKind::Bulb.config(1)], Faults::default(), 1).unwrap();
+    let devices = simulator.devices();
+    let mut client = client();
+
+    let schedule: Schedule = "
+        on      0 7 * * *   d073d5100000          power on
+        blue    0 8 * * *   *                     color 43690:65535:65535:3500
+        night   0 23 * * *  d073d5100001 catch-up power on
+        evening 0 18 * * *  *                     scene evening
+    "
+    .parse()
+    .unwrap();
+
+    let state_file = std::env::temp_dir().join(format!("lifx_lan_{}_schedule.state", std::process::id()));
+    let _ = std::fs::remove_file(&state_file);
+
+    let mut scheduler = Scheduler::new(schedule.clone());
+    scheduler.set_state_file(&state_file).unwrap();
+
+    // The first tick only starts the clock
+    assert!(scheduler.tick(&mut client, devices, utc(2024, 6, 21, 6, 59)).unwrap().ran.is_empty());
+
+    let tick = scheduler.tick(&mut client, devices, utc(2024, 6, 21, 7, 0)).unwrap();
+    assert_eq!(tick.ran.len(), 1);
+    assert_eq!(tick.ran[0].firing.job.name, "on");
+    assert!(!tick.ran[0].late && tick.ran[0].failed.is_empty());
+    assert_eq!(simulator.state(0).power, u16::MAX);
+    assert_eq!(simulator.state(1).power, 0);
+
+    // A dry run reports the job without sending it or saving the clock
+    scheduler.set_dry_run(true);
+    let tick = scheduler.tick(&mut client, devices, utc(2024, 6, 21, 8, 1)).unwrap();
+    assert_eq!(tick.ran[0].firing.job.name, "blue");
+    assert_eq!(simulator.state(0).color.hue, 0);
+    assert_eq!(scheduler.checked_until(), Some(utc(2024, 6, 21, 8, 1)));
+    assert!(scheduler.tick(&mut client, devices, utc(2024, 6, 21, 8, 2)).unwrap().ran.is_empty());
+    scheduler.set_dry_run(false);
+
+    // The scheduler goes down and comes back the next morning with the state file
+    let mut scheduler = Scheduler::new(schedule);
+    scheduler.set_state_file(&state_file).unwrap();
+    assert_eq!(scheduler.checked_until(), Some(utc(2024, 6, 21, 7, 0)));
+    let evening = Scene::capture(&mut client, devices).scene;
+    scheduler.add_scene("evening", evening);
+
+    let tick = scheduler.tick(&mut client, devices, utc(2024, 6, 22, 6, 0)).unwrap();
+    let missed: Vec<&str> = tick.missed.iter().map(|firing| firing.job.name.as_str()).collect();
+    assert_eq!(missed, ["blue", "evening"]);
+    assert_eq!(tick.ran.len(), 1);
+    assert_eq!(tick.ran[0].firing.job.name, "night");
+    assert_eq!(tick.ran[0].firing.at, utc(2024, 6, 21, 23, 0));
+    assert!(tick.ran[0].late);
+    assert_eq!(simulator.state(1).power, u16::MAX);
+
+    let saved = std::fs::read_to_string(&state_file).unwrap();
+    assert_eq!(saved.trim().parse::<u64>().unwrap(), utc(2024, 6, 22, 6, 0).duration_since(UNIX_EPOCH).unwrap().as_secs());
+    std::fs::remove_file(&state_file).unwrap();
+
+    // Scenes are restored by name, and an unknown one is reported
+    let mut scheduler = Scheduler::new("evening 0 18 * * * * scene evening\nother 0 18 * * * * scene other".parse().unwrap());
+    scheduler.add_scene("evening", Scene::capture(&mut client, devices).scene);
+    client.send_acked(&devices[0], &lifx_lan::DeviceRequest::SetLightPower { level: 0, duration_ms: 0 }).unwrap();
+
+    scheduler.tick(&mut client, devices, utc(2024, 6, 22, 17, 0)).unwrap();
+    let tick = scheduler.tick(&mut client, devices, utc(2024, 6, 22, 18, 0)).unwrap();
+    assert_eq!(tick.ran.len(), 2);
+    assert_eq!(tick.unknown_scenes, ["other"]);
+    assert_eq!(simulator.state(0).power, u16::MAX);
+}
+
+#[test]
+fn dry_runs_report_each_firing_once() {
+    let mut client = client();
+    let state_file = std::env::temp_dir().join(format!("lifx_lan_{}_dry_run.state", std::process::id()));
+    let started = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() - 90;
+    std::fs::write(&state_file, format!("{}\n", started)).unwrap();
+
+    let mut scheduler = Scheduler::new("minutely * * * * * * power on".parse().unwrap());
+    scheduler.set_state_file(&state_file).unwrap().set_dry_run(true);
+
+    let mut ticks: Vec<Vec<SystemTime>> = Vec::new();
+    scheduler.run(&mut client, &[], Duration::from_millis(200), |tick| ticks.push(tick.ran.iter().map(|run| run.firing.at).collect())).unwrap();
+
+    assert!(ticks.len() >= 2);
+    assert_eq!(ticks[0].len(), 1);
+    // A minute may turn over during the run, but no firing comes round again
+    let mut firings = ticks.concat();
+    let reported = firings.len();
+    firings.dedup();
+    assert_eq!(firings.len(), reported);
+
+    assert_eq!(std::fs::read_to_string(&state_file).unwrap().trim().parse::<u64>().unwrap(), started);
+    std::fs::remove_file(&state_file).unwrap();
+}
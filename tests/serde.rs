@@ -0,0 +1,99 @@
+#![cfg(feature = "serde")]
+
+use lifx_lan::{deserialize_lifx_packet, serialize_lifx_packet, LifxHeader, LifxRequestOptions, Message};
+use serde_json::json;
+
+fn label<T: TryFrom<&'static str>>(value: &'static str) -> T
+where
+    T::Error: core::fmt::Debug,
+{
+    value.try_into().unwrap()
+}
+
+fn payload<T: TryFrom<&'static [u8]>>(value: &'static [u8]) -> T
+where
+    T::Error: core::fmt::Debug,
+{
+    value.try_into().unwrap()
+}
+
+#[test]
+fn messages_are_tagged_by_variant_name_and_packet_number() {
+    let message = Message::SetColor { reserved_6: 0, hue: 1, saturation: 2, brightness: 3, kelvin: 3500, duration_ms: 100 };
+    let value = serde_json::to_value(&message).unwrap();
+
+    assert_eq!(
+        value,
+        json!({ "SetColor": { "packet_number": 102, "reserved_6": 0, "hue": 1, "saturation": 2, "brightness": 3, "kelvin": 3500, "duration_ms": 100 } })
+    );
+    assert_eq!(serde_json::from_value::<Message>(value).unwrap(), message);
+
+    let value = serde_json::to_value(Message::GetColor).unwrap();
+    assert_eq!(value, json!({ "GetColor": { "packet_number": 101 } }));
+    assert_eq!(serde_json::from_value::<Message>(value).unwrap(), Message::GetColor);
+
+    // Hand-written messages can leave the packet number out
+    assert_eq!(serde_json::from_value::<Message>(json!({ "GetColor": null })).unwrap(), Message::GetColor);
+    assert_eq!(serde_json::from_value::<Message>(json!({ "SetPower": { "level": 1 } })).unwrap(), Message::SetPower { level: 1 });
+}
+
+#[test]
+fn packet_numbers_are_checked_when_read() {
+    let error = serde_json::from_value::<Message>(json!({ "SetPower": { "packet_number": 22, "level": 1 } })).unwrap_err();
+    assert_eq!(error.to_string(), "SetPower is packet number 21, not 22");
+    assert!(serde_json::from_value::<Message>(json!({ "GetColor": { "packet_number": 102 } })).is_err());
+    assert!(serde_json::from_value::<Message>(json!({ "Dance": {} })).is_err());
+
+    // An unknown packet keeps the number it came with
+    let unknown = serde_json::from_value::<Message>(json!({ "Unknown": { "packet_number": 9000, "payload": "0102" } })).unwrap();
+    assert_eq!(serde_json::to_value(&unknown).unwrap(), json!({ "Unknown": { "packet_number": 9000, "payload": "0102" } }));
+}
+
+#[test]
+fn messages_round_trip_through_compact_formats() {
+    let messages = [
+        Message::GetColor,
+        Message::SetPower { level: 65535 },
+        Message::SetColor { reserved_6: 0, hue: 1, saturation: 2, brightness: 3, kelvin: 3500, duration_ms: 100 },
+        Message::Group { group: [0xAB; 16], label: label("Kitchen"), updated_at: 7 },
+        Message::Unknown { packet_number: 9000, payload: payload(&[1, 2]) },
+    ];
+
+    for message in messages {
+        let bytes = bincode::serialize(&message).unwrap();
+        assert_eq!(bincode::deserialize::<Message>(&bytes).unwrap(), message);
+    }
+
+    // Just the variant index, with no packet number field
+    assert_eq!(bincode::serialize(&Message::GetColor).unwrap().len(), 4);
+}
+
+#[test]
+fn labels_are_strings_and_byte_arrays_are_hex() {
+    let message = Message::Group { group: [0xAB; 16], label: label("Kitchen"), updated_at: 7 };
+    let value = serde_json::to_value(&message).unwrap();
+
+    assert_eq!(
+        value,
+        json!({ "Group": { "packet_number": 53, "group": "abababababababababababababababab", "label": "Kitchen", "updated_at": 7 } })
+    );
+    assert_eq!(serde_json::from_value::<Message>(value).unwrap(), message);
+}
+
+#[test]
+fn captured_packets_replay_from_json() {
+    let options = LifxRequestOptions { target: [0xD0, 0x73, 0xD5, 1, 2, 3, 0, 0], sequence: 9, ..Default::default() };
+    let options: LifxRequestOptions = serde_json::from_str(&serde_json::to_string(&options).unwrap()).unwrap();
+    assert_eq!(serde_json::to_value(&options).unwrap()["target"], "d073d50102030000");
+
+    let mut buffer = [0u8; 128];
+    serialize_lifx_packet(&options, &Message::SetPower { level: 65535 }, &mut buffer);
+    let (header, message) = deserialize_lifx_packet(&buffer).unwrap();
+
+    let logged = serde_json::to_string(&(&header, &message)).unwrap();
+    let (replayed_header, replayed_message): (LifxHeader, Message) = serde_json::from_str(&logged).unwrap();
+
+    assert_eq!(replayed_header.packet_number, 21);
+    assert_eq!(replayed_header.target, options.target);
+    assert_eq!(replayed_message, message);
+}
@@ -0,0 +1,121 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::{collections::HashSet, time::Duration};
+
+use lifx_lan::{
+    client::{ClientError, LifxClient},
+    messages::responses,
+    simulator::{Faults, ProductKind, Simulator},
+    DeviceRequest,
+};
+
+fn client(retries: u8) -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(100)).set_retries(retries);
+    client
+}
+
+#[test]
+fn discovers_a_whole_house() {
+    let simulator = Simulator::house(50, Faults::default(), 1).unwrap();
+
+    let found: HashSet<_> = client(0).discover(simulator.broadcast_address()).unwrap().into_iter().collect();
+    let expected: HashSet<_> = simulator.devices().iter().copied().collect();
+
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn discovery_retries_through_packet_loss() {
+    let faults = Faults { loss: 0.1, jitter: Duration::from_millis(5), ..Default::default() };
+    let simulator = Simulator::house(50, faults, 7).unwrap();
+
+    let found = client(3).discover(simulator.broadcast_address()).unwrap();
+
+    assert_eq!(found.len(), 50);
+}
+
+#[test]
+fn requests_retry_until_a_reply_gets_through() {
+    let simulator = Simulator::house(1, Faults { loss: 0.2, ..Default::default() }, 4).unwrap();
+    let device = simulator.devices()[0];
+    let mut client = client(5);
+
+    for _ in 0..10 {
+        let label: responses::Label = client.request(&device, &DeviceRequest::GetLabel).unwrap();
+        assert_eq!(label.label, "Bulb 1");
+    }
+
+    // Some of those requests or their replies were lost and had to be sent again
+    assert!(simulator.arrivals(0).len() > 10);
+}
+
+#[test]
+fn stale_duplicate_and_reordered_replies_are_ignored() {
+    let faults = Faults { duplicate: 1.0, reorder: 0.5, jitter: Duration::from_millis(10), ..Default::default() };
+    let simulator = Simulator::house(10, faults, 11).unwrap();
+    let mut client = client(2);
+    client.set_rate_limit(None);
+
+    for _ in 0..3 {
+        for (index, device) in simulator.devices().iter().enumerate() {
+            let version: responses::Version = client.request(device, &DeviceRequest::GetVersion).unwrap();
+            assert_eq!(version.vendor, 1);
+
+            let label: responses::Label = client.request(device, &DeviceRequest::GetLabel).unwrap();
+            assert!(label.label.ends_with(&format!(" {}", index + 1)), "{} answered as {}", index, label.label);
+        }
+    }
+}
+
+#[test]
+fn offline_devices_drop_out() {
+    let simulator = Simulator::house(20, Faults::default(), 5).unwrap();
+    simulator.set_online(3, false);
+
+    let mut client = client(1);
+    let found = client.discover(simulator.broadcast_address()).unwrap();
+    assert_eq!(found.len(), 19);
+    assert!(!found.contains(&simulator.devices()[3]));
+
+    let offline = simulator.devices()[3];
+    assert!(matches!(client.request_message(&offline, &DeviceRequest::GetLabel), Err(ClientError::Timeout)));
+
+    simulator.set_online(3, true);
+    assert!(client.request_message(&offline, &DeviceRequest::GetLabel).is_ok());
+}
+
+#[test]
+fn switches_have_no_light() {
+    let simulator = Simulator::house(10, Faults::default(), 9).unwrap();
+    let switch = simulator.devices()[8];
+    let mut client = client(0);
+
+    let version: responses::Version = client.request(&switch, &DeviceRequest::GetVersion).unwrap();
+    assert_eq!((version.vendor, version.product), ProductKind::Switch.ids());
+
+    assert!(matches!(client.request_message(&switch, &DeviceRequest::GetColor), Err(ClientError::Unsupported(_))));
+
+    client.set_validation(false);
+    assert!(matches!(client.request_message(&switch, &DeviceRequest::GetColor), Err(ClientError::Unhandled(101))));
+}
+
+#[test]
+fn packets_to_one_device_are_rate_limited() {
+    let simulator = Simulator::house(1, Faults::default(), 2).unwrap();
+    let device = simulator.devices()[0];
+    let mut client = client(0);
+
+    for level in [0, 65535, 0, 65535, 0] {
+        client.send(&device, &DeviceRequest::SetPower { level }).unwrap();
+    }
+
+    std::thread::sleep(Duration::from_millis(50));
+    let arrivals = simulator.arrivals(0);
+    assert_eq!(arrivals.len(), 5);
+
+    // The default limit of 20 messages a second spaces packets 50ms apart, less a little for polling
+    for pair in arrivals.windows(2) {
+        assert!(pair[1] - pair[0] >= Duration::from_millis(40), "{:?}", pair[1] - pair[0]);
+    }
+}
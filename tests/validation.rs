@@ -0,0 +1,116 @@
+#![cfg(not(feature = "no-std"))]
+
+use std::time::Duration;
+
+use lifx_lan::{
+    client::{ClientError, LifxClient},
+    emulator::{Emulator, EmulatorConfig},
+    products::{product_info, Capability, TemperatureRange, Unsupported},
+    DeviceRequest,
+};
+
+fn client() -> LifxClient {
+    let mut client = LifxClient::bind("127.0.0.1:0").unwrap();
+    client.set_timeout(Duration::from_millis(200)).set_retries(1);
+    client
+}
+
+fn set_color(saturation: u16, kelvin: u16) -> DeviceRequest {
+    DeviceRequest::SetColor { reserved_6: 0, hue: 0, saturation, brightness: 65535, kelvin, duration_ms: 0 }
+}
+
+#[test]
+fn rejects_messages_the_product_cannot_carry_out() {
+    // A LIFX A19, which has no infrared
+    let emulator = Emulator::start(EmulatorConfig::default()).unwrap();
+    let mut client = client();
+
+    match client.send_acked(&emulator.device(), &DeviceRequest::SetInfrared { brightness: 65535 }) {
+        Err(ClientError::Unsupported(Unsupported::Missing(Capability::Infrared))) => {}
+        other => panic!("Expected the message to be rejected, got {:?}", other),
+    }
+
+    // Only the lookups went out
+    assert_eq!(emulator.state().requests, vec![DeviceRequest::GetVersion, DeviceRequest::GetHostFirmware]);
+
+    client.send_acked(&emulator.device(), &set_color(65535, 3500)).unwrap();
+}
+
+#[test]
+fn rejects_kelvin_outside_the_temperature_range() {
+    // LIFX White 800, which covers 2700K to 6500K
+    let emulator = Emulator::start(EmulatorConfig { product: 10, ..Default::default() }).unwrap();
+    let mut client = client();
+
+    match client.send_acked(&emulator.device(), &set_color(0, 9000)) {
+        Err(ClientError::Unsupported(Unsupported::Kelvin { kelvin: 9000, range })) => {
+            assert_eq!(range, TemperatureRange { min: 2700, max: 6500 })
+        }
+        other => panic!("Expected the message to be rejected, got {:?}", other),
+    }
+
+    assert!(matches!(
+        client.send_acked(&emulator.device(), &set_color(65535, 4000)),
+        Err(ClientError::Unsupported(Unsupported::Missing(Capability::Color)))
+    ));
+
+    client.send_acked(&emulator.device(), &set_color(0, 4000)).unwrap();
+}
+
+#[test]
+fn ignores_kelvin_for_fully_saturated_colors() {
+    let emulator = Emulator::start(EmulatorConfig::default()).unwrap();
+    let mut client = client();
+
+    client.send_acked(&emulator.device(), &set_color(65535, 0)).unwrap();
+    // A LIFX Z
+    let strip = product_info(1, 32, (3, 0)).unwrap().features;
+    let zones = DeviceRequest::SetColorZones { start_index: 0, end_index: 7, hue: 0, saturation: 65535, brightness: 65535, kelvin: 0, duration_ms: 0, apply: 1 };
+    assert_eq!(strip.check(&zones), Ok(()));
+
+    // Any white left in the colour still needs a kelvin the light can do
+    assert!(matches!(
+        client.send_acked(&emulator.device(), &set_color(65534, 0)),
+        Err(ClientError::Unsupported(Unsupported::Kelvin { kelvin: 0, .. }))
+    ));
+}
+
+#[test]
+fn firmware_upgrades_are_taken_into_account() {
+    // LIFX A19 firmware 2.80 widened the range down to 1500K
+    let old = Emulator::start(EmulatorConfig { firmware_major: 2, firmware_minor: 70, ..Default::default() }).unwrap();
+    let new = Emulator::start(EmulatorConfig {
+        target: [0xD0, 0x73, 0xD5, 0x00, 0x00, 0x02, 0, 0],
+        firmware_major: 2,
+        firmware_minor: 80,
+        ..Default::default()
+    })
+    .unwrap();
+    let mut client = client();
+
+    assert!(matches!(client.send_acked(&old.device(), &set_color(0, 2000)), Err(ClientError::Unsupported(_))));
+    client.send_acked(&new.device(), &set_color(0, 2000)).unwrap();
+}
+
+#[test]
+fn validation_can_be_turned_off() {
+    let emulator = Emulator::start(EmulatorConfig::default()).unwrap();
+    let mut client = client();
+    client.set_validation(false);
+
+    // The emulator doesn't do infrared either, but now the device gets to say so
+    assert!(matches!(
+        client.request_message(&emulator.device(), &DeviceRequest::SetInfrared { brightness: 65535 }),
+        Err(ClientError::Unhandled(122))
+    ));
+    assert_eq!(emulator.state().requests, vec![DeviceRequest::SetInfrared { brightness: 65535 }]);
+}
+
+#[test]
+fn unknown_products_are_not_checked() {
+    let emulator = Emulator::start(EmulatorConfig { vendor: 99, ..Default::default() }).unwrap();
+    let mut client = client();
+
+    assert_eq!(client.product(&emulator.device()).unwrap(), None);
+    client.send_acked(&emulator.device(), &set_color(0, 12000)).unwrap();
+}